@@ -0,0 +1,49 @@
+//! Typed SOL amounts
+use {
+    solana_sdk::native_token::{lamports_to_sol, Sol, LAMPORTS_PER_SOL},
+    std::fmt,
+};
+
+/// An exact amount of lamports, convertible to SOL. Used for stake so consumers don't have to
+/// guess whether a bare integer is lamports or whole SOL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lamports(pub u64);
+
+impl Lamports {
+    pub fn lamports(self) -> u64 {
+        self.0
+    }
+
+    pub fn sol(self) -> f64 {
+        lamports_to_sol(self.0)
+    }
+
+    /// The amount in SOL, rounded down
+    pub fn whole_sol(self) -> u64 {
+        self.0 / LAMPORTS_PER_SOL
+    }
+}
+
+impl From<u64> for Lamports {
+    fn from(lamports: u64) -> Self {
+        Self(lamports)
+    }
+}
+
+impl From<Lamports> for u64 {
+    fn from(lamports: Lamports) -> Self {
+        lamports.0
+    }
+}
+
+impl From<Lamports> for u128 {
+    fn from(lamports: Lamports) -> Self {
+        u128::from(lamports.0)
+    }
+}
+
+impl fmt::Display for Lamports {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Sol(self.0))
+    }
+}
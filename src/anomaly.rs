@@ -0,0 +1,180 @@
+//! Detection of drops in a validator's credit accrual rate relative to its own baseline
+use {crate::check::CheckStatus, std::fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnomalyEvent {
+    /// `rate` fell more than the configured number of standard deviations below `baseline`
+    Dropped {
+        rate: f64,
+        baseline: f64,
+        z_score: f64,
+    },
+    /// `rate` is back within the configured number of standard deviations of `baseline`
+    Recovered { rate: f64, baseline: f64 },
+}
+
+impl AnomalyEvent {
+    pub fn severity(&self) -> CheckStatus {
+        match self {
+            AnomalyEvent::Dropped { .. } => CheckStatus::Warning,
+            AnomalyEvent::Recovered { .. } => CheckStatus::Ok,
+        }
+    }
+}
+
+impl fmt::Display for AnomalyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnomalyEvent::Dropped {
+                rate,
+                baseline,
+                z_score,
+            } => write!(
+                f,
+                "Credit rate dropped to {:.3} credits/slot, baseline {:.3} (z-score {:.1})",
+                rate, baseline, z_score
+            ),
+            AnomalyEvent::Recovered { rate, baseline } => write!(
+                f,
+                "Credit rate recovered to {:.3} credits/slot, baseline {:.3}",
+                rate, baseline
+            ),
+        }
+    }
+}
+
+/// Smallest standard deviation assumed, relative to the average. Without it a perfectly stable
+/// rate has no variance and no drop, however large, would stand out
+const MIN_RELATIVE_STD_DEV: f64 = 0.01;
+
+/// Tracks an exponentially weighted moving average and variance of a rate and reports when a
+/// sample falls `z_threshold` standard deviations below the average.
+///
+/// The average and variance are frozen while the rate is anomalous, so a sustained drop is not
+/// absorbed into the baseline
+#[derive(Debug, Clone, PartialEq)]
+pub struct EwmaAnomalyDetector {
+    /// Weight of each new sample, from 0.0 to 1.0
+    alpha: f64,
+    z_threshold: f64,
+    /// Samples to observe before reporting anomalies
+    warmup_samples: usize,
+    mean: f64,
+    variance: f64,
+    samples: usize,
+    anomalous: bool,
+}
+
+impl EwmaAnomalyDetector {
+    pub fn new(alpha: f64, z_threshold: f64, warmup_samples: usize) -> Self {
+        Self {
+            alpha: alpha.clamp(f64::EPSILON, 1.),
+            z_threshold,
+            warmup_samples,
+            mean: 0.,
+            variance: 0.,
+            samples: 0,
+            anomalous: false,
+        }
+    }
+
+    pub fn baseline(&self) -> f64 {
+        self.mean
+    }
+
+    /// Records a sample of the rate. An event is returned only when the anomaly state changes
+    pub fn observe(&mut self, rate: f64) -> Option<AnomalyEvent> {
+        let baseline = self.mean;
+        let std_dev = self
+            .variance
+            .sqrt()
+            .max(baseline.abs() * MIN_RELATIVE_STD_DEV);
+        let z_score = if std_dev > 0. {
+            (rate - baseline) / std_dev
+        } else {
+            0.
+        };
+
+        let event = if self.samples < self.warmup_samples {
+            None
+        } else if !self.anomalous && z_score < -self.z_threshold {
+            self.anomalous = true;
+            Some(AnomalyEvent::Dropped {
+                rate,
+                baseline,
+                z_score,
+            })
+        } else if self.anomalous && z_score >= -self.z_threshold {
+            self.anomalous = false;
+            Some(AnomalyEvent::Recovered { rate, baseline })
+        } else {
+            None
+        };
+
+        if self.anomalous {
+            return event;
+        }
+        if self.samples == 0 {
+            self.mean = rate;
+        } else {
+            let diff = rate - self.mean;
+            self.mean += self.alpha * diff;
+            self.variance = (1. - self.alpha) * (self.variance + self.alpha * diff * diff);
+        }
+        self.samples += 1;
+
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warmed_up(rates: &[f64]) -> EwmaAnomalyDetector {
+        let mut detector = EwmaAnomalyDetector::new(0.1, 3., rates.len());
+        for rate in rates {
+            assert_eq!(detector.observe(*rate), None);
+        }
+        detector
+    }
+
+    #[test]
+    fn test_stable_rate_drop() {
+        let mut detector = warmed_up(&[1.; 10]);
+        assert_eq!(detector.observe(0.99), None);
+        assert!(matches!(
+            detector.observe(0.),
+            Some(AnomalyEvent::Dropped { rate, baseline, .. }) if rate == 0. && baseline > 0.99
+        ));
+    }
+
+    #[test]
+    fn test_sustained_drop() {
+        let mut detector = warmed_up(&[1., 0.9, 1.1, 1., 0.95, 1.05, 1., 0.9, 1.1, 1.]);
+        let baseline = detector.baseline();
+        assert!(matches!(
+            detector.observe(0.2),
+            Some(AnomalyEvent::Dropped { .. })
+        ));
+        // The baseline does not follow the rate down, so the drop is not reported as a recovery
+        for _ in 0..100 {
+            assert_eq!(detector.observe(0.2), None);
+        }
+        assert_eq!(detector.baseline(), baseline);
+
+        assert_eq!(
+            detector.observe(1.),
+            Some(AnomalyEvent::Recovered { rate: 1., baseline })
+        );
+        assert_eq!(detector.observe(1.), None);
+    }
+
+    #[test]
+    fn test_warmup() {
+        let mut detector = EwmaAnomalyDetector::new(0.1, 3., 3);
+        assert_eq!(detector.observe(1.), None);
+        assert_eq!(detector.observe(0.), None);
+        assert_eq!(detector.observe(1.), None);
+    }
+}
@@ -0,0 +1,461 @@
+//! Detached ed25519 attestations over score snapshots
+use {
+    crate::error::Error,
+    serde_json::{json, Value},
+    solana_sdk::{clock::Epoch, pubkey::Pubkey, signature::Signature, signer::Signer},
+    std::{collections::BTreeMap, fmt::Write},
+};
+
+/// Serializes `value` as compact JSON with object keys sorted, so that the same snapshot always
+/// produces the same bytes regardless of how it was constructed or parsed
+pub fn canonical_json(value: &Value) -> String {
+    let mut json = String::new();
+    write_canonical_json(&mut json, value);
+    json
+}
+
+fn write_canonical_json(json: &mut String, value: &Value) {
+    match value {
+        Value::Array(values) => {
+            json.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_canonical_json(json, value);
+            }
+            json.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys = map.keys().collect::<Vec<_>>();
+            keys.sort();
+
+            json.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                let _ = write!(json, "{}:", Value::String(key.clone()));
+                write_canonical_json(json, &map[key]);
+            }
+            json.push('}');
+        }
+        _ => {
+            let _ = write!(json, "{}", value);
+        }
+    }
+}
+
+/// A detached signature over the canonical JSON serialization of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attestation {
+    pub signer: Pubkey,
+    pub signature: Signature,
+}
+
+impl Attestation {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "signer": self.signer.to_string(),
+            "signature": self.signature.to_string(),
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(|field| field.as_str())
+                .ok_or_else(|| format!("Attestation `{}` missing", name))
+        };
+
+        Ok(Self {
+            signer: field("signer")?
+                .parse()
+                .map_err(|err| format!("Invalid attestation signer: {:?}", err))?,
+            signature: field("signature")?
+                .parse()
+                .map_err(|err| format!("Invalid attestation signature: {:?}", err))?,
+        })
+    }
+
+    /// Returns true if this attestation is a valid signature of `snapshot` by `self.signer`
+    pub fn verify(&self, snapshot: &Value) -> bool {
+        self.signature
+            .verify(self.signer.as_ref(), canonical_json(snapshot).as_bytes())
+    }
+}
+
+/// Signs `snapshot` with `signer`. Signers that can't sign arbitrary messages, such as hardware
+/// wallets, fail with `Error::Config`
+pub fn attest(snapshot: &Value, signer: &dyn Signer) -> Result<Attestation, Error> {
+    Ok(Attestation {
+        signer: signer.pubkey(),
+        signature: signer
+            .try_sign_message(canonical_json(snapshot).as_bytes())
+            .map_err(|err| Error::Config(format!("Unable to sign snapshot: {}", err)))?,
+    })
+}
+
+/// An endorsement of a new signing key by the key it replaces, so consumers that trust the
+/// previous key can follow a rotation without being reconfigured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRotation {
+    pub previous_signer: Pubkey,
+    /// First epoch signed by the new signer. Consumers order competing rotations by it
+    pub epoch: Epoch,
+    /// Signature by `previous_signer` of `key_rotation_message` for the new signer
+    pub signature: Signature,
+}
+
+fn key_rotation_message(previous_signer: &Pubkey, new_signer: &Pubkey, epoch: Epoch) -> String {
+    format!(
+        "solana-credit-score signing key {} rotated to {} from epoch {}",
+        previous_signer, new_signer, epoch
+    )
+}
+
+impl KeyRotation {
+    /// Endorses `new_signer` from `epoch` on with the `previous_signer` being rotated out,
+    /// failing with `Error::Config` if `previous_signer` can't sign
+    pub fn new(
+        previous_signer: &dyn Signer,
+        new_signer: &Pubkey,
+        epoch: Epoch,
+    ) -> Result<Self, Error> {
+        let message = key_rotation_message(&previous_signer.pubkey(), new_signer, epoch);
+        Ok(Self {
+            previous_signer: previous_signer.pubkey(),
+            epoch,
+            signature: previous_signer
+                .try_sign_message(message.as_bytes())
+                .map_err(|err| Error::Config(format!("Unable to endorse key rotation: {}", err)))?,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "previousSigner": self.previous_signer.to_string(),
+            "epoch": self.epoch,
+            "signature": self.signature.to_string(),
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(|field| field.as_str())
+                .ok_or_else(|| format!("Key rotation `{}` missing", name))
+        };
+
+        Ok(Self {
+            previous_signer: field("previousSigner")?
+                .parse()
+                .map_err(|err| format!("Invalid key rotation signer: {:?}", err))?,
+            epoch: value
+                .get("epoch")
+                .and_then(|epoch| epoch.as_u64())
+                .ok_or("Key rotation `epoch` missing")?,
+            signature: field("signature")?
+                .parse()
+                .map_err(|err| format!("Invalid key rotation signature: {:?}", err))?,
+        })
+    }
+
+    /// Returns true if this is a valid endorsement of `new_signer` by `self.previous_signer`
+    pub fn verify(&self, new_signer: &Pubkey) -> bool {
+        self.signature.verify(
+            self.previous_signer.as_ref(),
+            key_rotation_message(&self.previous_signer, new_signer, self.epoch).as_bytes(),
+        )
+    }
+}
+
+/// The signers a consumer of signed snapshots trusts, following the key rotations they endorse.
+///
+/// A rotated out signer is no longer trusted, so a rotation can't be replayed to endorse another
+/// key, and a rotation older than the one that made its signer trusted is rejected. Persist the
+/// state with `to_json` to keep following rotations across runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedSigners {
+    /// Each trusted signer, with the epoch of the rotation that made it trusted if any
+    signers: BTreeMap<Pubkey, Option<Epoch>>,
+    /// Each rotated out signer, with the epoch of its rotation
+    retired: BTreeMap<Pubkey, Epoch>,
+}
+
+impl TrustedSigners {
+    pub fn new(signers: &[Pubkey]) -> Self {
+        Self {
+            signers: signers.iter().map(|signer| (*signer, None)).collect(),
+            retired: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_trusted(&self, signer: &Pubkey) -> bool {
+        self.signers.contains_key(signer)
+    }
+
+    /// Trusts `new_signer` in place of the signer of `key_rotation`
+    pub fn accept_rotation(
+        &mut self,
+        key_rotation: &KeyRotation,
+        new_signer: &Pubkey,
+    ) -> Result<(), String> {
+        let previous_signer = &key_rotation.previous_signer;
+        let trusted_since = match (
+            self.signers.get(previous_signer),
+            self.retired.get(previous_signer),
+        ) {
+            (Some(trusted_since), _) => *trusted_since,
+            (None, Some(epoch)) => {
+                return Err(format!(
+                    "{} was rotated out in epoch {}",
+                    previous_signer, epoch
+                ))
+            }
+            (None, None) => return Err(format!("Untrusted signer: {}", previous_signer)),
+        };
+        if let Some(trusted_since) = trusted_since.filter(|epoch| key_rotation.epoch < *epoch) {
+            return Err(format!(
+                "Key rotation of {} in epoch {} predates its own rotation in epoch {}",
+                previous_signer, key_rotation.epoch, trusted_since
+            ));
+        }
+        if !key_rotation.verify(new_signer) {
+            return Err(format!("Invalid key rotation from {}", previous_signer));
+        }
+
+        self.signers.remove(previous_signer);
+        self.retired.insert(*previous_signer, key_rotation.epoch);
+        self.signers.insert(*new_signer, Some(key_rotation.epoch));
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "signers": self.signers.iter().map(|(signer, epoch)| json!({
+                "pubkey": signer.to_string(),
+                "epoch": epoch,
+            })).collect::<Vec<_>>(),
+            "retired": self.retired.iter().map(|(signer, epoch)| json!({
+                "pubkey": signer.to_string(),
+                "epoch": epoch,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let entries = |name: &str| {
+            value
+                .get(name)
+                .and_then(|entries| entries.as_array())
+                .ok_or_else(|| format!("Trusted signers `{}` missing", name))?
+                .iter()
+                .map(|entry| {
+                    let signer = entry
+                        .get("pubkey")
+                        .and_then(|signer| signer.as_str())
+                        .and_then(|signer| signer.parse::<Pubkey>().ok())
+                        .ok_or_else(|| format!("Invalid trusted signer: {}", entry))?;
+                    Ok((signer, entry.get("epoch").and_then(|epoch| epoch.as_u64())))
+                })
+                .collect::<Result<Vec<_>, String>>()
+        };
+
+        Ok(Self {
+            signers: entries("signers")?.into_iter().collect(),
+            retired: entries("retired")?
+                .into_iter()
+                .map(|(signer, epoch)| {
+                    epoch
+                        .map(|epoch| (signer, epoch))
+                        .ok_or_else(|| format!("Retired signer {} has no `epoch`", signer))
+                })
+                .collect::<Result<_, String>>()?,
+        })
+    }
+}
+
+/// Adds `key_rotation` to the output of `sign_snapshot`, as its `rotation`
+pub fn add_key_rotation(signed_snapshot: &mut Value, key_rotation: &KeyRotation) {
+    signed_snapshot["rotation"] = key_rotation.to_json();
+}
+
+/// Wraps `snapshot` together with its attestation by `signer`:
+/// `{"feed": <snapshot>, "signer": <pubkey>, "signature": <signature>}`
+pub fn sign_snapshot(snapshot: Value, signer: &dyn Signer) -> Result<Value, Error> {
+    let attestation = attest(&snapshot, signer)?;
+    let mut signed_snapshot = attestation.to_json();
+    signed_snapshot["feed"] = snapshot;
+    Ok(signed_snapshot)
+}
+
+/// Verifies the output of `sign_snapshot`, returning the snapshot and its attestation.
+///
+/// If `trusted_signers` is provided the attestation must also be from one of them, or carry a
+/// `rotation` from one of them to the attestation's signer that `trusted_signers` accepts
+pub fn verify_signed_snapshot(
+    signed_snapshot: &Value,
+    trusted_signers: Option<&mut TrustedSigners>,
+) -> Result<(Value, Attestation), String> {
+    let snapshot = signed_snapshot
+        .get("feed")
+        .ok_or("Signed snapshot has no `feed`")?;
+    let attestation = Attestation::from_json(signed_snapshot)?;
+    if !attestation.verify(snapshot) {
+        return Err(format!("Invalid signature from {}", attestation.signer));
+    }
+
+    if let Some(trusted_signers) = trusted_signers {
+        if !trusted_signers.is_trusted(&attestation.signer) {
+            let key_rotation = signed_snapshot
+                .get("rotation")
+                .map(KeyRotation::from_json)
+                .transpose()?
+                .ok_or_else(|| format!("Untrusted signer: {}", attestation.signer))?;
+            trusted_signers.accept_rotation(&key_rotation, &attestation.signer)?;
+        }
+    }
+    Ok((snapshot.clone(), attestation))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::signature::Keypair};
+
+    #[test]
+    fn test_canonical_json() {
+        let value = json!({"b": [1, {"d": null, "c": "x"}], "a": true});
+        assert_eq!(
+            canonical_json(&value),
+            r#"{"a":true,"b":[1,{"c":"x","d":null}]}"#
+        );
+        let parsed = serde_json::from_str::<Value>(&canonical_json(&value)).unwrap();
+        assert_eq!(parsed, value);
+        assert_eq!(canonical_json(&parsed), canonical_json(&value));
+    }
+
+    #[test]
+    fn test_attestation() {
+        let signer = Keypair::new();
+        let snapshot = json!({"epoch": 7, "entries": []});
+        let attestation = attest(&snapshot, &signer).unwrap();
+        assert!(attestation.verify(&snapshot));
+        // Key order doesn't matter
+        assert!(attestation.verify(&json!({"entries": [], "epoch": 7})));
+        assert!(!attestation.verify(&json!({"epoch": 8, "entries": []})));
+        assert_eq!(
+            Attestation::from_json(&attestation.to_json()).unwrap(),
+            attestation
+        );
+    }
+
+    #[test]
+    fn test_verify_signed_snapshot() {
+        let signer = Keypair::new();
+        let snapshot = json!({"epoch": 7});
+        let signed_snapshot = sign_snapshot(snapshot.clone(), &signer).unwrap();
+
+        let (verified_snapshot, attestation) =
+            verify_signed_snapshot(&signed_snapshot, None).unwrap();
+        assert_eq!(verified_snapshot, snapshot);
+        assert_eq!(attestation.signer, signer.pubkey());
+        assert!(verify_signed_snapshot(
+            &signed_snapshot,
+            Some(&mut TrustedSigners::new(&[signer.pubkey()]))
+        )
+        .is_ok());
+        assert!(verify_signed_snapshot(
+            &signed_snapshot,
+            Some(&mut TrustedSigners::new(&[Pubkey::new_unique()]))
+        )
+        .is_err());
+
+        let mut tampered_snapshot = signed_snapshot.clone();
+        tampered_snapshot["feed"]["epoch"] = json!(8);
+        assert!(verify_signed_snapshot(&tampered_snapshot, None).is_err());
+    }
+
+    fn rotated_snapshot(previous_signer: &Keypair, new_signer: &Keypair, epoch: Epoch) -> Value {
+        let mut signed_snapshot = sign_snapshot(json!({ "epoch": epoch }), new_signer).unwrap();
+        let key_rotation = KeyRotation::new(previous_signer, &new_signer.pubkey(), epoch).unwrap();
+        add_key_rotation(&mut signed_snapshot, &key_rotation);
+        signed_snapshot
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let previous_signer = Keypair::new();
+        let new_signer = Keypair::new();
+        let mut trusted_signers = TrustedSigners::new(&[previous_signer.pubkey()]);
+        let signed_snapshot = sign_snapshot(json!({"epoch": 7}), &new_signer).unwrap();
+        assert!(verify_signed_snapshot(&signed_snapshot, Some(&mut trusted_signers)).is_err());
+
+        let key_rotation = KeyRotation::new(&previous_signer, &new_signer.pubkey(), 7).unwrap();
+        assert!(key_rotation.verify(&new_signer.pubkey()));
+        assert!(!key_rotation.verify(&Pubkey::new_unique()));
+        assert_eq!(
+            KeyRotation::from_json(&key_rotation.to_json()).unwrap(),
+            key_rotation
+        );
+        // The epoch is part of the endorsement
+        assert!(!KeyRotation {
+            epoch: 8,
+            ..key_rotation.clone()
+        }
+        .verify(&new_signer.pubkey()));
+
+        let signed_snapshot = rotated_snapshot(&previous_signer, &new_signer, 7);
+        assert!(verify_signed_snapshot(&signed_snapshot, Some(&mut trusted_signers)).is_ok());
+        assert!(trusted_signers.is_trusted(&new_signer.pubkey()));
+        assert!(!trusted_signers.is_trusted(&previous_signer.pubkey()));
+        // Accepting the same rotation again is a no-op
+        assert!(verify_signed_snapshot(&signed_snapshot, Some(&mut trusted_signers)).is_ok());
+        assert_eq!(
+            TrustedSigners::from_json(&trusted_signers.to_json()).unwrap(),
+            trusted_signers
+        );
+    }
+
+    #[test]
+    fn test_key_rotation_replay() {
+        let (first_signer, second_signer, third_signer) =
+            (Keypair::new(), Keypair::new(), Keypair::new());
+        let mut trusted_signers = TrustedSigners::new(&[first_signer.pubkey()]);
+        trusted_signers
+            .accept_rotation(
+                &KeyRotation::new(&first_signer, &second_signer.pubkey(), 10).unwrap(),
+                &second_signer.pubkey(),
+            )
+            .unwrap();
+
+        // The rotated out key can't endorse another key, whatever the epoch
+        let attacker = Keypair::new();
+        assert_eq!(
+            verify_signed_snapshot(
+                &rotated_snapshot(&first_signer, &attacker, 20),
+                Some(&mut trusted_signers)
+            ),
+            Err(format!(
+                "{} was rotated out in epoch 10",
+                first_signer.pubkey()
+            ))
+        );
+
+        // A rotation older than the one that made its signer trusted is rejected
+        assert!(verify_signed_snapshot(
+            &rotated_snapshot(&second_signer, &third_signer, 9),
+            Some(&mut trusted_signers)
+        )
+        .is_err());
+        assert!(verify_signed_snapshot(
+            &rotated_snapshot(&second_signer, &third_signer, 11),
+            Some(&mut trusted_signers)
+        )
+        .is_ok());
+        assert!(trusted_signers.is_trusted(&third_signer.pubkey()));
+        assert!(!trusted_signers.is_trusted(&attacker.pubkey()));
+    }
+}
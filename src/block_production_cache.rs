@@ -0,0 +1,201 @@
+//! Caching of `getBlockProduction` results
+use {
+    serde_json::{json, Value},
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcBlockProductionConfig, RpcBlockProductionConfigRange},
+    },
+    solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::{
+        collections::HashMap,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Default time a result covering unfinalized slots is reused for
+pub const DEFAULT_LIVE_TTL_MS: i64 = 60_000;
+
+/// Returns the percentage of `leader_slots` skipped, given `blocks_produced` blocks.
+///
+/// Without leader slots the skip rate is undefined rather than 0%: a validator that had no
+/// leader slots produced nothing and skipped nothing. `None` is returned so callers don't mistake
+/// it for a perfect skip rate
+pub fn skip_rate(leader_slots: usize, blocks_produced: usize) -> Option<f64> {
+    match leader_slots {
+        0 => None,
+        leader_slots => {
+            Some(leader_slots.saturating_sub(blocks_produced) as f64 * 100. / leader_slots as f64)
+        }
+    }
+}
+
+/// Rendering of a skip rate that tells an undefined skip rate apart from 0%
+pub fn format_skip_rate(skip_rate: Option<f64>) -> String {
+    skip_rate
+        .map(|skip_rate| format!("{:.2}%", skip_rate))
+        .unwrap_or_else(|| "- (no leader slots)".into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockProduction {
+    pub leader_slots: usize,
+    pub blocks_produced: usize,
+}
+
+impl BlockProduction {
+    /// See `skip_rate`
+    pub fn skip_rate(&self) -> Option<f64> {
+        skip_rate(self.leader_slots, self.blocks_produced)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CacheEntry {
+    block_production: BlockProduction,
+    /// Unix time, in milliseconds, of the fetch
+    fetched_at_ms: i64,
+    /// True if every slot of the range was finalized when fetched, so the result can't change
+    complete: bool,
+}
+
+/// Block production per (identity, slot range). Results for finalized ranges are kept forever,
+/// while results covering slots that were not yet finalized expire after `live_ttl_ms`.
+///
+/// The cache can be persisted with `to_json` and `from_json` to be shared between invocations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockProductionCache {
+    entries: HashMap<(Pubkey, Slot, Slot), CacheEntry>,
+    pub live_ttl_ms: i64,
+}
+
+impl Default for BlockProductionCache {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::default(),
+            live_ttl_ms: DEFAULT_LIVE_TTL_MS,
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+impl BlockProductionCache {
+    /// Returns the block production of `identity` in `first_slot..=last_slot`, fetching it if
+    /// there's no usable cached result
+    pub async fn get_block_production(
+        &mut self,
+        rpc_client: &RpcClient,
+        identity: &Pubkey,
+        first_slot: Slot,
+        last_slot: Slot,
+    ) -> Result<BlockProduction, Box<dyn std::error::Error>> {
+        let key = (*identity, first_slot, last_slot);
+        let now_ms = now_ms();
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.complete || now_ms - entry.fetched_at_ms < self.live_ttl_ms {
+                return Ok(entry.block_production);
+            }
+        }
+
+        let finalized_slot = rpc_client
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await?;
+        let response = rpc_client
+            .get_block_production_with_config(RpcBlockProductionConfig {
+                identity: Some(identity.to_string()),
+                range: Some(RpcBlockProductionConfigRange {
+                    first_slot,
+                    last_slot: Some(last_slot.min(finalized_slot)),
+                }),
+                commitment: Some(rpc_client.commitment()),
+            })
+            .await?
+            .value;
+        let (leader_slots, blocks_produced) = response
+            .by_identity
+            .get(&identity.to_string())
+            .copied()
+            .unwrap_or_default();
+        let block_production = BlockProduction {
+            leader_slots,
+            blocks_produced,
+        };
+
+        self.entries.insert(
+            key,
+            CacheEntry {
+                block_production,
+                fetched_at_ms: now_ms,
+                complete: last_slot <= finalized_slot,
+            },
+        );
+        Ok(block_production)
+    }
+
+    /// Drops results that have expired
+    pub fn prune(&mut self) {
+        let now_ms = now_ms();
+        let live_ttl_ms = self.live_ttl_ms;
+        self.entries
+            .retain(|_, entry| entry.complete || now_ms - entry.fetched_at_ms < live_ttl_ms);
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.entries
+                .iter()
+                .map(|((identity, first_slot, last_slot), entry)| {
+                    json!({
+                        "identity": identity.to_string(),
+                        "firstSlot": first_slot,
+                        "lastSlot": last_slot,
+                        "leaderSlots": entry.block_production.leader_slots,
+                        "blocksProduced": entry.block_production.blocks_produced,
+                        "fetchedAtMs": entry.fetched_at_ms,
+                        "complete": entry.complete,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Parses the output of `to_json`. Malformed entries are skipped
+    pub fn from_json(value: &Value, live_ttl_ms: i64) -> Self {
+        let entries = value
+            .as_array()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|entry| {
+                        let u64_field = |name: &str| entry.get(name).and_then(|v| v.as_u64());
+                        let identity = entry
+                            .get("identity")
+                            .and_then(|identity| identity.as_str())?
+                            .parse::<Pubkey>()
+                            .ok()?;
+                        Some((
+                            (identity, u64_field("firstSlot")?, u64_field("lastSlot")?),
+                            CacheEntry {
+                                block_production: BlockProduction {
+                                    leader_slots: u64_field("leaderSlots")? as usize,
+                                    blocks_produced: u64_field("blocksProduced")? as usize,
+                                },
+                                fetched_at_ms: entry.get("fetchedAtMs")?.as_i64()?,
+                                complete: entry.get("complete")?.as_bool()?,
+                            },
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            entries,
+            live_ttl_ms,
+        }
+    }
+}
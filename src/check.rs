@@ -0,0 +1,170 @@
+//! Nagios/Icinga compatible checks of a single validator
+use std::fmt;
+
+/// Plugin status, in order of severity except for `Unknown`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Critical,
+    /// The check itself failed, for example because the RPC node was unreachable
+    Unknown,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CheckStatus::Ok => "OK",
+            CheckStatus::Warning => "WARNING",
+            CheckStatus::Critical => "CRITICAL",
+            CheckStatus::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CheckThresholds {
+    /// Skip rate, in percent, above which the check warns
+    pub warn_skip_rate: f64,
+    pub crit_skip_rate: f64,
+    /// Slots behind the current slot the last vote may be before the check warns
+    pub warn_vote_distance: u64,
+    pub crit_vote_distance: u64,
+}
+
+impl Default for CheckThresholds {
+    fn default() -> Self {
+        Self {
+            warn_skip_rate: 10.,
+            crit_skip_rate: 25.,
+            warn_vote_distance: 32,
+            crit_vote_distance: 128,
+        }
+    }
+}
+
+/// The outcome of a check, displayed as a single plugin output line with performance data
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    /// Why the status is not OK, empty when it is
+    pub problems: Vec<String>,
+    pub delinquent: bool,
+    pub skip_rate: Option<f64>,
+    pub vote_distance: Option<u64>,
+    pub thresholds: CheckThresholds,
+}
+
+impl CheckResult {
+    /// A check that could not be performed
+    pub fn unknown(reason: String) -> Self {
+        Self {
+            status: CheckStatus::Unknown,
+            problems: vec![reason],
+            delinquent: false,
+            skip_rate: None,
+            vote_distance: None,
+            thresholds: CheckThresholds::default(),
+        }
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SOLANA VALIDATOR {} - ", self.status)?;
+        if self.status == CheckStatus::Unknown {
+            return write!(f, "{}", self.problems.join(", "));
+        }
+        if self.problems.is_empty() {
+            write!(f, "validator is healthy")?;
+        } else {
+            write!(f, "{}", self.problems.join(", "))?;
+        }
+
+        write!(f, " | delinquent={}", u8::from(self.delinquent))?;
+        if let Some(skip_rate) = self.skip_rate {
+            write!(
+                f,
+                " skip_rate={:.2}%;{};{};0;100",
+                skip_rate, self.thresholds.warn_skip_rate, self.thresholds.crit_skip_rate
+            )?;
+        }
+        if let Some(vote_distance) = self.vote_distance {
+            write!(
+                f,
+                " vote_distance={};{};{};0",
+                vote_distance,
+                self.thresholds.warn_vote_distance,
+                self.thresholds.crit_vote_distance
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates a validator's state against `thresholds`. A delinquent validator is always
+/// critical, a missing skip rate (no leader slots yet) or vote distance (no votes) is not checked
+pub fn check_validator(
+    thresholds: &CheckThresholds,
+    delinquent: bool,
+    skip_rate: Option<f64>,
+    vote_distance: Option<u64>,
+) -> CheckResult {
+    let mut status = CheckStatus::Ok;
+    let mut problems = vec![];
+    let mut raise = |new_status: CheckStatus, problem: String| {
+        status = status.max(new_status);
+        problems.push(problem);
+    };
+
+    if delinquent {
+        raise(CheckStatus::Critical, "delinquent".into());
+    }
+    if let Some(skip_rate) = skip_rate {
+        if skip_rate > thresholds.crit_skip_rate {
+            raise(
+                CheckStatus::Critical,
+                format!(
+                    "skip rate {:.2}% > {}%",
+                    skip_rate, thresholds.crit_skip_rate
+                ),
+            );
+        } else if skip_rate > thresholds.warn_skip_rate {
+            raise(
+                CheckStatus::Warning,
+                format!(
+                    "skip rate {:.2}% > {}%",
+                    skip_rate, thresholds.warn_skip_rate
+                ),
+            );
+        }
+    }
+    if let Some(vote_distance) = vote_distance {
+        if vote_distance > thresholds.crit_vote_distance {
+            raise(
+                CheckStatus::Critical,
+                format!(
+                    "vote distance {} > {} slots",
+                    vote_distance, thresholds.crit_vote_distance
+                ),
+            );
+        } else if vote_distance > thresholds.warn_vote_distance {
+            raise(
+                CheckStatus::Warning,
+                format!(
+                    "vote distance {} > {} slots",
+                    vote_distance, thresholds.warn_vote_distance
+                ),
+            );
+        }
+    }
+
+    CheckResult {
+        status,
+        problems,
+        delinquent,
+        skip_rate,
+        vote_distance,
+        thresholds: *thresholds,
+    }
+}
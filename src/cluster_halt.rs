@@ -0,0 +1,154 @@
+//! Detection of a cluster-wide halt, where the root slot stops advancing
+use {crate::check::CheckStatus, solana_sdk::clock::Slot, std::fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootStallEvent {
+    /// The root has not advanced past `slot` for `stalled_for_ms`
+    Stalled { slot: Slot, stalled_for_ms: i64 },
+    /// The root advanced to `slot` after a stall of `stalled_for_ms`
+    Resumed { slot: Slot, stalled_for_ms: i64 },
+}
+
+impl RootStallEvent {
+    pub fn severity(&self) -> CheckStatus {
+        match self {
+            RootStallEvent::Stalled { .. } => CheckStatus::Critical,
+            RootStallEvent::Resumed { .. } => CheckStatus::Ok,
+        }
+    }
+}
+
+impl fmt::Display for RootStallEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RootStallEvent::Stalled {
+                slot,
+                stalled_for_ms,
+            } => write!(
+                f,
+                "Cluster root stuck at slot {} for {}s. The cluster appears to be halted, \
+                 delinquency of individual validators is expected",
+                slot,
+                stalled_for_ms / 1000
+            ),
+            RootStallEvent::Resumed {
+                slot,
+                stalled_for_ms,
+            } => write!(
+                f,
+                "Cluster root advancing again at slot {} after a {}s stall",
+                slot,
+                stalled_for_ms / 1000
+            ),
+        }
+    }
+}
+
+/// Tracks observations of the cluster's root slot and reports when it stops advancing for
+/// longer than a threshold, and when it resumes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootStallDetector {
+    stall_threshold_ms: i64,
+    last_slot: Option<Slot>,
+    /// Unix time, in milliseconds, at which `last_slot` was first observed
+    last_advance_ms: i64,
+    stalled: bool,
+}
+
+impl RootStallDetector {
+    pub fn new(stall_threshold_ms: i64) -> Self {
+        Self {
+            stall_threshold_ms,
+            last_slot: None,
+            last_advance_ms: 0,
+            stalled: false,
+        }
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Records that the root was `slot` at Unix time `now_ms`, in milliseconds. An event is
+    /// returned only when the stall state changes
+    pub fn observe(&mut self, slot: Slot, now_ms: i64) -> Option<RootStallEvent> {
+        match self.last_slot {
+            Some(last_slot) if slot <= last_slot => {
+                let stalled_for_ms = now_ms - self.last_advance_ms;
+                if !self.stalled && stalled_for_ms >= self.stall_threshold_ms {
+                    self.stalled = true;
+                    return Some(RootStallEvent::Stalled {
+                        slot: last_slot,
+                        stalled_for_ms,
+                    });
+                }
+                None
+            }
+            _ => {
+                let stalled_for_ms = now_ms - self.last_advance_ms;
+                self.last_slot = Some(slot);
+                self.last_advance_ms = now_ms;
+                if self.stalled {
+                    self.stalled = false;
+                    return Some(RootStallEvent::Resumed {
+                        slot,
+                        stalled_for_ms,
+                    });
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_stall() {
+        let mut detector = RootStallDetector::new(60_000);
+        assert_eq!(detector.observe(100, 1_000_000), None);
+        assert_eq!(detector.observe(110, 1_005_000), None);
+        assert_eq!(detector.observe(110, 1_064_999), None);
+        assert_eq!(
+            detector.observe(110, 1_065_000),
+            Some(RootStallEvent::Stalled {
+                slot: 110,
+                stalled_for_ms: 60_000
+            })
+        );
+        assert!(detector.is_stalled());
+        // Reported once per stall, and a lower root doesn't count as progress
+        assert_eq!(detector.observe(110, 1_100_000), None);
+        assert_eq!(detector.observe(105, 1_110_000), None);
+
+        assert_eq!(
+            detector.observe(111, 1_125_000),
+            Some(RootStallEvent::Resumed {
+                slot: 111,
+                stalled_for_ms: 120_000
+            })
+        );
+        assert!(!detector.is_stalled());
+        assert_eq!(detector.observe(112, 1_126_000), None);
+    }
+
+    #[test]
+    fn test_severity() {
+        let stalled = RootStallEvent::Stalled {
+            slot: 1,
+            stalled_for_ms: 90_500,
+        };
+        assert_eq!(stalled.severity(), CheckStatus::Critical);
+        assert!(stalled.to_string().contains("for 90s"));
+        assert_eq!(
+            RootStallEvent::Resumed {
+                slot: 2,
+                stalled_for_ms: 90_500
+            }
+            .severity(),
+            CheckStatus::Ok
+        );
+    }
+}
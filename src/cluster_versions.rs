@@ -0,0 +1,415 @@
+//! Stake-weighted validator software version adoption
+use {
+    crate::{amount::Lamports, mev::MevCommission},
+    solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcGetVoteAccountsConfig},
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        cmp::Reverse,
+        collections::{BTreeMap, HashMap},
+        fmt,
+    },
+};
+
+/// A `major.minor.patch` version, ignoring any pre-release or build suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SoftwareVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl SoftwareVersion {
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim_start_matches('v').splitn(3, '.').map(|part| {
+            part.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse::<u64>()
+                .ok()
+        });
+        Some(Self {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next().flatten().unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Display for SoftwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionStake {
+    /// The version as reported in gossip, or "unknown"
+    pub version: String,
+    pub nodes: usize,
+    /// Activated stake, in lamports, of the nodes running this version
+    pub activated_stake: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClusterVersions {
+    /// Ordered by activated stake, highest first
+    pub versions: Vec<VersionStake>,
+    pub total_activated_stake: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeAdvice {
+    pub target_version: SoftwareVersion,
+    /// Percentage of the activated stake running `target_version` or newer
+    pub adopted_stake_percent: f64,
+    pub min_stake_percent: f64,
+    pub safe_to_upgrade: bool,
+}
+
+impl fmt::Display for UpgradeAdvice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}% of stake is on v{} or newer (threshold {:.2}%): {}",
+            self.adopted_stake_percent,
+            self.target_version,
+            self.min_stake_percent,
+            if self.safe_to_upgrade {
+                "safe to upgrade"
+            } else {
+                "wait before upgrading"
+            }
+        )
+    }
+}
+
+impl ClusterVersions {
+    fn stake_percent(&self, stake: u64) -> f64 {
+        stake as f64 * 100. / self.total_activated_stake.max(1) as f64
+    }
+
+    /// The version with the most activated stake
+    pub fn majority_version(&self) -> Option<&VersionStake> {
+        self.versions.first()
+    }
+
+    /// Percentage of the activated stake running `version` or newer
+    pub fn stake_percent_at_or_above(&self, version: &SoftwareVersion) -> f64 {
+        self.stake_percent(
+            self.versions
+                .iter()
+                .filter(|vs| SoftwareVersion::parse(&vs.version).is_some_and(|v| v >= *version))
+                .map(|vs| vs.activated_stake)
+                .sum(),
+        )
+    }
+
+    /// Recommends upgrading to `target_version` once at least `min_stake_percent` of the
+    /// activated stake already runs it, or a newer version
+    pub fn upgrade_advice(
+        &self,
+        target_version: SoftwareVersion,
+        min_stake_percent: f64,
+    ) -> UpgradeAdvice {
+        let adopted_stake_percent = self.stake_percent_at_or_above(&target_version);
+        UpgradeAdvice {
+            target_version,
+            adopted_stake_percent,
+            min_stake_percent,
+            safe_to_upgrade: adopted_stake_percent >= min_stake_percent,
+        }
+    }
+}
+
+impl fmt::Display for ClusterVersions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for vs in &self.versions {
+            writeln!(
+                f,
+                "{:<20} {:>6.2}% of stake, {} nodes",
+                vs.version,
+                self.stake_percent(vs.activated_stake),
+                vs.nodes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the activated stake per software version, from gossip and the vote accounts
+pub async fn get_cluster_versions(
+    rpc_client: &RpcClient,
+) -> Result<ClusterVersions, Box<dyn std::error::Error>> {
+    let cluster_nodes = rpc_client.get_cluster_nodes().await?;
+    let vote_accounts = rpc_client
+        .get_vote_accounts_with_config(RpcGetVoteAccountsConfig {
+            commitment: Some(rpc_client.commitment()),
+            ..RpcGetVoteAccountsConfig::default()
+        })
+        .await?;
+
+    let mut stake_by_identity = HashMap::<String, u64>::new();
+    for vai in vote_accounts
+        .current
+        .into_iter()
+        .chain(vote_accounts.delinquent)
+    {
+        *stake_by_identity.entry(vai.node_pubkey).or_default() += vai.activated_stake;
+    }
+
+    let mut versions = HashMap::<String, VersionStake>::new();
+    for node in cluster_nodes {
+        let version = node.version.unwrap_or_else(|| "unknown".into());
+        let activated_stake = stake_by_identity
+            .get(&node.pubkey)
+            .copied()
+            .unwrap_or_default();
+        let entry = versions
+            .entry(version.clone())
+            .or_insert_with(|| VersionStake {
+                version,
+                nodes: 0,
+                activated_stake: 0,
+            });
+        entry.nodes += 1;
+        entry.activated_stake += activated_stake;
+    }
+
+    let mut versions = versions.into_values().collect::<Vec<_>>();
+    versions.sort_by(|a, b| {
+        b.activated_stake
+            .cmp(&a.activated_stake)
+            .then(b.version.cmp(&a.version))
+    });
+
+    Ok(ClusterVersions {
+        total_activated_stake: stake_by_identity.values().sum(),
+        versions,
+    })
+}
+
+/// Validator client implementation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidatorClient {
+    Agave,
+    JitoAgave,
+    Frankendancer,
+    Unknown,
+}
+
+impl ValidatorClient {
+    /// Classifies a node by its gossip version. Frankendancer reports `0.x` versions, while
+    /// Agave and Jito-Agave report identical versions and are told apart by `runs_jito`
+    pub fn detect(version: Option<&str>, runs_jito: bool) -> Self {
+        match version.and_then(SoftwareVersion::parse) {
+            None => ValidatorClient::Unknown,
+            Some(version) if version.major == 0 => ValidatorClient::Frankendancer,
+            Some(_) if runs_jito => ValidatorClient::JitoAgave,
+            Some(_) => ValidatorClient::Agave,
+        }
+    }
+}
+
+impl fmt::Display for ValidatorClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ValidatorClient::Agave => "Agave",
+                ValidatorClient::JitoAgave => "Jito-Agave",
+                ValidatorClient::Frankendancer => "Frankendancer",
+                ValidatorClient::Unknown => "unknown",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorVersion {
+    pub vote_pubkey: Pubkey,
+    pub identity: Pubkey,
+    /// The version as reported in gossip, `None` if the node is not in gossip
+    pub version: Option<String>,
+    pub client: ValidatorClient,
+    pub activated_stake: u64,
+}
+
+/// The software version and client of every validator
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientDiversity {
+    /// Keyed by vote account
+    pub validators: BTreeMap<Pubkey, ValidatorVersion>,
+    pub total_activated_stake: u64,
+}
+
+impl ClientDiversity {
+    /// Returns the number of validators and their activated stake per client
+    pub fn stake_by_client(&self) -> BTreeMap<ValidatorClient, (usize, u64)> {
+        let mut stake_by_client = BTreeMap::<_, (usize, u64)>::new();
+        for validator in self.validators.values() {
+            let entry = stake_by_client.entry(validator.client).or_default();
+            entry.0 += 1;
+            entry.1 += validator.activated_stake;
+        }
+        stake_by_client
+    }
+
+    /// Returns the gossip version of every validator, keyed by vote account
+    pub fn versions(&self) -> BTreeMap<Pubkey, Option<String>> {
+        self.validators
+            .iter()
+            .map(|(vote_pubkey, validator)| (*vote_pubkey, validator.version.clone()))
+            .collect()
+    }
+
+    /// The version run by the most stake among Agave-based validators. Frankendancer versions are
+    /// numbered independently and are not considered
+    pub fn majority_version(&self) -> Option<SoftwareVersion> {
+        let mut stake_by_version = HashMap::<SoftwareVersion, u64>::new();
+        for validator in self.validators.values() {
+            if let (ValidatorClient::Agave | ValidatorClient::JitoAgave, Some(version)) = (
+                validator.client,
+                validator
+                    .version
+                    .as_deref()
+                    .and_then(SoftwareVersion::parse),
+            ) {
+                *stake_by_version.entry(version).or_default() += validator.activated_stake;
+            }
+        }
+        stake_by_version
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)))
+            .map(|(version, _)| version)
+    }
+}
+
+impl fmt::Display for ClientDiversity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (client, (validators, activated_stake)) in self.stake_by_client() {
+            writeln!(
+                f,
+                "{:<20} {:>6.2}% of stake, {} validators",
+                client.to_string(),
+                activated_stake as f64 * 100. / self.total_activated_stake.max(1) as f64,
+                validators
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A penalty on the score of validators running outdated software
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionPenalty {
+    /// Patch releases a validator may trail the majority version by, within the same minor
+    /// release. Any older minor release is outdated
+    pub max_patch_releases_behind: u64,
+    /// Percentage of the staker credits deducted from outdated validators
+    pub penalty_percent: u8,
+}
+
+impl VersionPenalty {
+    pub fn is_outdated(
+        &self,
+        version: &SoftwareVersion,
+        majority_version: &SoftwareVersion,
+    ) -> bool {
+        (version.major, version.minor) < (majority_version.major, majority_version.minor)
+            || ((version.major, version.minor) == (majority_version.major, majority_version.minor)
+                && version.patch + self.max_patch_releases_behind < majority_version.patch)
+    }
+
+    /// Deducts the penalty from the outdated validators in the output of
+    /// `get_validators_by_credit_score` and re-sorts it. Validators with an unknown version or a
+    /// client other than Agave or Jito-Agave are not penalized
+    pub fn apply(
+        &self,
+        validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+        client_diversity: &ClientDiversity,
+    ) -> Vec<(u64, Pubkey, Lamports)> {
+        let majority_version = client_diversity.majority_version();
+        let mut list = validators_by_staker_credits
+            .iter()
+            .map(|(staker_credits, vote_pubkey, activated_stake)| {
+                let outdated = client_diversity
+                    .validators
+                    .get(vote_pubkey)
+                    .filter(|validator| {
+                        matches!(
+                            validator.client,
+                            ValidatorClient::Agave | ValidatorClient::JitoAgave
+                        )
+                    })
+                    .and_then(|validator| validator.version.as_deref())
+                    .and_then(SoftwareVersion::parse)
+                    .zip(majority_version)
+                    .is_some_and(|(version, majority_version)| {
+                        self.is_outdated(&version, &majority_version)
+                    });
+                let staker_credits = if outdated {
+                    (u128::from(*staker_credits) * u128::from(100 - self.penalty_percent.min(100))
+                        / 100) as u64
+                } else {
+                    *staker_credits
+                };
+                (staker_credits, *vote_pubkey, *activated_stake)
+            })
+            .collect::<Vec<_>>();
+        list.sort_by_key(|(staker_credits, _, _)| Reverse(*staker_credits));
+        list
+    }
+}
+
+/// Returns the version and client of every staked validator. Without `mev_commissions`, keyed by
+/// vote account, Jito-Agave validators are reported as Agave
+pub async fn get_client_diversity(
+    rpc_client: &RpcClient,
+    mev_commissions: Option<&BTreeMap<Pubkey, MevCommission>>,
+) -> Result<ClientDiversity, Box<dyn std::error::Error>> {
+    let versions = rpc_client
+        .get_cluster_nodes()
+        .await?
+        .into_iter()
+        .map(|node| (node.pubkey, node.version))
+        .collect::<HashMap<_, _>>();
+    let vote_accounts = rpc_client
+        .get_vote_accounts_with_config(RpcGetVoteAccountsConfig {
+            commitment: Some(rpc_client.commitment()),
+            ..RpcGetVoteAccountsConfig::default()
+        })
+        .await?;
+
+    let mut client_diversity = ClientDiversity::default();
+    for vai in vote_accounts
+        .current
+        .into_iter()
+        .chain(vote_accounts.delinquent)
+    {
+        let (vote_pubkey, identity) = match (
+            vai.vote_pubkey.parse::<Pubkey>(),
+            vai.node_pubkey.parse::<Pubkey>(),
+        ) {
+            (Ok(vote_pubkey), Ok(identity)) => (vote_pubkey, identity),
+            _ => continue,
+        };
+        let version = versions.get(&vai.node_pubkey).cloned().flatten();
+        let runs_jito = mev_commissions
+            .and_then(|mev_commissions| mev_commissions.get(&vote_pubkey))
+            .is_some_and(|mev_commission| mev_commission.runs_jito());
+
+        client_diversity.total_activated_stake += vai.activated_stake;
+        client_diversity.validators.insert(
+            vote_pubkey,
+            ValidatorVersion {
+                vote_pubkey,
+                identity,
+                client: ValidatorClient::detect(version.as_deref(), runs_jito),
+                version,
+                activated_stake: vai.activated_stake,
+            },
+        );
+    }
+    Ok(client_diversity)
+}
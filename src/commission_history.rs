@@ -0,0 +1,85 @@
+//! Commission of every validator over a range of epochs, from the voting rewards paid in the
+//! first block of each epoch
+use {
+    crate::rpc::{get_first_block_rewards, voting_commissions, EpochCommissions},
+    log::*,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{clock::Epoch, epoch_schedule::EpochSchedule, pubkey::Pubkey},
+    std::{
+        collections::{btree_map::Entry, BTreeMap},
+        ops::Range,
+    },
+};
+
+/// Commissions per epoch. The first block of a past epoch is finalized, so entries never expire
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpochCommissionsCache {
+    entries: BTreeMap<Epoch, EpochCommissions>,
+}
+
+impl EpochCommissionsCache {
+    /// Returns the commission of each validator at the start of `epoch`, fetching the first
+    /// block of the epoch if it's not cached
+    pub async fn get_epoch_commissions(
+        &mut self,
+        rpc_client: &RpcClient,
+        epoch_schedule: &EpochSchedule,
+        epoch: Epoch,
+    ) -> Result<&EpochCommissions, Box<dyn std::error::Error>> {
+        Ok(match self.entries.entry(epoch) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let epoch_commissions = voting_commissions(
+                    get_first_block_rewards(
+                        rpc_client,
+                        epoch_schedule.get_first_slot_in_epoch(epoch),
+                    )
+                    .await?,
+                );
+                for warning in &epoch_commissions.warnings {
+                    warn!("Epoch {}: {}", epoch, warning);
+                }
+                entry.insert(epoch_commissions)
+            }
+        })
+    }
+}
+
+/// Returns the commission of every validator at the start of each of `epochs`, as (epoch,
+/// commission) oldest first. Validators are only included in the epochs they were paid a voting
+/// reward in. Epochs after the current one are ignored.
+///
+/// Each epoch boundary block is fetched once and kept in `cache`, to be shared by the checks
+/// built on the history
+pub async fn get_commission_history(
+    rpc_client: &RpcClient,
+    cache: &mut EpochCommissionsCache,
+    epochs: Range<Epoch>,
+) -> Result<BTreeMap<Pubkey, Vec<(Epoch, u8)>>, Box<dyn std::error::Error>> {
+    let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+    let current_epoch = rpc_client.get_epoch_info().await?.epoch;
+
+    let mut history = BTreeMap::<_, Vec<_>>::new();
+    for epoch in epochs.start..epochs.end.min(current_epoch + 1) {
+        for (vote_pubkey, commission) in &cache
+            .get_epoch_commissions(rpc_client, &epoch_schedule, epoch)
+            .await?
+            .commissions
+        {
+            history
+                .entry(*vote_pubkey)
+                .or_default()
+                .push((epoch, *commission));
+        }
+    }
+    Ok(history)
+}
+
+/// Returns the changes in a commission history as (epoch, previous commission, commission)
+pub fn commission_changes(history: &[(Epoch, u8)]) -> Vec<(Epoch, u8, u8)> {
+    history
+        .windows(2)
+        .filter(|pair| pair[0].1 != pair[1].1)
+        .map(|pair| (pair[1].0, pair[0].1, pair[1].1))
+        .collect()
+}
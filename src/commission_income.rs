@@ -0,0 +1,194 @@
+//! Tracking of a validator operator's commission income, accumulated epoch by epoch for revenue
+//! reporting
+use {
+    crate::{rpc::get_first_block, slot_clock::UtcDateTime},
+    serde_json::{json, Value},
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        clock::{Epoch, UnixTimestamp},
+        native_token::Sol,
+        pubkey::Pubkey,
+        reward_type::RewardType,
+    },
+    std::{collections::BTreeMap, fmt, ops::Range},
+};
+
+/// The commission a vote account earned in one epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochCommissionIncome {
+    pub epoch: Epoch,
+    /// Voting rewards paid to the vote account, in lamports
+    pub lamports: u64,
+    /// Time of the block that paid the income, `None` if the RPC node didn't know it
+    pub paid_at: Option<UnixTimestamp>,
+}
+
+/// Commission income of a calendar month, in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonthlyCommissionIncome {
+    pub year: i64,
+    pub month: u32,
+    pub lamports: u64,
+    pub epochs: usize,
+}
+
+impl fmt::Display for MonthlyCommissionIncome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}: {} over {} epoch(s)",
+            self.year,
+            self.month,
+            Sol(self.lamports),
+            self.epochs
+        )
+    }
+}
+
+/// Commission income per vote account and epoch.
+///
+/// The ledger can be persisted with `to_json` and `from_json`, so income keeps accumulating
+/// after RPC nodes have dropped the blocks that paid it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommissionIncomeLedger {
+    entries: BTreeMap<Pubkey, BTreeMap<Epoch, EpochCommissionIncome>>,
+}
+
+impl CommissionIncomeLedger {
+    /// Returns the recorded income of `vote_pubkey`, oldest epoch first
+    pub fn income(&self, vote_pubkey: &Pubkey) -> Vec<EpochCommissionIncome> {
+        self.entries
+            .get(vote_pubkey)
+            .map(|entries| entries.values().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn insert(&mut self, vote_pubkey: Pubkey, income: EpochCommissionIncome) {
+        self.entries
+            .entry(vote_pubkey)
+            .or_default()
+            .insert(income.epoch, income);
+    }
+
+    /// Records the income of each of `vote_pubkeys` in the completed `epochs` not yet in the
+    /// ledger, returning the number of entries added. The income of an epoch is the voting reward
+    /// paid to the vote account by the first block of the following epoch
+    pub async fn update(
+        &mut self,
+        rpc_client: &RpcClient,
+        vote_pubkeys: &[Pubkey],
+        epochs: Range<Epoch>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+        let mut added = 0;
+        for epoch in epochs {
+            let missing = vote_pubkeys
+                .iter()
+                .filter(|vote_pubkey| {
+                    !self
+                        .entries
+                        .get(vote_pubkey)
+                        .is_some_and(|entries| entries.contains_key(&epoch))
+                })
+                .collect::<Vec<_>>();
+            if missing.is_empty() {
+                continue;
+            }
+
+            let block = get_first_block(
+                rpc_client,
+                epoch_schedule.get_first_slot_in_epoch(epoch + 1),
+            )
+            .await?;
+            let rewards = block.rewards.unwrap_or_default();
+            for vote_pubkey in missing {
+                let vote_pubkey_str = vote_pubkey.to_string();
+                let lamports = rewards
+                    .iter()
+                    .filter(|reward| {
+                        reward.reward_type == Some(RewardType::Voting)
+                            && reward.pubkey == vote_pubkey_str
+                    })
+                    .map(|reward| reward.lamports.max(0) as u64)
+                    .sum();
+                self.insert(
+                    *vote_pubkey,
+                    EpochCommissionIncome {
+                        epoch,
+                        lamports,
+                        paid_at: block.block_time,
+                    },
+                );
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Returns the income of `vote_pubkey` per month it was paid in, oldest first. Epochs whose
+    /// payment time is unknown are left out
+    pub fn monthly_income(&self, vote_pubkey: &Pubkey) -> Vec<MonthlyCommissionIncome> {
+        let mut months = BTreeMap::<(i64, u32), MonthlyCommissionIncome>::new();
+        for income in self.income(vote_pubkey) {
+            let paid_at = match income.paid_at {
+                Some(paid_at) => UtcDateTime::from_unix_timestamp(paid_at),
+                None => continue,
+            };
+            let month =
+                months
+                    .entry((paid_at.year, paid_at.month))
+                    .or_insert(MonthlyCommissionIncome {
+                        year: paid_at.year,
+                        month: paid_at.month,
+                        lamports: 0,
+                        epochs: 0,
+                    });
+            month.lamports += income.lamports;
+            month.epochs += 1;
+        }
+        months.into_values().collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.entries
+                .iter()
+                .flat_map(|(vote_pubkey, entries)| {
+                    entries.values().map(move |income| {
+                        json!({
+                            "votePubkey": vote_pubkey.to_string(),
+                            "epoch": income.epoch,
+                            "lamports": income.lamports,
+                            "paidAt": income.paid_at,
+                        })
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Parses the output of `to_json`. Malformed entries are skipped
+    pub fn from_json(value: &Value) -> Self {
+        let mut ledger = Self::default();
+        for entry in value.as_array().into_iter().flatten() {
+            let vote_pubkey = entry
+                .get("votePubkey")
+                .and_then(|vote_pubkey| vote_pubkey.as_str())
+                .and_then(|vote_pubkey| vote_pubkey.parse::<Pubkey>().ok());
+            let epoch = entry.get("epoch").and_then(|epoch| epoch.as_u64());
+            let lamports = entry.get("lamports").and_then(|lamports| lamports.as_u64());
+            if let (Some(vote_pubkey), Some(epoch), Some(lamports)) = (vote_pubkey, epoch, lamports)
+            {
+                ledger.insert(
+                    vote_pubkey,
+                    EpochCommissionIncome {
+                        epoch,
+                        lamports,
+                        paid_at: entry.get("paidAt").and_then(|paid_at| paid_at.as_i64()),
+                    },
+                );
+            }
+        }
+        ledger
+    }
+}
@@ -0,0 +1,205 @@
+//! Side-by-side comparison of two validators
+use {
+    crate::{
+        block_production_cache::{format_skip_rate, skip_rate, BlockProductionCache},
+        epoch_info::EpochInfoHandle,
+        format::NumberFormat,
+        rewards::estimate_delegator_rewards,
+        score::{epoch_credits_earned, staker_credits},
+        vote_accounts::VoteAccountsSnapshot,
+    },
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey},
+    std::fmt,
+};
+
+/// The metrics of a validator used in a `ValidatorComparison`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatorSummary {
+    pub vote_pubkey: Pubkey,
+    pub identity: Pubkey,
+    pub commission: u8,
+    pub activated_stake: u64,
+    pub delinquent: bool,
+    /// Number of completed epochs the credit metrics cover
+    pub epochs: u64,
+    /// Completed epochs in which the validator earned credits
+    pub epochs_with_credits: u64,
+    /// Average staker credits per completed epoch, using the current commission
+    pub average_staker_credits: f64,
+    /// Leader slots so far in the current epoch
+    pub leader_slots: usize,
+    /// Blocks produced so far in the current epoch
+    pub blocks_produced: usize,
+    /// Projected annual delegator yield, in percent
+    pub apy: f64,
+}
+
+impl ValidatorSummary {
+    /// Percentage of leader slots skipped in the current epoch, `None` without leader slots
+    pub fn skip_rate(&self) -> Option<f64> {
+        skip_rate(self.leader_slots, self.blocks_produced)
+    }
+
+    /// Percentage of the completed epochs in which the validator earned credits
+    pub fn uptime(&self) -> f64 {
+        self.epochs_with_credits as f64 * 100. / self.epochs.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatorComparison {
+    pub a: ValidatorSummary,
+    pub b: ValidatorSummary,
+}
+
+impl fmt::Display for ValidatorComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut row =
+            |name: &str, a: String, b: String| writeln!(f, "{:<24} {:>44} {:>44}", name, a, b);
+        let (a, b) = (&self.a, &self.b);
+        let number_format = NumberFormat::default();
+
+        row(
+            "Vote account",
+            a.vote_pubkey.to_string(),
+            b.vote_pubkey.to_string(),
+        )?;
+        row("Identity", a.identity.to_string(), b.identity.to_string())?;
+        row(
+            "Commission",
+            format!("{}%", a.commission),
+            format!("{}%", b.commission),
+        )?;
+        row(
+            "Activated stake",
+            number_format.format_sol(a.activated_stake, 0),
+            number_format.format_sol(b.activated_stake, 0),
+        )?;
+        row(
+            "Delinquent",
+            a.delinquent.to_string(),
+            b.delinquent.to_string(),
+        )?;
+        row(
+            &format!("Staker credits ({} ep)", a.epochs),
+            number_format.format_decimal(a.average_staker_credits, 0),
+            number_format.format_decimal(b.average_staker_credits, 0),
+        )?;
+        row(
+            "Uptime",
+            format!("{:.2}%", a.uptime()),
+            format!("{:.2}%", b.uptime()),
+        )?;
+        row(
+            "Skip rate (this epoch)",
+            format_skip_rate(a.skip_rate()),
+            format_skip_rate(b.skip_rate()),
+        )?;
+        row(
+            "Projected yield",
+            format!("{:.3}%", a.apy),
+            format!("{:.3}%", b.apy),
+        )
+    }
+}
+
+/// Returns the metrics of `vote_pubkey`, with credits averaged over the `epochs` most recent
+/// completed epochs
+pub async fn get_validator_summary(
+    rpc_client: &RpcClient,
+    epoch_info_handle: &EpochInfoHandle,
+    block_production_cache: &mut BlockProductionCache,
+    vote_accounts: &VoteAccountsSnapshot,
+    vote_pubkey: &Pubkey,
+    epochs: u64,
+) -> Result<ValidatorSummary, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info_handle.get(rpc_client).await?;
+    let epoch = epoch_info.epoch;
+    let vai = vote_accounts
+        .find_by_vote(vote_pubkey)
+        .ok_or_else(|| format!("Vote account {} not found", vote_pubkey))?;
+    let identity = vote_accounts
+        .identity_of(vote_pubkey)
+        .ok_or_else(|| format!("Invalid identity {}", vai.node_pubkey))?;
+
+    let completed_epochs = (1..=epochs.min(epoch))
+        .map(|age| epoch - age)
+        .collect::<Vec<_>>();
+    let credits = completed_epochs
+        .iter()
+        .map(|epoch| epoch_credits_earned(&vai.epoch_credits, *epoch).unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    let first_slot_in_epoch = epoch_info.absolute_slot - epoch_info.slot_index;
+    let block_production = block_production_cache
+        .get_block_production(
+            rpc_client,
+            &identity,
+            first_slot_in_epoch,
+            first_slot_in_epoch + epoch_info.slots_in_epoch - 1,
+        )
+        .await?;
+
+    let apy = estimate_delegator_rewards(
+        rpc_client,
+        epoch_info_handle,
+        vote_accounts,
+        LAMPORTS_PER_SOL,
+        vote_pubkey,
+        1,
+    )
+    .await?
+    .apy();
+
+    Ok(ValidatorSummary {
+        vote_pubkey: *vote_pubkey,
+        identity,
+        commission: vai.commission,
+        activated_stake: vai.activated_stake,
+        delinquent: vote_accounts.is_delinquent(vote_pubkey),
+        epochs: completed_epochs.len() as u64,
+        epochs_with_credits: credits.iter().filter(|credits| **credits > 0).count() as u64,
+        average_staker_credits: credits
+            .iter()
+            .map(|credits| staker_credits(*credits, vai.commission))
+            .sum::<u64>() as f64
+            / credits.len().max(1) as f64,
+        leader_slots: block_production.leader_slots,
+        blocks_produced: block_production.blocks_produced,
+        apy,
+    })
+}
+
+/// Compares validators `a` and `b`, for example to decide whether to move stake from one to the
+/// other. Credits are averaged over the `epochs` most recent completed epochs
+pub async fn compare_validators(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    block_production_cache: &mut BlockProductionCache,
+    a: &Pubkey,
+    b: &Pubkey,
+    epochs: u64,
+) -> Result<ValidatorComparison, Box<dyn std::error::Error>> {
+    let vote_accounts = VoteAccountsSnapshot::fetch(rpc_client).await?;
+    Ok(ValidatorComparison {
+        a: get_validator_summary(
+            rpc_client,
+            epoch_info,
+            block_production_cache,
+            &vote_accounts,
+            a,
+            epochs,
+        )
+        .await?,
+        b: get_validator_summary(
+            rpc_client,
+            epoch_info,
+            block_production_cache,
+            &vote_accounts,
+            b,
+            epochs,
+        )
+        .await?,
+    })
+}
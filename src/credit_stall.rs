@@ -0,0 +1,140 @@
+//! Detection of a validator whose vote credits stop increasing, as happens when its vote signer
+//! is stuck, typically well before the validator is marked delinquent
+use {crate::check::CheckStatus, std::fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreditStallEvent {
+    /// Credits stayed at `credits` for `samples` consecutive samples
+    Stalled { credits: u64, samples: usize },
+    /// Credits increased to `credits` after not increasing for `samples` samples
+    Resumed { credits: u64, samples: usize },
+}
+
+impl CreditStallEvent {
+    pub fn severity(&self) -> CheckStatus {
+        match self {
+            CreditStallEvent::Stalled { .. } => CheckStatus::Critical,
+            CreditStallEvent::Resumed { .. } => CheckStatus::Ok,
+        }
+    }
+}
+
+impl fmt::Display for CreditStallEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CreditStallEvent::Stalled { credits, samples } => write!(
+                f,
+                "Vote credits stuck at {} for {} samples, check the vote signer even if the \
+                 validator is not delinquent yet",
+                credits, samples
+            ),
+            CreditStallEvent::Resumed { credits, samples } => write!(
+                f,
+                "Vote credits increasing again at {} after {} samples",
+                credits, samples
+            ),
+        }
+    }
+}
+
+/// Tracks samples of a vote account's total credits and reports when they don't increase for
+/// `stall_samples` consecutive samples, and when they increase again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreditStallDetector {
+    stall_samples: usize,
+    last_credits: Option<u64>,
+    /// Consecutive samples since `last_credits` last increased
+    unchanged_samples: usize,
+    stalled: bool,
+}
+
+impl CreditStallDetector {
+    pub fn new(stall_samples: usize) -> Self {
+        Self {
+            stall_samples: stall_samples.max(1),
+            last_credits: None,
+            unchanged_samples: 0,
+            stalled: false,
+        }
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Records a sample of the total credits. An event is returned only when the stall state
+    /// changes
+    pub fn observe(&mut self, credits: u64) -> Option<CreditStallEvent> {
+        match self.last_credits {
+            Some(last_credits) if credits <= last_credits => {
+                self.unchanged_samples += 1;
+                if !self.stalled && self.unchanged_samples >= self.stall_samples {
+                    self.stalled = true;
+                    return Some(CreditStallEvent::Stalled {
+                        credits: last_credits,
+                        samples: self.unchanged_samples,
+                    });
+                }
+                None
+            }
+            _ => {
+                let samples = self.unchanged_samples;
+                self.last_credits = Some(credits);
+                self.unchanged_samples = 0;
+                if self.stalled {
+                    self.stalled = false;
+                    return Some(CreditStallEvent::Resumed { credits, samples });
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credit_stall() {
+        let mut detector = CreditStallDetector::new(3);
+        assert_eq!(detector.observe(1_000), None);
+        assert_eq!(detector.observe(1_010), None);
+        assert_eq!(detector.observe(1_010), None);
+        assert_eq!(detector.observe(1_010), None);
+        assert_eq!(
+            detector.observe(1_010),
+            Some(CreditStallEvent::Stalled {
+                credits: 1_010,
+                samples: 3
+            })
+        );
+        assert!(detector.is_stalled());
+        // Reported once per stall, and a decrease doesn't count as progress
+        assert_eq!(detector.observe(1_010), None);
+        assert_eq!(detector.observe(1_000), None);
+
+        assert_eq!(
+            detector.observe(1_020),
+            Some(CreditStallEvent::Resumed {
+                credits: 1_020,
+                samples: 5
+            })
+        );
+        assert!(!detector.is_stalled());
+        assert_eq!(detector.observe(1_020), None);
+    }
+
+    #[test]
+    fn test_zero_stall_samples() {
+        let mut detector = CreditStallDetector::new(0);
+        assert_eq!(detector.observe(5), None);
+        assert_eq!(
+            detector.observe(5),
+            Some(CreditStallEvent::Stalled {
+                credits: 5,
+                samples: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Batching of non-critical events into one message per day or per epoch, so minor threshold
+//! wobbles don't flood the alert channel
+use {
+    crate::slot_clock::UtcDateTime,
+    solana_sdk::clock::Epoch,
+    std::{fmt, str::FromStr},
+};
+
+/// How often a digest is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestPeriod {
+    /// Per UTC day
+    Daily,
+    Epoch,
+}
+
+impl fmt::Display for DigestPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DigestPeriod::Daily => "daily",
+            DigestPeriod::Epoch => "epoch",
+        })
+    }
+}
+
+impl FromStr for DigestPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(DigestPeriod::Daily),
+            "epoch" => Ok(DigestPeriod::Epoch),
+            _ => Err(format!("Unknown digest period {}", s)),
+        }
+    }
+}
+
+/// Events collected for the current period
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Digest {
+    period: DigestPeriod,
+    /// Day since the Unix epoch, or epoch, of the events collected so far
+    current: Option<u64>,
+    events: Vec<String>,
+}
+
+impl Digest {
+    pub fn new(period: DigestPeriod) -> Self {
+        Self {
+            period,
+            current: None,
+            events: vec![],
+        }
+    }
+
+    pub fn period(&self) -> DigestPeriod {
+        self.period
+    }
+
+    pub fn push(&mut self, event: String) {
+        self.events.push(event);
+    }
+
+    /// Moves to the period of Unix time `now_ms`, in milliseconds, or of `epoch`. When the period
+    /// changes, returns the message for the events of the period left, `None` if there were none
+    pub fn advance(&mut self, now_ms: i64, epoch: Epoch) -> Option<String> {
+        let period = match self.period {
+            DigestPeriod::Daily => now_ms.div_euclid(86_400_000) as u64,
+            DigestPeriod::Epoch => epoch,
+        };
+        let previous = self.current.replace(period)?;
+        if previous == period || self.events.is_empty() {
+            return None;
+        }
+        let events = std::mem::take(&mut self.events);
+        Some(self.message(previous, &events))
+    }
+
+    fn message(&self, period: u64, events: &[String]) -> String {
+        let heading = match self.period {
+            DigestPeriod::Daily => {
+                let date = UtcDateTime::from_unix_timestamp(period as i64 * 86_400);
+                format!(
+                    "Digest of {:04}-{:02}-{:02}",
+                    date.year, date.month, date.day
+                )
+            }
+            DigestPeriod::Epoch => format!("Digest of epoch {}", period),
+        };
+        let mut message = format!("{}, {} event(s):", heading, events.len());
+        for event in events {
+            message.push_str("\n  ");
+            message.push_str(event);
+        }
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MS: i64 = 86_400_000;
+
+    #[test]
+    fn test_daily_digest() {
+        let mut digest = Digest::new(DigestPeriod::Daily);
+        // 2024-03-01
+        let day = 19_783 * DAY_MS;
+        digest.push("first".to_string());
+        assert_eq!(digest.advance(day + 1_000, 100), None);
+        digest.push("second".to_string());
+        assert_eq!(digest.advance(day + DAY_MS - 1, 101), None);
+        assert_eq!(
+            digest.advance(day + DAY_MS, 101),
+            Some("Digest of 2024-03-01, 2 event(s):\n  first\n  second".to_string())
+        );
+        // Nothing is sent for a period without events
+        assert_eq!(digest.advance(day + 3 * DAY_MS, 102), None);
+    }
+
+    #[test]
+    fn test_epoch_digest() {
+        let mut digest = Digest::new(DigestPeriod::Epoch);
+        assert_eq!(digest.advance(0, 100), None);
+        digest.push("skip rate above 5%".to_string());
+        assert_eq!(digest.advance(2 * DAY_MS, 100), None);
+        assert_eq!(
+            digest.advance(2 * DAY_MS, 101),
+            Some("Digest of epoch 100, 1 event(s):\n  skip rate above 5%".to_string())
+        );
+        assert_eq!(digest.advance(3 * DAY_MS, 102), None);
+    }
+
+    #[test]
+    fn test_digest_period() {
+        assert_eq!("daily".parse(), Ok(DigestPeriod::Daily));
+        assert_eq!("epoch".parse(), Ok(DigestPeriod::Epoch));
+        assert!("weekly".parse::<DigestPeriod>().is_err());
+        assert_eq!(DigestPeriod::Epoch.to_string(), "epoch");
+    }
+}
@@ -0,0 +1,58 @@
+//! Epoch info shared between computations and refreshed when stale
+use {
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::epoch_info::EpochInfo,
+    std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Default age after which `EpochInfoHandle` fetches the epoch info again
+pub const DEFAULT_EPOCH_INFO_TTL: Duration = Duration::from_secs(10);
+
+/// Lazily fetched epoch info, refetched once it's older than `ttl`.
+///
+/// Functions that need the epoch info take a handle rather than an `EpochInfo`, so a long running
+/// caller can't hand them one from an epoch that has since ended
+#[derive(Debug)]
+pub struct EpochInfoHandle {
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, EpochInfo)>>,
+}
+
+impl Default for EpochInfoHandle {
+    fn default() -> Self {
+        Self::new(DEFAULT_EPOCH_INFO_TTL)
+    }
+}
+
+impl EpochInfoHandle {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Returns the epoch info, fetching it if the cached copy is missing or older than the TTL
+    pub async fn get(
+        &self,
+        rpc_client: &RpcClient,
+    ) -> Result<EpochInfo, Box<dyn std::error::Error>> {
+        if let Some((fetched_at, epoch_info)) = self.cached.lock().unwrap().as_ref() {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(epoch_info.clone());
+            }
+        }
+
+        let epoch_info = rpc_client.get_epoch_info().await?;
+        *self.cached.lock().unwrap() = Some((Instant::now(), epoch_info.clone()));
+        Ok(epoch_info)
+    }
+
+    /// Forces the next `get` to fetch the epoch info
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+}
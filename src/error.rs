@@ -0,0 +1,77 @@
+//! Errors callers may want to tell apart from other failures.
+//!
+//! Functions return `Box<dyn std::error::Error>`, so use `downcast_ref::<Error>()` to match these
+use {
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    std::{fmt, time::Duration},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// `operation` did not complete within `timeout`
+    Timeout {
+        operation: String,
+        timeout: Duration,
+    },
+    /// The epoch changed between fetching the epoch info and the vote accounts, so they can't be
+    /// combined
+    EpochRolledOver {
+        epoch_info_epoch: Epoch,
+        vote_accounts_epoch: Epoch,
+    },
+    /// `vote_pubkey` earned credits in `epoch` but its commission in `epoch` is unknown
+    MissingCommission { vote_pubkey: Pubkey, epoch: Epoch },
+    /// A configuration file or argument is invalid
+    Config(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Timeout { operation, timeout } => write!(
+                f,
+                "{} did not complete within {} seconds",
+                operation,
+                timeout.as_secs_f64()
+            ),
+            Error::EpochRolledOver {
+                epoch_info_epoch,
+                vote_accounts_epoch,
+            } => write!(
+                f,
+                "The epoch rolled over while fetching data: the epoch info is from epoch {} but \
+                 the vote accounts are from epoch {}",
+                epoch_info_epoch, vote_accounts_epoch
+            ),
+            Error::MissingCommission { vote_pubkey, epoch } => write!(
+                f,
+                "{} earned credits in epoch {} but its commission in that epoch is unknown",
+                vote_pubkey, epoch
+            ),
+            Error::Config(msg) => write!(f, "Invalid configuration: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Awaits `future`, failing with `Error::Timeout` if it doesn't complete within `timeout`.
+/// Without a timeout, `future` is awaited for as long as it takes
+#[cfg(feature = "rpc")]
+pub async fn with_timeout<T>(
+    operation: &str,
+    timeout: Option<Duration>,
+    future: impl std::future::Future<Output = Result<T, Box<dyn std::error::Error>>>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    match timeout {
+        Some(timeout) => {
+            tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| Error::Timeout {
+                    operation: operation.to_string(),
+                    timeout,
+                })?
+        }
+        None => future.await,
+    }
+}
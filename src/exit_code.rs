@@ -0,0 +1,68 @@
+//! Stable process exit codes, so scripts and cron jobs can branch on the outcome of a run without
+//! parsing its output
+use {
+    crate::{check::CheckStatus, error::Error},
+    std::fmt,
+};
+
+/// Outcome of a run, in order of severity. The codes of `Ok` to `DataUnavailable` are the
+/// standard plugin exit codes, so a `CheckStatus` converts to the exit code of the same value.
+///
+/// Watching never ends on its own, so it only exits with `DataUnavailable` or `ConfigError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExitCode {
+    Ok,
+    /// A warning threshold of a check was breached, or the scores were not confirmed by a quorum
+    /// of scorers
+    Warning,
+    /// A critical threshold was breached, for example an SLA or a delinquent fleet member
+    Critical,
+    /// The data could not be fetched, for example because the RPC node was unreachable
+    DataUnavailable,
+    /// Invalid arguments or configuration files
+    ConfigError,
+}
+
+impl ExitCode {
+    pub fn code(self) -> i32 {
+        match self {
+            ExitCode::Ok => 0,
+            ExitCode::Warning => 1,
+            ExitCode::Critical => 2,
+            ExitCode::DataUnavailable => 3,
+            ExitCode::ConfigError => 4,
+        }
+    }
+
+    /// Exit code of a run that failed with `err`: `ConfigError` for `Error::Config`, and
+    /// `DataUnavailable` for anything else, as every other failure happens while fetching data
+    pub fn from_error(err: &(dyn std::error::Error + 'static)) -> Self {
+        match err.downcast_ref::<Error>() {
+            Some(Error::Config(_)) => ExitCode::ConfigError,
+            _ => ExitCode::DataUnavailable,
+        }
+    }
+}
+
+impl From<CheckStatus> for ExitCode {
+    fn from(status: CheckStatus) -> Self {
+        match status {
+            CheckStatus::Ok => ExitCode::Ok,
+            CheckStatus::Warning => ExitCode::Warning,
+            CheckStatus::Critical => ExitCode::Critical,
+            CheckStatus::Unknown => ExitCode::DataUnavailable,
+        }
+    }
+}
+
+impl fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ExitCode::Ok => "ok",
+            ExitCode::Warning => "warning",
+            ExitCode::Critical => "critical",
+            ExitCode::DataUnavailable => "data unavailable",
+            ExitCode::ConfigError => "config error",
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! Runtime feature-gate activation tracking
+use {
+    crate::slot_clock::SlotClock,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        clock::{Epoch, Slot},
+        epoch_schedule::EpochSchedule,
+        feature::{self, Feature},
+        feature_set::FEATURE_NAMES,
+        pubkey::Pubkey,
+    },
+    std::{fmt, ops::Range},
+};
+
+/// Maximum number of accounts per `getMultipleAccounts` request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Features that change how vote credits are earned. A score measured across their activation
+/// is not comparable with one measured before or after it
+pub fn credit_affecting_features() -> Vec<(Pubkey, &'static str)> {
+    vec![(timely_vote_credits_feature(), "timely vote credits")]
+}
+
+fn timely_vote_credits_feature() -> Pubkey {
+    "tvcF6b1TRz353zKuhBjinZkKzjmihXmBAHJdjNYw1sQ"
+        .parse()
+        .unwrap()
+}
+
+/// Returns the first epoch with Timely Vote Credits, `None` if the feature is not active.
+/// Features activate at the first slot of an epoch
+pub async fn get_timely_vote_credits_activation_epoch(
+    rpc_client: &RpcClient,
+) -> Result<Option<Epoch>, Box<dyn std::error::Error>> {
+    let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+    let activated_at = rpc_client
+        .get_account_with_commitment(&timely_vote_credits_feature(), rpc_client.commitment())
+        .await?
+        .value
+        .and_then(|account| feature::from_account(&account)?.activated_at);
+    Ok(activated_at.map(|slot| epoch_schedule.get_epoch(slot)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureStatus {
+    /// No activation has been requested
+    Inactive,
+    /// Activation has been requested and will happen at the start of `epoch`
+    Pending {
+        epoch: Epoch,
+        estimated_time_ms: i64,
+    },
+    Active {
+        slot: Slot,
+        epoch: Epoch,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureGate {
+    pub id: Pubkey,
+    pub description: String,
+    pub status: FeatureStatus,
+}
+
+impl FeatureGate {
+    /// Returns true if this feature activated within `slots`
+    pub fn activated_within(&self, slots: &Range<Slot>) -> bool {
+        matches!(self.status, FeatureStatus::Active { slot, .. } if slots.contains(&slot))
+    }
+}
+
+impl fmt::Display for FeatureGate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.status {
+            FeatureStatus::Inactive => write!(f, "{} inactive: {}", self.id, self.description),
+            FeatureStatus::Pending { epoch, .. } => write!(
+                f,
+                "{} pending activation in epoch {}: {}",
+                self.id, epoch, self.description
+            ),
+            FeatureStatus::Active { slot, epoch } => write!(
+                f,
+                "{} active since slot {} (epoch {}): {}",
+                self.id, slot, epoch, self.description
+            ),
+        }
+    }
+}
+
+/// Returns the status of every feature known to this build of the Solana SDK, plus
+/// `extra_features`
+pub async fn get_feature_gates(
+    rpc_client: &RpcClient,
+    slot_clock: &SlotClock,
+    extra_features: &[(Pubkey, &str)],
+) -> Result<Vec<FeatureGate>, Box<dyn std::error::Error>> {
+    let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+
+    let mut features = FEATURE_NAMES
+        .iter()
+        .map(|(id, description)| (*id, description.to_string()))
+        .collect::<Vec<_>>();
+    for (id, description) in extra_features {
+        if !features.iter().any(|(known_id, _)| known_id == id) {
+            features.push((*id, description.to_string()));
+        }
+    }
+    features.sort();
+
+    let mut feature_gates = vec![];
+    for chunk in features.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let ids = chunk.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let accounts = rpc_client.get_multiple_accounts(&ids).await?;
+
+        for ((id, description), account) in chunk.iter().zip(accounts) {
+            let status = match account.and_then(|account| feature::from_account(&account)) {
+                None => FeatureStatus::Inactive,
+                Some(Feature {
+                    activated_at: Some(slot),
+                }) => FeatureStatus::Active {
+                    slot,
+                    epoch: epoch_schedule.get_epoch(slot),
+                },
+                Some(Feature { activated_at: None }) => pending_status(&epoch_schedule, slot_clock),
+            };
+            feature_gates.push(FeatureGate {
+                id: *id,
+                description: description.clone(),
+                status,
+            });
+        }
+    }
+    Ok(feature_gates)
+}
+
+fn pending_status(epoch_schedule: &EpochSchedule, slot_clock: &SlotClock) -> FeatureStatus {
+    let epoch = epoch_schedule.get_epoch(slot_clock.reference_slot) + 1;
+    FeatureStatus::Pending {
+        epoch,
+        estimated_time_ms: slot_clock.slot_time_ms(epoch_schedule.get_first_slot_in_epoch(epoch)),
+    }
+}
+
+/// Returns the credit-affecting features that activated within `slots`, such as the slots of
+/// the epochs covered by a score
+pub async fn get_credit_affecting_activations(
+    rpc_client: &RpcClient,
+    slots: Range<Slot>,
+) -> Result<Vec<FeatureGate>, Box<dyn std::error::Error>> {
+    let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+    let features = credit_affecting_features();
+    let ids = features.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let accounts = rpc_client.get_multiple_accounts(&ids).await?;
+
+    Ok(features
+        .into_iter()
+        .zip(accounts)
+        .filter_map(|((id, description), account)| {
+            let slot = feature::from_account(&account?)?.activated_at?;
+            Some(FeatureGate {
+                id,
+                description: description.to_string(),
+                status: FeatureStatus::Active {
+                    slot,
+                    epoch: epoch_schedule.get_epoch(slot),
+                },
+            })
+        })
+        .filter(|feature_gate| feature_gate.activated_within(&slots))
+        .collect())
+}
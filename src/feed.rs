@@ -0,0 +1,252 @@
+//! Score feeds for consumption by stake pools and delegation gauges
+use {
+    crate::{
+        amount::Lamports,
+        attestation::sign_snapshot,
+        error::Error,
+        merkle::ScoreMerkleTree,
+        mev::MevCommission,
+        report::Percentiles,
+        schema::{check_schema_version, with_schema_version},
+    },
+    serde_json::{json, Value},
+    solana_sdk::{clock::Epoch, pubkey::Pubkey, signer::Signer},
+    std::collections::BTreeMap,
+};
+
+/// Requirements a validator must meet to be flagged as eligible for delegation in a feed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EligibilityCriteria {
+    /// Minimum credit score percentile
+    pub min_percentile: u8,
+    /// Minimum staker credits earned in the epoch
+    pub min_staker_credits: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreFeedEntry {
+    pub vote_pubkey: Pubkey,
+    pub staker_credits: u64,
+    pub percentile: u8,
+    pub activated_stake: u64,
+    pub eligible: bool,
+    /// Reasons `eligible` is false, empty for eligible validators
+    pub ineligibility_reasons: Vec<String>,
+    /// `None` if MEV commissions were not looked up for this feed
+    pub mev_commission: Option<MevCommission>,
+    /// Software version reported in gossip. `None` if versions were not looked up for this feed
+    /// or the validator is not in gossip
+    pub version: Option<String>,
+    /// Oldest epoch the vote account keeps credits for, see
+    /// `VoteAccountCredits::first_epoch_with_credits`. `None` if not looked up for this feed
+    pub first_epoch_with_credits: Option<Epoch>,
+}
+
+/// Per-validator scores and eligibility flags for a single epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreFeed {
+    pub epoch: Epoch,
+    pub entries: Vec<ScoreFeedEntry>,
+}
+
+impl ScoreFeed {
+    /// Builds a feed from the output of `get_validators_by_credit_score`
+    pub fn new(
+        epoch: Epoch,
+        validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+        criteria: &EligibilityCriteria,
+    ) -> Self {
+        let percentiles = Percentiles::new(
+            &validators_by_staker_credits
+                .iter()
+                .map(|(staker_credits, ..)| *staker_credits as f64)
+                .collect::<Vec<_>>(),
+        );
+
+        let entries = validators_by_staker_credits
+            .iter()
+            .map(|(staker_credits, vote_pubkey, activated_stake)| {
+                let percentile = percentiles.rank(*staker_credits as f64);
+
+                let mut ineligibility_reasons = vec![];
+                if percentile < criteria.min_percentile {
+                    ineligibility_reasons.push(format!(
+                        "{}th percentile is below the {}th percentile minimum",
+                        percentile, criteria.min_percentile
+                    ));
+                }
+                if *staker_credits < criteria.min_staker_credits {
+                    ineligibility_reasons.push(format!(
+                        "{} staker credits is below the {} credit minimum",
+                        staker_credits, criteria.min_staker_credits
+                    ));
+                }
+
+                ScoreFeedEntry {
+                    vote_pubkey: *vote_pubkey,
+                    staker_credits: *staker_credits,
+                    percentile,
+                    activated_stake: activated_stake.lamports(),
+                    eligible: ineligibility_reasons.is_empty(),
+                    ineligibility_reasons,
+                    mev_commission: None,
+                    version: None,
+                    first_epoch_with_credits: None,
+                }
+            })
+            .collect();
+
+        Self { epoch, entries }
+    }
+
+    /// Records the MEV commission of each validator found in `mev_commissions`
+    pub fn set_mev_commissions(&mut self, mev_commissions: &BTreeMap<Pubkey, MevCommission>) {
+        for entry in &mut self.entries {
+            if let Some(mev_commission) = mev_commissions.get(&entry.vote_pubkey) {
+                entry.mev_commission = Some(*mev_commission);
+            }
+        }
+    }
+
+    /// Records the software version of each validator found in `versions`
+    pub fn set_versions(&mut self, versions: &BTreeMap<Pubkey, Option<String>>) {
+        for entry in &mut self.entries {
+            if let Some(version) = versions.get(&entry.vote_pubkey) {
+                entry.version = version.clone();
+            }
+        }
+    }
+
+    /// Records the first epoch with credits of each validator found in `first_epochs`
+    pub fn set_first_epochs_with_credits(&mut self, first_epochs: &BTreeMap<Pubkey, Epoch>) {
+        for entry in &mut self.entries {
+            if let Some(first_epoch) = first_epochs.get(&entry.vote_pubkey) {
+                entry.first_epoch_with_credits = Some(*first_epoch);
+            }
+        }
+    }
+
+    /// Returns a Merkle tree over the scores in this feed, see `merkle::ScoreMerkleTree`
+    pub fn merkle_tree(&self) -> ScoreMerkleTree {
+        ScoreMerkleTree::new(
+            self.epoch,
+            self.entries
+                .iter()
+                .map(|entry| (entry.vote_pubkey, entry.staker_credits)),
+        )
+    }
+
+    /// Returns the feed as JSON, see `schema` for how its format is versioned
+    pub fn to_json(&self) -> Value {
+        with_schema_version(json!({
+            "epoch": self.epoch,
+            "merkleRoot": self.merkle_tree().root().to_string(),
+            "validators": self.entries.iter().map(|entry| {
+                let mut value = json!({
+                    "votePubkey": entry.vote_pubkey.to_string(),
+                    "score": entry.staker_credits,
+                    "percentile": entry.percentile,
+                    "activatedStake": entry.activated_stake,
+                    "eligible": entry.eligible,
+                    "ineligibilityReasons": entry.ineligibility_reasons,
+                });
+                if let Some(mev_commission) = entry.mev_commission {
+                    value["runsJito"] = json!(mev_commission.runs_jito());
+                    value["mevCommissionBps"] = json!(mev_commission.bps());
+                }
+                if let Some(version) = &entry.version {
+                    value["version"] = json!(version);
+                }
+                if let Some(first_epoch_with_credits) = entry.first_epoch_with_credits {
+                    value["firstEpochWithCredits"] = json!(first_epoch_with_credits);
+                }
+                value
+            }).collect::<Vec<_>>(),
+        }))
+    }
+
+    /// Parses the output of `to_json`, checking that the `merkleRoot` matches the scores
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        check_schema_version(value)?;
+        let epoch = value
+            .get("epoch")
+            .and_then(|epoch| epoch.as_u64())
+            .ok_or("Feed `epoch` missing")?;
+
+        let entries = value
+            .get("validators")
+            .and_then(|validators| validators.as_array())
+            .ok_or("Feed `validators` missing")?
+            .iter()
+            .map(|entry| {
+                let vote_pubkey = entry
+                    .get("votePubkey")
+                    .and_then(|vote_pubkey| vote_pubkey.as_str())
+                    .and_then(|vote_pubkey| vote_pubkey.parse::<Pubkey>().ok())
+                    .ok_or_else(|| format!("Invalid feed entry: {}", entry))?;
+                let u64_field = |name: &str| {
+                    entry
+                        .get(name)
+                        .and_then(|field| field.as_u64())
+                        .ok_or_else(|| format!("Feed entry {} has no `{}`", vote_pubkey, name))
+                };
+
+                Ok(ScoreFeedEntry {
+                    vote_pubkey,
+                    staker_credits: u64_field("score")?,
+                    percentile: u64_field("percentile")?.min(100) as u8,
+                    activated_stake: u64_field("activatedStake")?,
+                    eligible: entry
+                        .get("eligible")
+                        .and_then(|eligible| eligible.as_bool())
+                        .unwrap_or_default(),
+                    ineligibility_reasons: entry
+                        .get("ineligibilityReasons")
+                        .and_then(|reasons| reasons.as_array())
+                        .map(|reasons| {
+                            reasons
+                                .iter()
+                                .filter_map(|reason| reason.as_str().map(|s| s.to_string()))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    mev_commission: entry
+                        .get("runsJito")
+                        .and_then(|runs_jito| runs_jito.as_bool())
+                        .map(|runs_jito| {
+                            match entry.get("mevCommissionBps").and_then(|bps| bps.as_u64()) {
+                                Some(bps) if runs_jito => {
+                                    MevCommission::Bps(bps.min(u16::MAX.into()) as u16)
+                                }
+                                _ => MevCommission::NotRunningJito,
+                            }
+                        }),
+                    version: entry
+                        .get("version")
+                        .and_then(|version| version.as_str())
+                        .map(|version| version.to_string()),
+                    first_epoch_with_credits: entry
+                        .get("firstEpochWithCredits")
+                        .and_then(|epoch| epoch.as_u64()),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let score_feed = Self { epoch, entries };
+        let merkle_root = score_feed.merkle_tree().root().to_string();
+        if value.get("merkleRoot").and_then(|root| root.as_str()) != Some(merkle_root.as_str()) {
+            return Err(format!(
+                "Feed Merkle root mismatch, computed {}",
+                merkle_root
+            ));
+        }
+        Ok(score_feed)
+    }
+
+    /// Returns the feed as JSON, together with the signer's pubkey and a detached signature over
+    /// the canonical serialization of the `feed` object. See `attestation::verify_signed_snapshot`
+    /// and, for signers that can't sign, `attestation::attest`
+    pub fn sign(&self, signer: &dyn Signer) -> Result<Value, Error> {
+        sign_snapshot(self.to_json(), signer)
+    }
+}
@@ -0,0 +1,321 @@
+//! Consumption of score feeds published by other instances of this crate
+use {
+    crate::{
+        attestation::{verify_signed_snapshot, Attestation, TrustedSigners},
+        feed::ScoreFeed,
+    },
+    log::*,
+    reqwest::Client,
+    serde_json::Value,
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    std::{cmp::Reverse, collections::BTreeMap, fmt},
+};
+
+/// A score feed whose signature and Merkle root have been verified
+#[derive(Debug, Clone)]
+pub struct VerifiedScoreFeed {
+    pub url: String,
+    pub attestation: Attestation,
+    pub score_feed: ScoreFeed,
+}
+
+/// Fetches the signed score feed at `url`, as written by `ScoreFeed::sign`, and verifies it.
+///
+/// If `trusted_signers` is provided the feed must be signed by one of them, or by a key they
+/// rotated to
+pub async fn fetch_score_feed(
+    client: &Client,
+    url: &str,
+    trusted_signers: Option<&mut TrustedSigners>,
+) -> Result<VerifiedScoreFeed, Box<dyn std::error::Error>> {
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let signed_feed = serde_json::from_str::<Value>(&body)?;
+
+    let (feed, attestation) = verify_signed_snapshot(&signed_feed, trusted_signers)
+        .map_err(|err| format!("{}: {}", url, err))?;
+    let score_feed = ScoreFeed::from_json(&feed).map_err(|err| format!("{}: {}", url, err))?;
+
+    Ok(VerifiedScoreFeed {
+        url: url.to_string(),
+        attestation,
+        score_feed,
+    })
+}
+
+/// A feed that could not be used, with the reason
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnavailableScoreFeed {
+    pub url: String,
+    pub reason: String,
+}
+
+/// Fetches and verifies the feeds at `urls` for `epoch`. Feeds that fail to fetch, fail
+/// verification or are for another epoch are logged and returned as unavailable
+pub async fn fetch_score_feeds(
+    client: &Client,
+    urls: &[String],
+    epoch: Epoch,
+    mut trusted_signers: Option<&mut TrustedSigners>,
+) -> (Vec<VerifiedScoreFeed>, Vec<UnavailableScoreFeed>) {
+    let (mut feeds, mut unavailable) = (vec![], vec![]);
+    for url in urls {
+        let reason = match fetch_score_feed(client, url, trusted_signers.as_deref_mut()).await {
+            Ok(feed) if feed.score_feed.epoch == epoch => {
+                feeds.push(feed);
+                continue;
+            }
+            Ok(feed) => format!("feed is for epoch {}, not {}", feed.score_feed.epoch, epoch),
+            Err(err) => err.to_string(),
+        };
+        warn!("{}: {}", url, reason);
+        unavailable.push(UnavailableScoreFeed {
+            url: url.clone(),
+            reason,
+        });
+    }
+    (feeds, unavailable)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregatedScore {
+    pub vote_pubkey: Pubkey,
+    pub median_staker_credits: u64,
+    /// Number of feeds that included this validator
+    pub scorers: usize,
+}
+
+/// Combines the scores of independent scorers into a per-validator median, ordered by median
+/// staker credits.
+///
+/// Validators are only included if they appear in at least `min_scorers` of the feeds
+pub fn median_scores(score_feeds: &[&ScoreFeed], min_scorers: usize) -> Vec<AggregatedScore> {
+    let mut scores = BTreeMap::<Pubkey, Vec<u64>>::new();
+    for score_feed in score_feeds {
+        for entry in &score_feed.entries {
+            scores
+                .entry(entry.vote_pubkey)
+                .or_default()
+                .push(entry.staker_credits);
+        }
+    }
+
+    let mut aggregated_scores = scores
+        .into_iter()
+        .filter(|(_, staker_credits)| staker_credits.len() >= min_scorers.max(1))
+        .map(|(vote_pubkey, mut staker_credits)| {
+            staker_credits.sort_unstable();
+            let len = staker_credits.len();
+            let median_staker_credits = ((u128::from(staker_credits[(len - 1) / 2])
+                + u128::from(staker_credits[len / 2]))
+                / 2) as u64;
+
+            AggregatedScore {
+                vote_pubkey,
+                median_staker_credits,
+                scorers: staker_credits.len(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    aggregated_scores.sort_by_key(|score| Reverse(score.median_staker_credits));
+    aggregated_scores
+}
+
+/// How many scorers must agree on the scores, and how closely, before they are published
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuorumConfig {
+    /// Number of scorers, including this one, that must agree
+    pub quorum: usize,
+    /// Largest difference in staker credits, in percent of the local score, still agreeing
+    pub tolerance_percent: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuorumOutcome {
+    pub quorum: usize,
+    /// Number of scorers, including this one and the unavailable ones
+    pub scorers: usize,
+    /// Number of scorers agreeing with this one, including itself
+    pub agreeing: usize,
+    /// URL and number of differing validators of each peer feed that disagrees
+    pub disagreeing: Vec<(String, usize)>,
+    /// Peer feeds that could not be checked
+    pub unavailable: Vec<UnavailableScoreFeed>,
+}
+
+impl QuorumOutcome {
+    pub fn reached(&self) -> bool {
+        self.agreeing >= self.quorum
+    }
+}
+
+impl fmt::Display for QuorumOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} scorers agree, quorum of {} {}",
+            self.agreeing,
+            self.scorers,
+            self.quorum,
+            if self.reached() {
+                "reached"
+            } else {
+                "not reached"
+            }
+        )?;
+        for (url, differing_validators) in &self.disagreeing {
+            write!(
+                f,
+                "\n  {} differs for {} validator(s)",
+                url, differing_validators
+            )?;
+        }
+        for unavailable in &self.unavailable {
+            write!(
+                f,
+                "\n  {} unavailable: {}",
+                unavailable.url, unavailable.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the validators of `local` whose score in `peer` is missing or differs by more than
+/// `tolerance_percent`
+fn differing_validators(local: &ScoreFeed, peer: &ScoreFeed, tolerance_percent: f64) -> usize {
+    let peer_scores = peer
+        .entries
+        .iter()
+        .map(|entry| (entry.vote_pubkey, entry.staker_credits))
+        .collect::<BTreeMap<_, _>>();
+    local
+        .entries
+        .iter()
+        .filter(|entry| {
+            peer_scores
+                .get(&entry.vote_pubkey)
+                .is_none_or(|peer_staker_credits| {
+                    entry.staker_credits.abs_diff(*peer_staker_credits) as f64 * 100.
+                        > entry.staker_credits.max(1) as f64 * tolerance_percent
+                })
+        })
+        .count()
+}
+
+/// Checks the local score feed against the feeds of redundant scorers, so that a single bad RPC
+/// source can't poison the published scores. A peer agrees if it scores every validator of
+/// `local` within `config.tolerance_percent`. `unavailable` peers count as scorers that don't
+/// agree
+pub fn score_quorum(
+    local: &ScoreFeed,
+    peers: &[VerifiedScoreFeed],
+    unavailable: &[UnavailableScoreFeed],
+    config: &QuorumConfig,
+) -> QuorumOutcome {
+    let mut outcome = QuorumOutcome {
+        quorum: config.quorum,
+        scorers: peers.len() + unavailable.len() + 1,
+        agreeing: 1,
+        disagreeing: vec![],
+        unavailable: unavailable.to_vec(),
+    };
+    for peer in peers {
+        match differing_validators(local, &peer.score_feed, config.tolerance_percent) {
+            0 => outcome.agreeing += 1,
+            differing => outcome.disagreeing.push((peer.url.clone(), differing)),
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{amount::Lamports, feed::EligibilityCriteria},
+    };
+
+    fn score_feed(scores: &[(u64, Pubkey)]) -> ScoreFeed {
+        ScoreFeed::new(
+            7,
+            &scores
+                .iter()
+                .map(|(staker_credits, vote_pubkey)| (*staker_credits, *vote_pubkey, Lamports(1)))
+                .collect::<Vec<_>>(),
+            &EligibilityCriteria::default(),
+        )
+    }
+
+    fn peer(url: &str, score_feed: ScoreFeed) -> VerifiedScoreFeed {
+        VerifiedScoreFeed {
+            url: url.to_string(),
+            attestation: Attestation {
+                signer: Pubkey::new_unique(),
+                signature: Default::default(),
+            },
+            score_feed,
+        }
+    }
+
+    #[test]
+    fn test_score_quorum() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let local = score_feed(&[(1_000, a), (500, b)]);
+        let peers = [
+            peer("https://agrees", score_feed(&[(1_005, a), (500, b)])),
+            peer("https://differs", score_feed(&[(1_100, a), (500, b)])),
+            peer("https://incomplete", score_feed(&[(1_000, a)])),
+        ];
+        let unavailable = [UnavailableScoreFeed {
+            url: "https://unavailable".to_string(),
+            reason: "timeout".to_string(),
+        }];
+        let config = QuorumConfig {
+            quorum: 2,
+            tolerance_percent: 1.,
+        };
+
+        let outcome = score_quorum(&local, &peers, &unavailable, &config);
+        assert_eq!(
+            outcome,
+            QuorumOutcome {
+                quorum: 2,
+                scorers: 5,
+                agreeing: 2,
+                disagreeing: vec![
+                    ("https://differs".to_string(), 1),
+                    ("https://incomplete".to_string(), 1),
+                ],
+                unavailable: unavailable.to_vec(),
+            }
+        );
+        assert!(outcome.reached());
+
+        let outcome = score_quorum(&local, &peers[1..], &unavailable, &config);
+        assert_eq!(outcome.agreeing, 1);
+        assert!(!outcome.reached());
+    }
+
+    #[test]
+    fn test_score_quorum_without_peers() {
+        let local = score_feed(&[(1_000, Pubkey::new_unique())]);
+        let outcome = score_quorum(
+            &local,
+            &[],
+            &[],
+            &QuorumConfig {
+                quorum: 1,
+                tolerance_percent: 0.,
+            },
+        );
+        assert_eq!(outcome.scorers, 1);
+        assert!(outcome.reached());
+    }
+}
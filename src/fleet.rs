@@ -0,0 +1,418 @@
+//! Monitoring of several validators run by the same operator as one fleet
+use {
+    crate::{
+        amount::Lamports, block_production_cache::BlockProductionCache,
+        epoch_info::EpochInfoHandle, error::Error, vote_accounts::VoteAccountsSnapshot,
+    },
+    reqwest::Client,
+    serde_json::Value,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::pubkey::Pubkey,
+    std::{collections::BTreeMap, fmt},
+};
+
+/// A validator of the fleet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FleetMember {
+    pub vote_pubkey: Pubkey,
+    pub label: String,
+    pub region: Option<String>,
+    pub owner: Option<String>,
+    /// Group used for aggregation and alert routing
+    pub group: Option<String>,
+}
+
+fn string_field(value: &Value, name: &str) -> Option<String> {
+    value
+        .get(name)
+        .and_then(|field| field.as_str())
+        .map(|field| field.to_string())
+}
+
+impl FleetMember {
+    /// A member labeled `label`, or its vote account address without a label
+    pub fn new(vote_pubkey: Pubkey, label: Option<String>) -> Self {
+        Self {
+            vote_pubkey,
+            label: label.unwrap_or_else(|| vote_pubkey.to_string()),
+            region: None,
+            owner: None,
+            group: None,
+        }
+    }
+
+    /// Parses a member of the form `{"votePubkey", "label", "region", "owner", "group"}`, where
+    /// only `votePubkey` is required
+    pub fn from_json(member: &Value) -> Result<Self, String> {
+        let vote_pubkey = member
+            .get("votePubkey")
+            .and_then(|vote_pubkey| vote_pubkey.as_str())
+            .and_then(|vote_pubkey| vote_pubkey.parse::<Pubkey>().ok())
+            .ok_or_else(|| format!("Invalid fleet member: {}", member))?;
+        Ok(Self {
+            region: string_field(member, "region"),
+            owner: string_field(member, "owner"),
+            group: string_field(member, "group"),
+            ..Self::new(vote_pubkey, string_field(member, "label"))
+        })
+    }
+}
+
+/// The validators of an operator, with the Slack webhook alerts of each group are sent to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fleet {
+    pub members: Vec<FleetMember>,
+    /// Slack webhook per group
+    pub alert_routes: BTreeMap<String, String>,
+    /// Slack webhook for members without a group, or whose group has no route
+    pub default_alert_route: Option<String>,
+}
+
+impl Fleet {
+    /// Parses a fleet definition of the form
+    /// `{"validators": [{"votePubkey", "label", "region", "owner", "group"}],
+    /// "alertRoutes": {"<group>": "<webhook>"}, "defaultAlertRoute": "<webhook>"}`, where only
+    /// `validators` and `votePubkey` are required
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let members = value
+            .get("validators")
+            .and_then(|validators| validators.as_array())
+            .ok_or("Fleet `validators` missing")?
+            .iter()
+            .map(FleetMember::from_json)
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let alert_routes = value
+            .get("alertRoutes")
+            .and_then(|alert_routes| alert_routes.as_object())
+            .map(|alert_routes| {
+                alert_routes
+                    .iter()
+                    .filter_map(|(group, webhook)| {
+                        webhook
+                            .as_str()
+                            .map(|webhook| (group.clone(), webhook.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            members,
+            alert_routes,
+            default_alert_route: string_field(value, "defaultAlertRoute"),
+        })
+    }
+
+    /// Adds the `members` not in the fleet yet, returning how many were added. Members already in
+    /// the fleet keep their definition
+    pub fn add_members(&mut self, members: impl IntoIterator<Item = FleetMember>) -> usize {
+        let mut added = 0;
+        for member in members {
+            if self.find(&member.vote_pubkey).is_none() {
+                self.members.push(member);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    pub fn find(&self, vote_pubkey: &Pubkey) -> Option<&FleetMember> {
+        self.members
+            .iter()
+            .find(|member| member.vote_pubkey == *vote_pubkey)
+    }
+
+    /// Returns the Slack webhook alerts about `vote_pubkey` go to
+    pub fn alert_route(&self, vote_pubkey: &Pubkey) -> Option<&str> {
+        self.find(vote_pubkey)
+            .and_then(|member| member.group.as_ref())
+            .and_then(|group| self.alert_routes.get(group))
+            .or(self.default_alert_route.as_ref())
+            .map(|webhook| webhook.as_str())
+    }
+
+    /// Aggregates `statuses` by member group. Members without a group are aggregated under ""
+    pub fn metrics_by_group(&self, statuses: &[MemberStatus]) -> BTreeMap<String, FleetMetrics> {
+        let mut metrics_by_group = BTreeMap::<String, FleetMetrics>::new();
+        for status in statuses {
+            let group = self
+                .find(&status.vote_pubkey)
+                .and_then(|member| member.group.clone())
+                .unwrap_or_default();
+            metrics_by_group.entry(group).or_default().add(status);
+        }
+        metrics_by_group
+    }
+}
+
+/// Parses a watchlist of validators to add to a fleet, either JSON or CSV.
+///
+/// JSON watchlists are an array of vote account addresses or of fleet members, see
+/// `FleetMember::from_json`, or a fleet definition. CSV watchlists have a vote account address
+/// and an optional label per line, with an optional header line; empty lines and lines starting
+/// with `#` are ignored
+pub fn parse_watchlist(contents: &str) -> Result<Vec<FleetMember>, String> {
+    let contents = contents.trim();
+    if contents.starts_with('{') || contents.starts_with('[') {
+        let value = serde_json::from_str::<Value>(contents)
+            .map_err(|err| format!("Invalid JSON: {}", err))?;
+        if value.is_object() {
+            return Fleet::from_json(&value).map(|fleet| fleet.members);
+        }
+        return value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|entry| match entry.as_str() {
+                Some(vote_pubkey) => vote_pubkey
+                    .parse::<Pubkey>()
+                    .map(|vote_pubkey| FleetMember::new(vote_pubkey, None))
+                    .map_err(|_| format!("Invalid vote pubkey {}", vote_pubkey)),
+                None => FleetMember::from_json(entry),
+            })
+            .collect();
+    }
+
+    let mut members = vec![];
+    let mut first_line = true;
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line
+            .splitn(2, ',')
+            .map(|field| field.trim().trim_matches('"').trim());
+        let vote_pubkey = fields.next().unwrap_or_default();
+        let label = fields
+            .next()
+            .filter(|label| !label.is_empty())
+            .map(|label| label.to_string());
+        match vote_pubkey.parse::<Pubkey>() {
+            Ok(vote_pubkey) => members.push(FleetMember::new(vote_pubkey, label)),
+            // Header line
+            Err(_) if first_line => {}
+            Err(_) => {
+                return Err(format!(
+                    "Invalid vote pubkey {} on line {}",
+                    vote_pubkey,
+                    i + 1
+                ))
+            }
+        }
+        first_line = false;
+    }
+    Ok(members)
+}
+
+/// Loads the watchlist at `source`, fetched if it is an HTTP(S) URL and read from disk otherwise,
+/// see `parse_watchlist`. An unreadable file or invalid watchlist fails with `Error::Config`
+pub async fn load_watchlist(
+    client: &Client,
+    source: &str,
+) -> Result<Vec<FleetMember>, Box<dyn std::error::Error>> {
+    let contents = if source.starts_with("http://") || source.starts_with("https://") {
+        client
+            .get(source)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?
+    } else {
+        std::fs::read_to_string(source)
+            .map_err(|err| Error::Config(format!("Unable to read {}: {}", source, err)))?
+    };
+    Ok(parse_watchlist(&contents).map_err(|err| Error::Config(format!("{}: {}", source, err)))?)
+}
+
+/// The current state of a fleet member
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberStatus {
+    pub vote_pubkey: Pubkey,
+    pub activated_stake: Lamports,
+    pub delinquent: bool,
+    /// Percentage of leader slots skipped so far in the current epoch, `None` without leader
+    /// slots
+    pub skip_rate: Option<f64>,
+}
+
+/// Aggregate state of several fleet members
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FleetMetrics {
+    pub validators: usize,
+    pub total_stake: Lamports,
+    /// Highest skip rate and the validator it belongs to
+    pub worst_skip_rate: Option<(Pubkey, f64)>,
+    /// Validators without leader slots so far, whose skip rate is undefined
+    pub without_leader_slots: Vec<Pubkey>,
+    pub delinquent: Vec<Pubkey>,
+}
+
+impl FleetMetrics {
+    pub fn new<'a, I>(statuses: I) -> Self
+    where
+        I: IntoIterator<Item = &'a MemberStatus>,
+    {
+        let mut metrics = Self::default();
+        for status in statuses {
+            metrics.add(status);
+        }
+        metrics
+    }
+
+    fn add(&mut self, status: &MemberStatus) {
+        self.validators += 1;
+        self.total_stake =
+            Lamports(self.total_stake.lamports() + status.activated_stake.lamports());
+        match status.skip_rate {
+            Some(skip_rate)
+                if self
+                    .worst_skip_rate
+                    .is_none_or(|(_, worst_skip_rate)| skip_rate > worst_skip_rate) =>
+            {
+                self.worst_skip_rate = Some((status.vote_pubkey, skip_rate));
+            }
+            Some(_) => {}
+            // A delinquent member missing from the vote accounts has no block production either
+            None if !status.delinquent => self.without_leader_slots.push(status.vote_pubkey),
+            None => {}
+        }
+        if status.delinquent {
+            self.delinquent.push(status.vote_pubkey);
+        }
+    }
+
+    pub fn any_delinquent(&self) -> bool {
+        !self.delinquent.is_empty()
+    }
+}
+
+impl fmt::Display for FleetMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} validators, {} staked, worst skip rate {}, {} without leader slots, {} delinquent",
+            self.validators,
+            self.total_stake,
+            self.worst_skip_rate
+                .map(|(vote_pubkey, skip_rate)| format!("{:.2}% ({})", skip_rate, vote_pubkey))
+                .unwrap_or_else(|| "-".into()),
+            self.without_leader_slots.len(),
+            self.delinquent.len()
+        )
+    }
+}
+
+/// Returns the status of every member of `fleet`. Members missing from `vote_accounts` are
+/// reported as delinquent
+pub async fn get_fleet_status(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    block_production_cache: &mut BlockProductionCache,
+    vote_accounts: &VoteAccountsSnapshot,
+    fleet: &Fleet,
+) -> Result<Vec<MemberStatus>, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let first_slot_in_epoch = epoch_info.absolute_slot - epoch_info.slot_index;
+
+    let mut statuses = vec![];
+    for member in &fleet.members {
+        let vai = match vote_accounts.find_by_vote(&member.vote_pubkey) {
+            Some(vai) => vai,
+            None => {
+                // A vote account missing from `getVoteAccounts` isn't voting at all
+                statuses.push(MemberStatus {
+                    vote_pubkey: member.vote_pubkey,
+                    activated_stake: Lamports::default(),
+                    delinquent: true,
+                    skip_rate: None,
+                });
+                continue;
+            }
+        };
+        let identity = vote_accounts
+            .identity_of(&member.vote_pubkey)
+            .ok_or_else(|| format!("Invalid identity {}", vai.node_pubkey))?;
+        let block_production = block_production_cache
+            .get_block_production(
+                rpc_client,
+                &identity,
+                first_slot_in_epoch,
+                epoch_info.absolute_slot,
+            )
+            .await?;
+        statuses.push(MemberStatus {
+            vote_pubkey: member.vote_pubkey,
+            activated_stake: Lamports(vai.activated_stake),
+            delinquent: vote_accounts.is_delinquent(&member.vote_pubkey),
+            skip_rate: block_production.skip_rate(),
+        });
+    }
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn test_parse_watchlist_csv() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let members = parse_watchlist(&format!(
+            "vote_pubkey,label\n# comment\n\n{}, \"Validator A\"\n{}\n",
+            a, b
+        ))
+        .unwrap();
+        assert_eq!(
+            members,
+            vec![
+                FleetMember::new(a, Some("Validator A".to_string())),
+                FleetMember::new(b, None),
+            ]
+        );
+        assert_eq!(members[1].label, b.to_string());
+
+        // Without a header
+        assert_eq!(
+            parse_watchlist(&a.to_string()).unwrap(),
+            vec![FleetMember::new(a, None)]
+        );
+        assert_eq!(
+            parse_watchlist(&format!("{}\nnot-a-pubkey", a)),
+            Err("Invalid vote pubkey not-a-pubkey on line 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_watchlist_json() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(
+            parse_watchlist(&json!([a.to_string(), b.to_string()]).to_string()).unwrap(),
+            vec![FleetMember::new(a, None), FleetMember::new(b, None)]
+        );
+
+        let members = vec![
+            FleetMember::new(a, None),
+            FleetMember {
+                region: Some("fra".to_string()),
+                group: Some("eu".to_string()),
+                ..FleetMember::new(b, Some("Validator B".to_string()))
+            },
+        ];
+        let json_members = json!([
+            {"votePubkey": a.to_string()},
+            {"votePubkey": b.to_string(), "label": "Validator B", "region": "fra", "group": "eu"},
+        ]);
+        assert_eq!(parse_watchlist(&json_members.to_string()).unwrap(), members);
+        assert_eq!(
+            parse_watchlist(&json!({"validators": json_members}).to_string()).unwrap(),
+            members
+        );
+
+        assert!(parse_watchlist(&json!([{"label": "Validator A"}]).to_string()).is_err());
+        assert!(parse_watchlist(&json!({"members": []}).to_string()).is_err());
+        assert!(parse_watchlist("[").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Detection of votes cast on slots that were later abandoned on a fork
+use {
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    solana_vote_program::vote_state::VoteState,
+    std::fmt,
+};
+
+/// Votes of a validator's tower, checked against the finalized chain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForkVoteStats {
+    pub vote_pubkey: Pubkey,
+    /// Number of voted slots that are at or below the finalized slot
+    pub votes: usize,
+    /// Voted slots with no finalized block, i.e. skipped or abandoned on a fork
+    pub fork_votes: Vec<Slot>,
+}
+
+impl ForkVoteStats {
+    /// Percentage of `votes` that were cast on slots later abandoned
+    pub fn fork_vote_percent(&self) -> f64 {
+        self.fork_votes.len() as f64 * 100. / self.votes.max(1) as f64
+    }
+}
+
+impl fmt::Display for ForkVoteStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} of {} recent votes ({:.2}%) on slots that were not finalized",
+            self.vote_pubkey,
+            self.fork_votes.len(),
+            self.votes,
+            self.fork_vote_percent()
+        )?;
+        if !self.fork_votes.is_empty() {
+            write!(
+                f,
+                " ({})",
+                self.fork_votes
+                    .iter()
+                    .map(|slot| slot.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the slots of `voted_slots` at or below `finalized_slot` that are missing from the
+/// sorted `finalized_blocks`, together with the number of voted slots that were checked
+pub fn fork_votes(
+    voted_slots: &[Slot],
+    finalized_blocks: &[Slot],
+    finalized_slot: Slot,
+) -> (usize, Vec<Slot>) {
+    let checked = voted_slots
+        .iter()
+        .copied()
+        .filter(|slot| *slot <= finalized_slot)
+        .collect::<Vec<_>>();
+    let fork_votes = checked
+        .iter()
+        .copied()
+        .filter(|slot| finalized_blocks.binary_search(slot).is_err())
+        .collect();
+    (checked.len(), fork_votes)
+}
+
+/// Checks the votes in the tower of `vote_pubkey` against the finalized blocks
+pub async fn get_fork_vote_stats(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+) -> Result<ForkVoteStats, Box<dyn std::error::Error>> {
+    let vote_account = rpc_client.get_account(vote_pubkey).await?;
+    let vote_state = VoteState::deserialize(&vote_account.data)
+        .map_err(|err| format!("Invalid vote account {}: {}", vote_pubkey, err))?;
+    let voted_slots = vote_state
+        .votes
+        .iter()
+        .map(|lockout| lockout.slot)
+        .collect::<Vec<_>>();
+
+    let (first_slot, last_slot) = match (voted_slots.first(), voted_slots.last()) {
+        (Some(first_slot), Some(last_slot)) => (*first_slot, *last_slot),
+        _ => {
+            return Ok(ForkVoteStats {
+                vote_pubkey: *vote_pubkey,
+                ..ForkVoteStats::default()
+            })
+        }
+    };
+
+    let finalized_slot = rpc_client.get_slot().await?;
+    let finalized_blocks = rpc_client
+        .get_blocks(first_slot, Some(last_slot.min(finalized_slot)))
+        .await?;
+
+    let (votes, fork_votes) = fork_votes(&voted_slots, &finalized_blocks, finalized_slot);
+    Ok(ForkVoteStats {
+        vote_pubkey: *vote_pubkey,
+        votes,
+        fork_votes,
+    })
+}
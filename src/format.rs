@@ -0,0 +1,289 @@
+//! Formatting of amounts and dates for reports and notifications
+use {crate::slot_clock::UtcDateTime, solana_sdk::native_token::lamports_to_sol, std::env};
+
+/// Returns the first non-empty of the environment variables `names`
+fn locale_from_env(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+/// Splits a POSIX locale name such as `de_DE.UTF-8` into its language and territory
+fn locale_parts(locale: &str) -> Vec<&str> {
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    locale.split(['_', '-']).collect()
+}
+
+/// Separators used when formatting numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub thousands_separator: char,
+    pub decimal_separator: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::EN
+    }
+}
+
+impl NumberFormat {
+    /// 1,234,567.89
+    pub const EN: Self = Self {
+        thousands_separator: ',',
+        decimal_separator: '.',
+    };
+    /// 1.234.567,89
+    pub const DE: Self = Self {
+        thousands_separator: '.',
+        decimal_separator: ',',
+    };
+    /// 1 234 567,89, with narrow no-break spaces
+    pub const FR: Self = Self {
+        thousands_separator: '\u{202f}',
+        decimal_separator: ',',
+    };
+    /// 1'234'567.89
+    pub const CH: Self = Self {
+        thousands_separator: '\'',
+        decimal_separator: '.',
+    };
+
+    /// Returns the format for a POSIX locale name such as `de_DE.UTF-8`. Unknown locales use
+    /// `NumberFormat::EN`
+    pub fn from_locale(locale: &str) -> Self {
+        match locale_parts(locale).as_slice() {
+            [_, "CH"] | [_, "LI"] => Self::CH,
+            ["de" | "nl" | "id" | "it" | "es" | "pt" | "da" | "tr", ..] => Self::DE,
+            ["fr" | "ru" | "pl" | "cs" | "sv" | "fi" | "nb" | "uk", ..] => Self::FR,
+            _ => Self::EN,
+        }
+    }
+
+    /// Returns the format for the locale in `LC_ALL`, `LC_NUMERIC` or `LANG`
+    pub fn from_env() -> Self {
+        locale_from_env(&["LC_ALL", "LC_NUMERIC", "LANG"])
+            .map(|locale| Self::from_locale(&locale))
+            .unwrap_or_default()
+    }
+
+    /// 412345 -> 412,345
+    pub fn format_integer(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut formatted = String::with_capacity(digits.len() * 4 / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                formatted.push(self.thousands_separator);
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+
+    /// 412345.678 with 2 decimals -> 412,345.68
+    pub fn format_decimal(&self, n: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, n.abs());
+        let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        let mut result = String::new();
+        if n < 0. && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            result.push('-');
+        }
+        result.push_str(&self.format_integer(integer.parse::<u64>().unwrap_or_default()));
+        if !fraction.is_empty() {
+            result.push(self.decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+
+    /// An amount of lamports in SOL, for example ◎1,234.57
+    pub fn format_sol(&self, lamports: u64, decimals: usize) -> String {
+        format!(
+            "◎{}",
+            self.format_decimal(lamports_to_sol(lamports), decimals)
+        )
+    }
+
+    /// Abbreviates large numbers to one decimal: 412345 -> 412.3k, 2500000 -> 2.5M. Numbers
+    /// below 1000 are rounded to an integer
+    pub fn abbreviate(&self, n: f64) -> String {
+        const SUFFIXES: [(f64, &str); 4] = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "k")];
+
+        for (scale, suffix) in SUFFIXES {
+            // Rounded like the next smaller unit displays it before comparing, so that 999950
+            // becomes 1.0M rather than 1000.0k while 950000 stays 950.0k
+            let smaller_unit_step = if scale > 1e3 { scale / 1e4 } else { 1. };
+            if (n.abs() / smaller_unit_step).round() * smaller_unit_step >= scale {
+                let scaled = (n.abs() / scale * 10.).round() / 10.;
+                return format!("{}{}", self.format_decimal(n.signum() * scaled, 1), suffix);
+            }
+        }
+        self.format_decimal(n, 0)
+    }
+}
+
+/// Order of the day, month and year in a date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    YearMonthDay,
+    DayMonthYear,
+    MonthDayYear,
+}
+
+/// How dates and times of day are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTimeFormat {
+    pub date_order: DateOrder,
+    pub date_separator: char,
+    /// 3:04:05 PM rather than 15:04:05
+    pub hour12: bool,
+}
+
+impl Default for DateTimeFormat {
+    fn default() -> Self {
+        Self::ISO
+    }
+}
+
+impl DateTimeFormat {
+    /// 2024-07-01 15:04:05
+    pub const ISO: Self = Self {
+        date_order: DateOrder::YearMonthDay,
+        date_separator: '-',
+        hour12: false,
+    };
+    /// 07/01/2024 3:04:05 PM
+    pub const US: Self = Self {
+        date_order: DateOrder::MonthDayYear,
+        date_separator: '/',
+        hour12: true,
+    };
+    /// 01/07/2024 15:04:05
+    pub const GB: Self = Self {
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '/',
+        hour12: false,
+    };
+    /// 01.07.2024 15:04:05
+    pub const DE: Self = Self {
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '.',
+        hour12: false,
+    };
+
+    /// Returns the format for a POSIX locale name such as `en_US.UTF-8`. Unknown locales use
+    /// `DateTimeFormat::ISO`
+    pub fn from_locale(locale: &str) -> Self {
+        match locale_parts(locale).as_slice() {
+            ["en", "US" | "PH"] => Self::US,
+            ["en" | "fr" | "es" | "it" | "pt" | "el", _] => Self::GB,
+            ["de" | "ru" | "pl" | "cs" | "fi" | "nb" | "da" | "tr" | "uk", ..] => Self::DE,
+            _ => Self::ISO,
+        }
+    }
+
+    /// Returns the format for the locale in `LC_ALL`, `LC_TIME` or `LANG`
+    pub fn from_env() -> Self {
+        locale_from_env(&["LC_ALL", "LC_TIME", "LANG"])
+            .map(|locale| Self::from_locale(&locale))
+            .unwrap_or_default()
+    }
+
+    /// Formats `date_time`, followed by UTC
+    pub fn format(&self, date_time: &UtcDateTime) -> String {
+        let separator = self.date_separator;
+        let date = match self.date_order {
+            DateOrder::YearMonthDay => format!(
+                "{:04}{}{:02}{}{:02}",
+                date_time.year, separator, date_time.month, separator, date_time.day
+            ),
+            DateOrder::DayMonthYear => format!(
+                "{:02}{}{:02}{}{:04}",
+                date_time.day, separator, date_time.month, separator, date_time.year
+            ),
+            DateOrder::MonthDayYear => format!(
+                "{:02}{}{:02}{}{:04}",
+                date_time.month, separator, date_time.day, separator, date_time.year
+            ),
+        };
+        let time = if self.hour12 {
+            format!(
+                "{}:{:02}:{:02} {}",
+                (date_time.hour + 11) % 12 + 1,
+                date_time.minute,
+                date_time.second,
+                if date_time.hour < 12 { "AM" } else { "PM" }
+            )
+        } else {
+            format!(
+                "{:02}:{:02}:{:02}",
+                date_time.hour, date_time.minute, date_time.second
+            )
+        };
+        format!("{} {} UTC", date, time)
+    }
+}
+
+/// Number and date formats of a report. Each sink, such as the terminal or a notification
+/// channel, can render reports in its own locale
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Locale {
+    pub number_format: NumberFormat,
+    pub date_time_format: DateTimeFormat,
+}
+
+impl Locale {
+    /// Returns the formats for a POSIX locale name such as `de_DE.UTF-8`
+    pub fn from_locale(locale: &str) -> Self {
+        Self {
+            number_format: NumberFormat::from_locale(locale),
+            date_time_format: DateTimeFormat::from_locale(locale),
+        }
+    }
+
+    /// Returns the formats for the locale of the environment, as `NumberFormat::from_env` and
+    /// `DateTimeFormat::from_env` pick it
+    pub fn from_env() -> Self {
+        Self {
+            number_format: NumberFormat::from_env(),
+            date_time_format: DateTimeFormat::from_env(),
+        }
+    }
+
+    pub fn format_date_time(&self, date_time: &UtcDateTime) -> String {
+        self.date_time_format.format(date_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_decimal() {
+        assert_eq!(NumberFormat::EN.format_decimal(412345.678, 2), "412,345.68");
+        assert_eq!(NumberFormat::DE.format_decimal(412345.678, 2), "412.345,68");
+        assert_eq!(NumberFormat::EN.format_decimal(-1234.5, 1), "-1,234.5");
+        assert_eq!(NumberFormat::EN.format_decimal(999.999, 2), "1,000.00");
+        assert_eq!(NumberFormat::EN.format_decimal(0.4, 0), "0");
+        // Values that round to zero aren't negative
+        assert_eq!(NumberFormat::EN.format_decimal(-0.004, 2), "0.00");
+    }
+
+    #[test]
+    fn test_abbreviate() {
+        let number_format = NumberFormat::EN;
+        assert_eq!(number_format.abbreviate(0.), "0");
+        assert_eq!(number_format.abbreviate(999.4), "999");
+        assert_eq!(number_format.abbreviate(412_345.), "412.3k");
+        assert_eq!(number_format.abbreviate(2_500_000.), "2.5M");
+        assert_eq!(number_format.abbreviate(999_950.), "1.0M");
+        assert_eq!(number_format.abbreviate(950_000.), "950.0k");
+        assert_eq!(number_format.abbreviate(999_949.), "999.9k");
+        assert_eq!(number_format.abbreviate(999.96), "1.0k");
+        assert_eq!(number_format.abbreviate(-412_345.), "-412.3k");
+        assert_eq!(number_format.abbreviate(-999_950.), "-1.0M");
+        assert_eq!(number_format.abbreviate(1.5e15), "1,500.0T");
+        assert_eq!(NumberFormat::DE.abbreviate(2_500_000.), "2,5M");
+    }
+}
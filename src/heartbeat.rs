@@ -0,0 +1,31 @@
+//! Heartbeat pings to dead man's switch services such as Healthchecks.io or Dead Man's Snitch,
+//! which alert when the pings stop because the monitor itself died
+use {log::*, reqwest::Client, std::time::Duration};
+
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Heartbeat {
+    client: Client,
+    url: String,
+}
+
+impl Heartbeat {
+    pub fn new(url: String, client: Client) -> Self {
+        Self { client, url }
+    }
+
+    /// Pings the heartbeat URL. Failures are only logged, a missed ping is exactly what the
+    /// service alerts on
+    pub async fn ping(&self) {
+        let result = self
+            .client
+            .get(&self.url)
+            .timeout(HEARTBEAT_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(err) = result {
+            warn!("Heartbeat ping to {} failed: {}", self.url, err);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! An RPC transport over an HTTP client built by the caller, so RPC requests can go through a
+//! proxy and carry custom headers, such as the `Authorization` header most paid RPC providers
+//! require
+use {
+    async_trait::async_trait,
+    log::*,
+    reqwest::{
+        header::{HeaderName, HeaderValue, RETRY_AFTER},
+        Client, ClientBuilder, Proxy, Response, StatusCode,
+    },
+    serde_json::{json, Value},
+    solana_client::{
+        client_error::Result as ClientResult,
+        nonblocking::rpc_client::RpcClient,
+        rpc_client::RpcClientConfig,
+        rpc_custom_error::{
+            NodeUnhealthyErrorData, JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+            JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+        },
+        rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+        rpc_response::RpcSimulateTransactionResult,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    },
+    solana_sdk::commitment_config::CommitmentConfig,
+    std::{
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    },
+    tokio::time::sleep,
+};
+
+/// Parses a `NAME: VALUE` header, as given to `curl --header`
+pub fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid header {}, expected NAME: VALUE", s))?;
+    Ok((
+        name.trim()
+            .parse::<HeaderName>()
+            .map_err(|err| format!("Invalid header name {}: {}", name, err))?,
+        value
+            .trim()
+            .parse::<HeaderValue>()
+            .map_err(|err| format!("Invalid header value for {}: {}", name, err))?,
+    ))
+}
+
+/// Builds an HTTP client that sends every request through `proxy`, if any. Without a proxy the
+/// HTTP_PROXY and HTTPS_PROXY environment variables are used
+pub fn http_client_builder(proxy: Option<&Proxy>) -> ClientBuilder {
+    let builder = Client::builder();
+    match proxy {
+        Some(proxy) => builder.proxy(proxy.clone()),
+        None => builder,
+    }
+}
+
+/// Sends JSON RPC requests over HTTP like the client's default transport, retrying rate limited
+/// requests, but with `client` and its proxy, default headers and timeout
+pub struct HttpSenderWithClient {
+    client: Client,
+    url: String,
+    request_id: AtomicU64,
+}
+
+impl HttpSenderWithClient {
+    pub fn new(url: String, client: Client) -> Self {
+        Self {
+            client,
+            url,
+            request_id: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Retries of a request the RPC node rejected with 429 Too Many Requests
+const TOO_MANY_REQUESTS_RETRIES: usize = 5;
+/// Pause before retrying a rate limited request, unless the node sent a shorter `Retry-After`
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_millis(500);
+/// Longest `Retry-After` honored, in seconds
+const MAX_RETRY_AFTER_SECS: u64 = 120;
+
+/// Parses a JSON RPC error object like the client's default transport does, including the `data`
+/// of preflight failures and unhealthy node errors
+fn rpc_error_from_json(error: &Value) -> RpcError {
+    let code = error.get("code").and_then(|code| code.as_i64());
+    let message = error.get("message").and_then(|message| message.as_str());
+    let (code, message) = match (code, message) {
+        (Some(code), Some(message)) => (code, message.to_string()),
+        _ => {
+            return RpcError::RpcRequestError(format!(
+                "Failed to deserialize RPC error response: {}",
+                error
+            ))
+        }
+    };
+
+    let data = error.get("data").cloned().unwrap_or_default();
+    let data = match code {
+        JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE => {
+            serde_json::from_value::<RpcSimulateTransactionResult>(data)
+                .map(RpcResponseErrorData::SendTransactionPreflightFailure)
+                .unwrap_or(RpcResponseErrorData::Empty)
+        }
+        JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY => {
+            serde_json::from_value::<NodeUnhealthyErrorData>(data)
+                .map(|data| RpcResponseErrorData::NodeUnhealthy {
+                    num_slots_behind: data.num_slots_behind,
+                })
+                .unwrap_or(RpcResponseErrorData::Empty)
+        }
+        _ => RpcResponseErrorData::Empty,
+    };
+    RpcError::RpcResponseError {
+        code,
+        message,
+        data,
+    }
+}
+
+/// How long the node asked to wait before retrying, if it's reasonable
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .filter(|retry_after| *retry_after < MAX_RETRY_AFTER_SECS)
+        .map(Duration::from_secs)
+}
+
+#[async_trait]
+impl RpcSender for HttpSenderWithClient {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let request_id = self.request_id.fetch_add(1, Ordering::Relaxed);
+        let request_json = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": request.to_string(),
+            "params": params,
+        });
+
+        let mut too_many_requests_retries = TOO_MANY_REQUESTS_RETRIES;
+        loop {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&request_json)
+                .send()
+                .await?;
+
+            let status_error = response.error_for_status_ref().err();
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && too_many_requests_retries > 0 {
+                let duration = retry_after(&response).unwrap_or(DEFAULT_RETRY_AFTER);
+                too_many_requests_retries -= 1;
+                debug!(
+                    "Too many requests to {}, {} retries left, pausing for {:?}",
+                    self.url, too_many_requests_retries, duration
+                );
+                sleep(duration).await;
+                continue;
+            }
+
+            // Nodes report some errors, such as an unhealthy node, with both an HTTP error status
+            // and a JSON RPC error, which is more useful
+            let mut response = match (response.json::<Value>().await, status_error) {
+                (Ok(response), _) if response["error"].is_object() => {
+                    return Err(rpc_error_from_json(&response["error"]).into())
+                }
+                (_, Some(status_error)) => return Err(status_error.into()),
+                (response, None) => response?,
+            };
+            return Ok(response["result"].take());
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+/// Builds an `RpcClient` for `url` that sends its requests with `client`
+pub fn rpc_client_with_http_client(
+    url: String,
+    client: Client,
+    commitment: CommitmentConfig,
+) -> RpcClient {
+    RpcClient::new_sender(
+        HttpSenderWithClient::new(url, client),
+        RpcClientConfig::with_commitment(commitment),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_error_from_json() {
+        assert!(matches!(
+            rpc_error_from_json(&json!({
+                "code": JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+                "message": "Node is behind by 42 slots",
+                "data": {"numSlotsBehind": 42},
+            })),
+            RpcError::RpcResponseError {
+                code: JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+                data: RpcResponseErrorData::NodeUnhealthy {
+                    num_slots_behind: Some(42)
+                },
+                ..
+            }
+        ));
+        assert!(matches!(
+            rpc_error_from_json(&json!({"code": -32601, "message": "Method not found"})),
+            RpcError::RpcResponseError {
+                code: -32601,
+                data: RpcResponseErrorData::Empty,
+                ..
+            }
+        ));
+        assert!(matches!(
+            rpc_error_from_json(&json!({"message": "Method not found"})),
+            RpcError::RpcRequestError(_)
+        ));
+    }
+}
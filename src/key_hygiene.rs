@@ -0,0 +1,167 @@
+//! Checks of how a validator's keys are used, surfacing setups where a leaked identity keypair,
+//! which has to live on the validator host, would also give away funds
+use {
+    crate::stake_pools::STAKE_WITHDRAWER_OFFSET,
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
+    },
+    solana_sdk::{clock::Epoch, pubkey::Pubkey, stake, vote},
+    solana_vote_program::vote_state::VoteState,
+    std::fmt,
+};
+
+/// Offset of `VoteState::authorized_withdrawer` within a vote account
+const VOTE_WITHDRAWER_OFFSET: usize = 36;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyHygieneIssue {
+    /// The identity is the withdraw authority of a vote account
+    IdentityWithdrawsVoteAccount {
+        identity: Pubkey,
+        vote_pubkey: Pubkey,
+    },
+    /// The identity is the withdraw authority of a stake account
+    IdentityWithdrawsStakeAccount {
+        identity: Pubkey,
+        stake_pubkey: Pubkey,
+    },
+    /// The authorized voter was last changed in `last_rotation_epoch`, `None` if it was never
+    /// changed
+    VoterNotRotated {
+        authorized_voter: Pubkey,
+        last_rotation_epoch: Option<Epoch>,
+    },
+}
+
+impl fmt::Display for KeyHygieneIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyHygieneIssue::IdentityWithdrawsVoteAccount {
+                identity,
+                vote_pubkey,
+            } => write!(
+                f,
+                "Identity {} is the withdraw authority of vote account {}, use a cold keypair",
+                identity, vote_pubkey
+            ),
+            KeyHygieneIssue::IdentityWithdrawsStakeAccount {
+                identity,
+                stake_pubkey,
+            } => write!(
+                f,
+                "Identity {} is the withdraw authority of stake account {}, use a cold keypair",
+                identity, stake_pubkey
+            ),
+            KeyHygieneIssue::VoterNotRotated {
+                authorized_voter,
+                last_rotation_epoch: Some(last_rotation_epoch),
+            } => write!(
+                f,
+                "Authorized voter {} has not been rotated since epoch {}",
+                authorized_voter, last_rotation_epoch
+            ),
+            KeyHygieneIssue::VoterNotRotated {
+                authorized_voter,
+                last_rotation_epoch: None,
+            } => write!(
+                f,
+                "Authorized voter {} has never been rotated",
+                authorized_voter
+            ),
+        }
+    }
+}
+
+async fn get_accounts_withdrawable_by(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    withdrawer_offset: usize,
+    withdrawer: &Pubkey,
+) -> Result<Vec<Pubkey>, Box<dyn std::error::Error>> {
+    Ok(rpc_client
+        .get_program_accounts_with_config(
+            program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    withdrawer_offset,
+                    withdrawer.as_ref(),
+                ))]),
+                account_config: RpcAccountInfoConfig {
+                    commitment: Some(rpc_client.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .map(|(pubkey, _)| pubkey)
+        .collect())
+}
+
+/// Returns the key hygiene issues of the validator voting with `vote_pubkey`: every vote or stake
+/// account its identity can withdraw from, and an authorized voter that has not been rotated in
+/// more than `max_voter_age_epochs` epochs.
+///
+/// Vote accounts only remember the epoch of their last 32 authorized voter changes, so a voter
+/// that was never changed is reported regardless of the age of the vote account
+pub async fn check_key_hygiene(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    max_voter_age_epochs: u64,
+) -> Result<Vec<KeyHygieneIssue>, Box<dyn std::error::Error>> {
+    let vote_account = rpc_client.get_account(vote_pubkey).await?;
+    let mut vote_state = VoteState::deserialize(&vote_account.data)
+        .map_err(|err| format!("Invalid vote account {}: {}", vote_pubkey, err))?;
+    let identity = vote_state.node_pubkey;
+    let epoch = rpc_client.get_epoch_info().await?.epoch;
+
+    let mut issues = vec![];
+    for vote_pubkey in get_accounts_withdrawable_by(
+        rpc_client,
+        &vote::program::id(),
+        VOTE_WITHDRAWER_OFFSET,
+        &identity,
+    )
+    .await?
+    {
+        issues.push(KeyHygieneIssue::IdentityWithdrawsVoteAccount {
+            identity,
+            vote_pubkey,
+        });
+    }
+    for stake_pubkey in get_accounts_withdrawable_by(
+        rpc_client,
+        &stake::program::id(),
+        STAKE_WITHDRAWER_OFFSET,
+        &identity,
+    )
+    .await?
+    {
+        issues.push(KeyHygieneIssue::IdentityWithdrawsStakeAccount {
+            identity,
+            stake_pubkey,
+        });
+    }
+
+    if let Some((_, authorized_voter)) = vote_state.authorized_voters().last() {
+        let authorized_voter = *authorized_voter;
+        // Each entry is (previous voter, first epoch, epoch it was replaced)
+        let last_rotation_epoch = vote_state
+            .prior_voters()
+            .last()
+            .map(|(_, _, end_epoch)| *end_epoch);
+        if last_rotation_epoch.is_none_or(|last_rotation_epoch| {
+            epoch.saturating_sub(last_rotation_epoch) > max_voter_age_epochs
+        }) {
+            issues.push(KeyHygieneIssue::VoterNotRotated {
+                authorized_voter,
+                last_rotation_epoch,
+            });
+        }
+    }
+
+    Ok(issues)
+}
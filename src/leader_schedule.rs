@@ -0,0 +1,259 @@
+//! Leader schedule retrieval and export
+use {
+    crate::{
+        epoch_info::EpochInfoHandle,
+        slot_clock::{SlotClock, UtcDateTime},
+    },
+    solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcLeaderScheduleConfig},
+    solana_sdk::{
+        clock::{Slot, UnixTimestamp, DEFAULT_MS_PER_SLOT},
+        pubkey::Pubkey,
+    },
+    std::{
+        collections::HashMap,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// A run of consecutive leader slots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderWindow {
+    pub first_slot: Slot,
+    pub last_slot: Slot,
+    /// Estimated Unix time, in milliseconds, of the start of `first_slot`
+    pub start_time_ms: i64,
+    /// Estimated Unix time, in milliseconds, of the end of `last_slot`
+    pub end_time_ms: i64,
+}
+
+impl LeaderWindow {
+    pub fn num_slots(&self) -> u64 {
+        self.last_slot - self.first_slot + 1
+    }
+}
+
+/// Returns a `SlotClock` anchored at the current slot, using the average slot time of the recent
+/// performance samples
+pub async fn get_slot_clock(
+    rpc_client: &RpcClient,
+) -> Result<SlotClock, Box<dyn std::error::Error>> {
+    let slot = rpc_client.get_slot().await?;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+
+    let samples = rpc_client.get_recent_performance_samples(Some(60)).await?;
+    let num_slots = samples.iter().map(|sample| sample.num_slots).sum::<u64>();
+    let period_ms = samples
+        .iter()
+        .map(|sample| u64::from(sample.sample_period_secs) * 1000)
+        .sum::<u64>();
+
+    let ms_per_slot = period_ms
+        .checked_div(num_slots)
+        .unwrap_or(DEFAULT_MS_PER_SLOT);
+    Ok(SlotClock::new(slot, now_ms, ms_per_slot))
+}
+
+/// Returns the absolute leader slots of `identity` in the current epoch
+pub async fn get_leader_slots(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    identity: &Pubkey,
+) -> Result<Vec<Slot>, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let first_slot_in_epoch = epoch_info.absolute_slot - epoch_info.slot_index;
+    get_leader_slots_in_epoch(rpc_client, first_slot_in_epoch, identity).await
+}
+
+/// Returns the sorted absolute leader slots of every identity in the epoch starting at
+/// `first_slot_in_epoch`, or only of `identity`. The leader schedule is available up to one epoch
+/// ahead
+async fn fetch_leader_slots(
+    rpc_client: &RpcClient,
+    first_slot_in_epoch: Slot,
+    identity: Option<&Pubkey>,
+) -> Result<HashMap<Pubkey, Vec<Slot>>, Box<dyn std::error::Error>> {
+    let leader_schedule = rpc_client
+        .get_leader_schedule_with_config(
+            Some(first_slot_in_epoch),
+            RpcLeaderScheduleConfig {
+                identity: identity.map(|identity| identity.to_string()),
+                commitment: Some(rpc_client.commitment()),
+            },
+        )
+        .await?
+        .ok_or_else(|| {
+            format!(
+                "Leader schedule unavailable for the epoch starting at slot {}",
+                first_slot_in_epoch
+            )
+        })?;
+
+    Ok(leader_schedule
+        .into_iter()
+        .filter_map(|(identity, slot_indices)| {
+            let mut leader_slots = slot_indices
+                .into_iter()
+                .map(|slot_index| first_slot_in_epoch + slot_index as Slot)
+                .collect::<Vec<_>>();
+            leader_slots.sort_unstable();
+            identity
+                .parse::<Pubkey>()
+                .ok()
+                .map(|identity| (identity, leader_slots))
+        })
+        .collect())
+}
+
+/// Returns the absolute leader slots of `identity` in the epoch starting at
+/// `first_slot_in_epoch`. The leader schedule is available up to one epoch ahead
+pub async fn get_leader_slots_in_epoch(
+    rpc_client: &RpcClient,
+    first_slot_in_epoch: Slot,
+    identity: &Pubkey,
+) -> Result<Vec<Slot>, Box<dyn std::error::Error>> {
+    Ok(
+        fetch_leader_slots(rpc_client, first_slot_in_epoch, Some(identity))
+            .await?
+            .remove(identity)
+            .unwrap_or_default(),
+    )
+}
+
+/// The leader schedule of every validator in one epoch, fetched with a single
+/// `getLeaderSchedule` request. Prefer it over `get_leader_slots_in_epoch` when looking up the
+/// leader slots of many validators
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeaderSchedule {
+    pub first_slot_in_epoch: Slot,
+    /// Sorted absolute leader slots per identity
+    slots_by_identity: HashMap<Pubkey, Vec<Slot>>,
+}
+
+impl LeaderSchedule {
+    /// Fetches the full leader schedule of the epoch starting at `first_slot_in_epoch`
+    pub async fn fetch(
+        rpc_client: &RpcClient,
+        first_slot_in_epoch: Slot,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            first_slot_in_epoch,
+            slots_by_identity: fetch_leader_slots(rpc_client, first_slot_in_epoch, None).await?,
+        })
+    }
+
+    /// Returns the sorted absolute leader slots of `identity`, empty if it has none
+    pub fn leader_slots(&self, identity: &Pubkey) -> &[Slot] {
+        self.slots_by_identity
+            .get(identity)
+            .map(|leader_slots| leader_slots.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Identities with at least one leader slot
+    pub fn identities(&self) -> impl Iterator<Item = &Pubkey> {
+        self.slots_by_identity.keys()
+    }
+}
+
+/// Groups sorted `leader_slots` into windows of consecutive slots
+pub fn leader_windows(leader_slots: &[Slot], slot_clock: &SlotClock) -> Vec<LeaderWindow> {
+    let mut windows: Vec<LeaderWindow> = vec![];
+    for slot in leader_slots {
+        match windows.last_mut() {
+            Some(window) if window.last_slot + 1 == *slot => {
+                window.last_slot = *slot;
+                window.end_time_ms = slot_clock.slot_time_ms(*slot + 1);
+            }
+            _ => windows.push(LeaderWindow {
+                first_slot: *slot,
+                last_slot: *slot,
+                start_time_ms: slot_clock.slot_time_ms(*slot),
+                end_time_ms: slot_clock.slot_time_ms(*slot + 1),
+            }),
+        }
+    }
+    windows
+}
+
+/// Maximum length of an iCalendar content line, in octets, excluding the line break
+const ICS_MAX_LINE_OCTETS: usize = 75;
+
+/// Folds an iCalendar content line into lines of at most `ICS_MAX_LINE_OCTETS` octets, each
+/// continuation line starting with a space (RFC 5545 section 3.1). Lines are only split between
+/// characters, so multi-octet UTF-8 sequences stay intact
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_octets = 0;
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > ICS_MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+    folded
+}
+
+fn ics_timestamp(timestamp: UnixTimestamp) -> String {
+    let dt = UtcDateTime::from_unix_timestamp(timestamp);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+    )
+}
+
+/// Renders `leader_windows` as an iCalendar (RFC 5545) document with one event per window
+pub fn leader_windows_to_ics(identity: &Pubkey, leader_windows: &[LeaderWindow]) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as UnixTimestamp)
+        .unwrap_or_default();
+
+    let mut ics = String::new();
+    let mut line = |s: String| {
+        ics.push_str(&fold_ics_line(&s));
+        ics.push_str("\r\n");
+    };
+
+    line("BEGIN:VCALENDAR".into());
+    line("VERSION:2.0".into());
+    line(format!(
+        "PRODID:-//{}//{}//EN",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    ));
+    line(format!("X-WR-CALNAME:Leader slots for {}", identity));
+    for window in leader_windows {
+        line("BEGIN:VEVENT".into());
+        line(format!(
+            "UID:{}-{}@{}",
+            window.first_slot,
+            identity,
+            env!("CARGO_PKG_NAME")
+        ));
+        line(format!("DTSTAMP:{}", ics_timestamp(now)));
+        line(format!(
+            "DTSTART:{}",
+            ics_timestamp(window.start_time_ms.div_euclid(1000))
+        ));
+        // Round the end up so that short windows don't collapse to zero duration
+        line(format!(
+            "DTEND:{}",
+            ics_timestamp((window.end_time_ms + 999).div_euclid(1000))
+        ));
+        line(format!(
+            "SUMMARY:Leader slots {}-{} ({} slots)",
+            window.first_slot,
+            window.last_slot,
+            window.num_slots()
+        ));
+        line(format!(
+            "DESCRIPTION:{} is leader for slots {} to {}. Times are estimates",
+            identity, window.first_slot, window.last_slot
+        ));
+        line("END:VEVENT".into());
+    }
+    line("END:VCALENDAR".into());
+    ics
+}
@@ -1,21 +1,185 @@
+#[cfg(feature = "notifier")]
 mod notifier;
+#[cfg(feature = "notifier")]
+use notifier::*;
+#[cfg(feature = "notifier")]
+use solana_credit_score::digest::{Digest, DigestPeriod};
 use {
-    clap::{crate_description, crate_name, crate_version, Arg, Command},
+    clap::{crate_description, crate_name, Arg, ArgGroup, ArgMatches, Command},
+    futures::StreamExt,
     log::*,
-    notifier::*,
-    solana_clap_v3_utils::input_validators::{
-        is_parsable, is_url_or_moniker, normalize_to_url_if_moniker,
+    reqwest::{header::HeaderMap, Client},
+    serde_json::json,
+    solana_clap_v3_utils::{
+        input_parsers::{pubkey_of, pubkeys_of},
+        input_validators::{
+            is_parsable, is_pubkey, is_url_or_moniker, normalize_to_url_if_moniker,
+        },
+        keypair::signer_from_path,
     },
     solana_client::nonblocking::rpc_client::RpcClient,
+    solana_credit_score::{
+        anomaly::EwmaAnomalyDetector,
+        attestation::{add_key_rotation, KeyRotation, TrustedSigners},
+        block_production_cache::{BlockProductionCache, DEFAULT_LIVE_TTL_MS},
+        check::{check_validator, CheckResult, CheckStatus, CheckThresholds},
+        cluster_halt::RootStallDetector,
+        cluster_versions::{
+            get_client_diversity, get_cluster_versions, SoftwareVersion, VersionPenalty,
+        },
+        commission_history::{commission_changes, get_commission_history, EpochCommissionsCache},
+        commission_income::CommissionIncomeLedger,
+        compare::compare_validators,
+        credit_stall::CreditStallDetector,
+        epoch_info::EpochInfoHandle,
+        error::{with_timeout, Error},
+        exit_code::ExitCode,
+        feature_gates::{
+            credit_affecting_features, get_credit_affecting_activations, get_feature_gates,
+            get_timely_vote_credits_activation_epoch, FeatureStatus,
+        },
+        feed::{EligibilityCriteria, ScoreFeed},
+        feed_client::{fetch_score_feeds, score_quorum, QuorumConfig},
+        fleet::{get_fleet_status, load_watchlist, Fleet, FleetMetrics},
+        forks::get_fork_vote_stats,
+        format::Locale,
+        get_mev_commissions, get_score_completeness,
+        get_validators_by_credit_score_with_vote_accounts, get_validators_by_smoothed_score,
+        get_vote_account_presence, get_vote_progress_with_commitment,
+        heartbeat::Heartbeat,
+        http_sender::{http_client_builder, parse_header, rpc_client_with_http_client},
+        key_hygiene::check_key_hygiene,
+        leader_schedule::{
+            get_leader_slots, get_slot_clock, leader_windows, leader_windows_to_ics,
+        },
+        maintenance::{plan_maintenance, MaintenancePlanConfig, QuietHours},
+        onboarding::audit_new_validator,
+        openmetrics::MetricSet,
+        publish::{publish_score_summary, score_summary, PublishOutcome, PublishTarget},
+        rank_events::{rank_events, LeaderboardPosition},
+        report::{
+            default_stake_buckets, format_stake_cohorts, format_validators_by_credit_score,
+            format_validators_by_smoothed_score, format_validators_by_yield, stake_cohorts,
+            validators_by_yield, RewardEstimate,
+        },
+        rewards::{
+            get_epoch_rewards_breakdown, get_estimated_total_epoch_reward,
+            get_stake_account_rewards, stake_account_rewards_to_csv, stake_account_rewards_to_json,
+            SLOTS_PER_YEAR,
+        },
+        schema::with_schema_version,
+        score::{
+            score_validators, EpochWeights, MissingCommission, ScoringConfig,
+            MAX_CREDITS_PER_VOTE_BEFORE_TVC, MAX_CREDITS_PER_VOTE_TVC,
+        },
+        score_epochs,
+        simulation::{synthetic_vote_accounts, SimulationConfig},
+        skips::{get_leader_slot_distribution, get_skip_events, skip_rate_history},
+        sla::{get_sla_reports, Sla},
+        slot_clock::UtcDateTime,
+        stake_activation::get_stake_activation_countdowns,
+        stake_flow::get_delegation_changes,
+        stake_pools::{get_known_stake_pools, get_pool_stake_by_vote_account},
+        test_alert::TestAlert,
+        vote_accounts::VoteAccountsSnapshot,
+        vote_latency::get_vote_latency_histograms,
+        vote_progress::{
+            RootAdvanceDetector, VoteAccountPresence, VoteAccountPresenceDetector, VoteProgress,
+        },
+        zabbix::{zabbix_send, DEFAULT_ZABBIX_PORT},
+    },
     solana_sdk::{
-        account::from_account,
-        commitment_config::CommitmentConfig,
-        inflation::Inflation,
-        native_token::Sol,
-        sysvar::stake_history::{self, StakeHistory},
+        clock::Epoch,
+        commitment_config::{CommitmentConfig, CommitmentLevel},
+        epoch_info::EpochInfo,
+        native_token::sol_to_lamports,
+        pubkey::Pubkey,
+    },
+    solana_vote_program::vote_state::MAX_EPOCH_CREDITS_HISTORY,
+    std::{
+        collections::{BTreeMap, VecDeque},
+        env,
+        path::Path,
+        time::{Duration, SystemTime, UNIX_EPOCH},
     },
 };
 
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Minimum number of slots between the samples of a credit rate
+const CREDIT_SAMPLE_SLOTS: u64 = 150;
+/// Number of the most recent events kept in the --state-file
+const STATE_RECENT_EVENTS: usize = 20;
+/// Arguments that each request a report in place of the leaderboard, only one per run
+const REPORT_ARGS: [&str; 21] = [
+    "leader_schedule_ics",
+    "skipped_slots",
+    "leader_slot_distribution",
+    "plan_maintenance",
+    "upgrade_target",
+    "feature_gates",
+    "delegation_changes",
+    "skip_rate_history",
+    "fleet",
+    "compare",
+    "sla",
+    "rewards_breakdown",
+    "commission_income",
+    "score_epochs",
+    "commission_history",
+    "audit_new_validator",
+    "key_hygiene",
+    "fork_votes",
+    "vote_latency",
+    "stake_countdown",
+    "stake_account_rewards",
+];
+
+fn parse_quiet_hours(s: &str) -> Result<QuietHours, String> {
+    let (start_hour, end_hour) = s
+        .split_once('-')
+        .ok_or_else(|| format!("Expected START-END, got {}", s))?;
+    let parse_hour = |hour: &str| {
+        hour.parse::<u32>()
+            .ok()
+            .filter(|hour| *hour < 24)
+            .ok_or_else(|| format!("Invalid hour: {}", hour))
+    };
+    Ok(QuietHours {
+        start_hour: parse_hour(start_hour)?,
+        end_hour: parse_hour(end_hour)?,
+    })
+}
+
+fn parse_percentiles(s: &str) -> Result<Vec<u8>, String> {
+    s.split(',')
+        .map(|percentile| {
+            percentile
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|percentile| *percentile <= 100)
+                .ok_or_else(|| format!("Invalid percentile: {}", percentile))
+        })
+        .collect()
+}
+
+fn parse_epoch_weights(s: &str) -> Result<EpochWeights, String> {
+    if s == "linear" {
+        return Ok(EpochWeights::Linear);
+    }
+    s.split(',')
+        .map(|weight| {
+            weight
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| *weight >= 0.)
+                .ok_or_else(|| format!("Invalid weight: {}", weight))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(EpochWeights::Explicit)
+}
+
 fn app_version() -> String {
     let tag = option_env!("GITHUB_REF")
         .and_then(|github_ref| github_ref.strip_prefix("refs/tags/").map(|s| s.to_string()));
@@ -26,12 +190,50 @@ fn app_version() -> String {
     })
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let app_version = &*app_version();
-    let matches = Command::new(crate_name!())
+/// Checks `vote_pubkey` against `thresholds` for `--check`
+async fn check_vote_account(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    thresholds: &CheckThresholds,
+) -> Result<CheckResult, Box<dyn std::error::Error>> {
+    let vote_accounts = VoteAccountsSnapshot::fetch(rpc_client).await?;
+    let identity = vote_accounts
+        .identity_of(vote_pubkey)
+        .ok_or_else(|| format!("Vote account {} not found", vote_pubkey))?;
+
+    let vote_progress =
+        get_vote_progress_with_commitment(rpc_client, vote_pubkey, CommitmentConfig::confirmed())
+            .await?;
+
+    let epoch_info = rpc_client.get_epoch_info().await?;
+    let block_production = BlockProductionCache::default()
+        .get_block_production(
+            rpc_client,
+            &identity,
+            epoch_info.absolute_slot - epoch_info.slot_index,
+            epoch_info.absolute_slot,
+        )
+        .await?;
+    let skip_rate = block_production.skip_rate();
+
+    Ok(check_validator(
+        thresholds,
+        vote_accounts.is_delinquent(vote_pubkey),
+        skip_rate,
+        vote_progress.vote_distance(),
+    ))
+}
+
+fn app(app_version: &str) -> Command<'_> {
+    Command::new(crate_name!())
         .about(crate_description!())
         .version(app_version)
+        .after_help(
+            "EXIT CODES:\n    0    OK\n    1    A --check warning threshold was breached, or \
+             --quorum was not reached\n    2    A critical threshold was breached: --check, \
+             --sla or a delinquent --fleet member\n    3    Data unavailable\n    \
+             4    Invalid arguments or configuration",
+        )
         .arg({
             let arg = Arg::new("config_file")
                 .short('C')
@@ -47,49 +249,985 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         })
         .arg(
-            Arg::new("json_rpc_url")
-                .short('u')
-                .long("url")
-                .value_name("URL")
+            Arg::new("json_rpc_url")
+                .short('u')
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .validator(|s| is_url_or_moniker(s))
+                .help("JSON RPC URL for the cluster [default: value from configuration file]"),
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .value_name("URL")
+                .takes_value(true)
+                .validator(|s| {
+                    reqwest::Proxy::all(s)
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })
+                .help("Send all HTTP requests, including RPC requests and notifications, through \
+                      this http://, https:// or socks5:// proxy [default: the HTTPS_PROXY and \
+                      HTTP_PROXY environment variables]"),
+        )
+        .arg(
+            Arg::new("rpc_header")
+                .long("rpc-header")
+                .value_name("NAME: VALUE")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .validator(|s| parse_header(s).map(|_| ()))
+                .help("Add this header to every RPC request, for example \
+                      \"Authorization: Bearer TOKEN\". May be specified multiple times"),
+        )
+        .arg(
+            Arg::new("num")
+                .short('n')
+                .long("num")
+                .value_name("N")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .help("Limit output to the top N validators [default: all validators]"),
+        )
+        .arg(
+            Arg::new("max_percentile")
+                .short('p')
+                .long("percentile")
+                .value_name("P")
+                .takes_value(true)
+                .validator(is_parsable::<u8>)
+                .default_value("0")
+                .help("Limit output to the validators in the Pth percentile [default: all validators]"),
+        )
+        .arg(
+            Arg::new("ignore_commission")
+                .short('i')
+                .long("ignore-commission")
+                .help("Ignore validator commission")
+        )
+        .arg(
+            Arg::new("missing_commission")
+                .long("missing-commission")
+                .value_name("ACTION")
+                .takes_value(true)
+                .possible_values(["skip", "current", "fail"])
+                .default_value("current")
+                .help("What to do with a validator whose commission at the start of a past \
+                      epoch is unknown, such as a vote account created during the epoch: leave \
+                      it out, use its current commission, or fail"),
+        )
+        .arg(
+            Arg::new("completeness")
+                .long("completeness")
+                .help("Mark the validators whose score is missing an input, such as their \
+                      commission at the start of the epoch or their credits in the epoch")
+        )
+        .arg(
+            Arg::new("exclude_incomplete")
+                .long("exclude-incomplete")
+                .help("Leave out the validators whose score is missing an input")
+        )
+        .arg(
+            Arg::new("min_voting_epochs")
+                .long("min-voting-epochs")
+                .value_name("EPOCHS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Leave out validators that earned their first credits less than EPOCHS \
+                      epochs ago. Vote accounts only keep the credits of their last 64 epochs"),
+        )
+        .arg(
+            Arg::new("vote_age")
+                .long("vote-age")
+                .help("Show the first epoch each validator earned credits in, for validators \
+                      younger than 64 epochs")
+        )
+        .arg(
+            Arg::new("mev")
+                .long("mev")
+                .help("Show whether each validator shares Jito MEV tips, and its MEV commission. \
+                      Only available for recent epochs")
+        )
+        .arg(
+            Arg::new("half_life")
+                .long("half-life")
+                .value_name("EPOCHS")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .help("Also rank validators by their credits averaged over --history-epochs, with \
+                      the weight of each epoch halving every EPOCHS epochs"),
+        )
+        .arg(
+            Arg::new("epoch_weights")
+                .long("epoch-weights")
+                .value_name("WEIGHTS")
+                .takes_value(true)
+                .conflicts_with("half_life")
+                .validator(|s| parse_epoch_weights(s).map(|_| ()))
+                .help("Also rank validators by their credits averaged over multiple epochs, \
+                      weighted either `linear`ly over --history-epochs or by a comma-separated \
+                      list of weights, newest epoch first"),
+        )
+        .arg(
+            Arg::new("rpc_timeout")
+                .long("rpc-timeout")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("30")
+                .help("Fail an RPC request that takes longer than SECONDS"),
+        )
+        .arg(
+            Arg::new("deadline")
+                .long("deadline")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Fail if scoring the validators takes longer than SECONDS in total"),
+        )
+        .arg(
+            Arg::new("history_epochs")
+                .long("history-epochs")
+                .value_name("NUM")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("10")
+                .help("Number of epochs, ending with EPOCH, included in --half-life and \
+                      --epoch-weights linear"),
+        )
+        .arg(
+            Arg::new("score_epochs")
+                .long("score-epochs")
+                .value_name("NUM")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Score each of the NUM epochs ending with EPOCH and show the top --num \
+                      validators of each, as the epochs complete"),
+        )
+        .arg(
+            Arg::new("score_concurrency")
+                .long("score-concurrency")
+                .value_name("NUM")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value("4")
+                .requires("score_epochs")
+                .help("Maximum number of epochs --score-epochs fetches at once"),
+        )
+        .arg(
+            Arg::new("compare")
+                .long("compare")
+                .value_names(&["VOTE_ADDRESS_A", "VOTE_ADDRESS_B"])
+                .number_of_values(2)
+                .validator(is_pubkey)
+                .help("Compare two validators side by side, with credits averaged over \
+                      --history-epochs completed epochs"),
+        )
+        .arg(
+            Arg::new("fleet")
+                .long("fleet")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("Report the aggregate state of the validators defined in this JSON fleet \
+                      file, overall and per group, alerting each group's route about its \
+                      delinquent validators"),
+        )
+        .arg(
+            Arg::new("watchlist")
+                .long("watchlist")
+                .value_name("PATH_OR_URL")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .conflicts_with_all(
+                    &REPORT_ARGS
+                        .into_iter()
+                        .filter(|arg| *arg != "fleet")
+                        .collect::<Vec<_>>(),
+                )
+                .help("Add the validators of this watchlist to the fleet, or monitor them as a \
+                      fleet without --fleet. A watchlist is a CSV file of vote accounts with \
+                      optional labels, or a JSON array of vote accounts or fleet members. \
+                      May be specified multiple times"),
+        )
+        .arg(
+            Arg::new("block_production_cache")
+                .long("block-production-cache")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("Cache block production for --compare, --fleet and --skip-rate-history in \
+                      this JSON file between runs. Results for completed epochs are kept, others expire \
+                      after a minute"),
+        )
+        .arg(
+            Arg::new("cohorts")
+                .long("cohorts")
+                .help("Also report the distribution of credits within stake buckets of <10k, \
+                      10k-100k, 100k-1M and >1M SOL"),
+        )
+        .arg(
+            Arg::new("clients")
+                .long("clients")
+                .help("Show each validator's client implementation, and the stake share of each \
+                      client"),
+        )
+        .arg(
+            Arg::new("stake_pools")
+                .long("stake-pools")
+                .help("Show the share of each validator's stake delegated by Marinade, Jito, \
+                      BlazeStake, Sanctum LSTs and SFDP. Fetches every delegated stake \
+                      account with a single getProgramAccounts request"),
+        )
+        .arg(
+            Arg::new("version_penalty")
+                .long("version-penalty")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_parsable::<u8>)
+                .help("Deduct PERCENT of the credits of validators running a version older than \
+                      the cluster majority allows, see --max-patch-releases-behind"),
+        )
+        .arg(
+            Arg::new("max_patch_releases_behind")
+                .long("max-patch-releases-behind")
+                .value_name("NUM")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("2")
+                .help("Patch releases a validator may trail the majority version by before \
+                      --version-penalty applies. Any older minor release is outdated"),
+        )
+        .arg(
+            Arg::new("locale")
+                .long("locale")
+                .value_name("LOCALE")
+                .takes_value(true)
+                .help("Write numbers and dates in the output as in LOCALE, such as de_DE \
+                      [default: the locale of the environment]"),
+        )
+        .arg(
+            Arg::new("notification_locale")
+                .long("notification-locale")
+                .value_name("LOCALE")
+                .takes_value(true)
+                .help("Write numbers and dates in notifications as in LOCALE [default: en_US \
+                      numbers and ISO 8601 dates]"),
+        )
+        .arg(
+            Arg::new("digest")
+                .long("digest")
+                .value_name("PERIOD")
+                .takes_value(true)
+                .possible_values(["daily", "epoch"])
+                .help("With --watch-root-stall or --watch-credits, send only critical events \
+                      right away and batch the others into one notification per UTC day or \
+                      per epoch"),
+        )
+        .arg(
+            Arg::new("yield_ranking")
+                .long("yield-ranking")
+                .conflicts_with("ignore_commission")
+                .help("Also rank validators by projected delegator yield, annualized")
+        )
+        .arg(
+            Arg::new("feed")
+                .long("feed")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("Write a signed JSON score feed for stake pools to PATH"),
+        )
+        .arg(
+            Arg::new("feed_min_percentile")
+                .long("feed-min-percentile")
+                .value_name("P")
+                .takes_value(true)
+                .validator(is_parsable::<u8>)
+                .default_value("0")
+                .help("Flag validators below the Pth percentile as ineligible in the score feed"),
+        )
+        .arg(
+            Arg::new("quorum_peer")
+                .long("quorum-peer")
+                .value_name("URL")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("Score feed URL of a redundant scorer. The scores are only published \
+                      and alerted on when --quorum scorers agree. --feed is written either \
+                      way, as the scorers check each other's feeds. May be specified multiple \
+                      times"),
+        )
+        .arg(
+            Arg::new("quorum")
+                .long("quorum")
+                .value_name("N")
+                .takes_value(true)
+                .requires("quorum_peer")
+                .validator(is_parsable::<usize>)
+                .help("Number of scorers, including this one, that must agree [default: a \
+                      majority of the scorers]"),
+        )
+        .arg(
+            Arg::new("quorum_tolerance")
+                .long("quorum-tolerance")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .default_value("1")
+                .help("Largest difference in a validator's score, in percent, between scorers \
+                      that agree"),
+        )
+        .arg(
+            Arg::new("quorum_signer")
+                .long("quorum-signer")
+                .value_name("PUBKEY")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .validator(is_pubkey)
+                .help("Only accept --quorum-peer feeds signed by PUBKEY. May be specified \
+                      multiple times"),
+        )
+        .arg(
+            Arg::new("quorum_signer_state")
+                .long("quorum-signer-state")
+                .value_name("PATH")
+                .takes_value(true)
+                .requires("quorum_signer")
+                .help("Remember the key rotations accepted from --quorum-signer keys in PATH, \
+                      so rotated out keys and older rotations are rejected in later runs"),
+        )
+        .arg(
+            Arg::new("rank_events")
+                .long("rank-events")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Alert when VOTE_ADDRESS entered or left the top --rank-top validators, or \
+                      crossed one of the --rank-percentiles, compared to the previous epoch"),
+        )
+        .arg(
+            Arg::new("rank_top")
+                .long("rank-top")
+                .value_name("N")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value("100")
+                .requires("rank_events")
+                .help("Size of the top of the leaderboard for --rank-events"),
+        )
+        .arg(
+            Arg::new("rank_percentiles")
+                .long("rank-percentiles")
+                .value_name("P1,P2,...")
+                .takes_value(true)
+                .validator(|s| parse_percentiles(s).map(|_| ()))
+                .default_value("50,90,99")
+                .requires("rank_events")
+                .help("Percentile boundaries for --rank-events"),
+        )
+        .arg(
+            Arg::new("publish")
+                .long("publish")
+                .help("Publish a summary of the score feed on chain, as a memo signed by --keypair"),
+        )
+        .arg(
+            Arg::new("publish_account")
+                .long("publish-account")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .requires_all(&["publish", "publish_program"])
+                .help("Write the published summary to this data account instead of a memo"),
+        )
+        .arg(
+            Arg::new("publish_program")
+                .long("publish-program")
+                .value_name("PROGRAM_ID")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .requires("publish_account")
+                .help("Program that owns the --publish-account data account"),
+        )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .requires("publish")
+                .help("Build and sign the publishing transaction without sending it"),
+        )
+        .arg(
+            Arg::new("keypair")
+                .long("keypair")
+                .value_name("KEYPAIR")
+                .takes_value(true)
+                .help("Keypair used to sign the score feed and published summaries, as a file \
+                      path, usb:// hardware wallet URL or other signer URL \
+                      [default: value from configuration file]"),
+        )
+        .arg(
+            Arg::new("previous_keypair")
+                .long("previous-keypair")
+                .value_name("KEYPAIR")
+                .takes_value(true)
+                .help("While rotating the --keypair, the keypair being replaced. It endorses the \
+                      new key in the score feed, so consumers trusting it accept the new key"),
+        )
+        .arg(
+            Arg::new("identity")
+                .long("identity")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Validator identity address for the leader schedule export"),
+        )
+        .arg(
+            Arg::new("leader_schedule_ics")
+                .long("leader-schedule-ics")
+                .value_name("PATH")
+                .takes_value(true)
+                .requires("identity")
+                .help("Write the leader slots of --identity in the current epoch to PATH as an \
+                      iCalendar file"),
+        )
+        .arg(
+            Arg::new("skipped_slots")
+                .long("skipped-slots")
+                .requires("identity")
+                .help("Report the skipped leader slots of --identity in the current epoch, noting \
+                      whether neighboring leaders skipped around the same time"),
+        )
+        .arg(
+            Arg::new("skip_rate_history")
+                .long("skip-rate-history")
+                .value_name("EPOCHS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .requires("identity")
+                .help("Report the skip rate of --identity in each of the last EPOCHS epochs, \
+                      including the current one"),
+        )
+        .arg(
+            Arg::new("leader_slot_distribution")
+                .long("leader-slot-distribution")
+                .requires("identity")
+                .help("Report how the leader slots of --identity are grouped and spread across \
+                      the current epoch, and the skip rate by slot position and epoch segment"),
+        )
+        .arg(
+            Arg::new("plan_maintenance")
+                .long("plan-maintenance")
+                .value_name("MINUTES")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .requires("identity")
+                .help("List windows of MINUTES for maintenance of --identity that avoid its \
+                      leader slots and epoch boundaries"),
+        )
+        .arg(
+            Arg::new("maintenance_deadline")
+                .long("maintenance-deadline")
+                .value_name("HOURS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("24")
+                .help("Only consider maintenance windows ending within HOURS from now"),
+        )
+        .arg(
+            Arg::new("quiet_hours")
+                .long("quiet-hours")
+                .value_name("START-END")
+                .takes_value(true)
+                .validator(|s| parse_quiet_hours(s).map(|_| ()))
+                .help("Avoid maintenance windows between these hours of the day, in UTC, e.g. 22-6"),
+        )
+        .arg(
+            Arg::new("upgrade_target")
+                .long("upgrade-target")
+                .value_name("VERSION")
+                .takes_value(true)
+                .validator(|s| {
+                    SoftwareVersion::parse(s)
+                        .map(|_| ())
+                        .ok_or_else(|| format!("Invalid version: {}", s))
+                })
+                .help("Report the stake share per software version and whether it's safe to \
+                      upgrade to VERSION"),
+        )
+        .arg(
+            Arg::new("upgrade_threshold")
+                .long("upgrade-threshold")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .default_value("66")
+                .help("Percentage of stake that must already be on --upgrade-target before \
+                      upgrading is recommended"),
+        )
+        .arg(
+            Arg::new("feature_gates")
+                .long("feature-gates")
+                .help("Report pending feature activations, with estimated activation times"),
+        )
+        .arg(
+            Arg::new("delegation_changes")
+                .long("delegation-changes")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Report stake accounts that delegated to, or started deactivating from, \
+                      VOTE_ADDRESS in the current epoch"),
+        )
+        .arg(
+            Arg::new("delegation_change_min_sol")
+                .long("delegation-change-min-sol")
+                .value_name("SOL")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .default_value("1000")
+                .help("Ignore delegation changes smaller than SOL"),
+        )
+        .arg(
+            Arg::new("stake_account")
+                .long("stake-account")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .validator(is_pubkey)
+                .help("Stake account to include in --stake-account-rewards and \
+                      --stake-countdown. May be specified multiple times"),
+        )
+        .arg(
+            Arg::new("stake_countdown")
+                .long("stake-countdown")
+                .requires("stake_account")
+                .help("Report how many epochs remain until the activating or deactivating stake \
+                      of each --stake-account is fully effective, at the current warmup and \
+                      cooldown rates"),
+        )
+        .arg(
+            Arg::new("stake_account_rewards")
+                .long("stake-account-rewards")
+                .value_name("PATH")
+                .takes_value(true)
+                .requires("stake_account")
+                .help("Write the rewards of each --stake-account per epoch to PATH, as JSON if \
+                      PATH ends in .json and as CSV otherwise"),
+        )
+        .arg(
+            Arg::new("rewards_epochs")
+                .long("rewards-epochs")
+                .value_name("NUM")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("10")
+                .help("Number of completed epochs to include in --stake-account-rewards"),
+        )
+        .arg(
+            Arg::new("rewards_breakdown")
+                .long("rewards-breakdown")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Break down the rewards of VOTE_ADDRESS in EPOCH, or the previous epoch if \
+                      EPOCH has not completed, into staker rewards, commission, block rewards \
+                      and MEV tips"),
+        )
+        .arg(
+            Arg::new("commission_income")
+                .long("commission-income")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .validator(is_pubkey)
+                .requires("commission_income_ledger")
+                .help("Record the commission income of VOTE_ADDRESS in each completed epoch in \
+                      --commission-income-ledger and show it per month. May be specified \
+                      multiple times"),
+        )
+        .arg(
+            Arg::new("commission_income_ledger")
+                .long("commission-income-ledger")
+                .value_name("PATH")
+                .takes_value(true)
+                .requires("commission_income")
+                .help("JSON file the --commission-income income is accumulated in across runs"),
+        )
+        .arg(
+            Arg::new("commission_income_epochs")
+                .long("commission-income-epochs")
+                .value_name("EPOCHS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("10")
+                .requires("commission_income")
+                .help("Number of completed epochs --commission-income fills in when missing from \
+                      the ledger"),
+        )
+        .arg(
+            Arg::new("commission_history")
+                .long("commission-history")
+                .value_name("EPOCHS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Show every commission change in the last EPOCHS epochs"),
+        )
+        .arg(
+            Arg::new("audit_new_validator")
+                .long("audit-new-validator")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Run every available check on the validator voting with VOTE_ADDRESS and \
+                      show an onboarding report, for vetting delegation program applicants"),
+        )
+        .arg(
+            Arg::new("key_hygiene")
+                .long("key-hygiene")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Warn when the identity of VOTE_ADDRESS can withdraw from any vote or stake \
+                      account, or its authorized voter has not been rotated in \
+                      --voter-rotation-epochs epochs"),
+        )
+        .arg(
+            Arg::new("voter_rotation_epochs")
+                .long("voter-rotation-epochs")
+                .value_name("EPOCHS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("180")
+                .requires("key_hygiene")
+                .help("Maximum age of the authorized voter for --key-hygiene"),
+        )
+        .arg(
+            Arg::new("fork_votes")
+                .long("fork-votes")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Report the recent votes of VOTE_ADDRESS on slots that were not finalized"),
+        )
+        .arg(
+            Arg::new("fork_vote_alert_percent")
+                .long("fork-vote-alert-percent")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .default_value("10")
+                .help("Alert when more than PERCENT of the --fork-votes votes were not finalized"),
+        )
+        .arg(
+            Arg::new("vote_latency")
+                .long("vote-latency")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Report how many slots the recent votes of VOTE_ADDRESS took to land, as a \
+                      histogram per epoch"),
+        )
+        .arg(
+            Arg::new("vote_latency_samples")
+                .long("vote-latency-samples")
+                .value_name("VOTES")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value("1000")
+                .help("Number of recent votes sampled by --vote-latency and --sla, each one \
+                      costs an RPC call"),
+        )
+        .arg(
+            Arg::new("sla")
+                .long("sla")
+                .value_name("PATH")
+                .takes_value(true)
+                .requires("sla_vote")
+                .help("Report the compliance of --sla-vote with the SLA defined in this JSON \
+                      file, {\"maxSkipRate\", \"minUptime\", \"maxVoteLatency\", \
+                      \"minCreditsPercentile\"}, in each of the last --sla-epochs completed \
+                      epochs"),
+        )
+        .arg(
+            Arg::new("sla_vote")
+                .long("sla-vote")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .requires("sla")
+                .help("Vote account checked against --sla"),
+        )
+        .arg(
+            Arg::new("sla_epochs")
+                .long("sla-epochs")
+                .value_name("EPOCHS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("5")
+                .requires("sla")
+                .help("Number of completed epochs checked against --sla"),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Run as a Nagios/Icinga plugin: print a single status line for \
+                      VOTE_ADDRESS and exit with 0 (OK), 1 (WARNING), 2 (CRITICAL) or 3 (UNKNOWN) \
+                      based on delinquency, skip rate and vote distance"),
+        )
+        .arg(
+            Arg::new("check_warn_skip_rate")
+                .long("check-warn-skip-rate")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .default_value("10")
+                .requires("check")
+                .help("--check warns when the skip rate in the current epoch exceeds PERCENT"),
+        )
+        .arg(
+            Arg::new("check_crit_skip_rate")
+                .long("check-crit-skip-rate")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .default_value("25")
+                .requires("check")
+                .help("--check is critical when the skip rate in the current epoch exceeds PERCENT"),
+        )
+        .arg(
+            Arg::new("check_warn_vote_distance")
+                .long("check-warn-vote-distance")
+                .value_name("SLOTS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("32")
+                .requires("check")
+                .help("--check warns when the last vote is more than SLOTS behind"),
+        )
+        .arg(
+            Arg::new("check_crit_vote_distance")
+                .long("check-crit-vote-distance")
+                .value_name("SLOTS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("128")
+                .requires("check")
+                .help("--check is critical when the last vote is more than SLOTS behind"),
+        )
+        .arg(
+            Arg::new("metrics_textfile")
+                .long("metrics-textfile")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("With --watch-root-stall or --watch-credits, rewrite this file in the \
+                      OpenMetrics text format after every poll, for the node_exporter textfile \
+                      collector. The name must end in .prom"),
+        )
+        .arg(
+            Arg::new("state_file")
+                .long("state-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("With --watch-root-stall or --watch-credits, rewrite this file after every \
+                      poll with a JSON dump of the monitor's state: poll counts, open incidents, \
+                      last samples, recent events and detector internals, for debugging"),
+        )
+        .arg(
+            Arg::new("zabbix_server")
+                .long("zabbix-server")
+                .value_name("HOST[:PORT]")
+                .takes_value(true)
+                .requires("zabbix_host")
+                .help("With --watch-root-stall or --watch-credits, push the metrics to this \
+                      Zabbix server or proxy after every poll"),
+        )
+        .arg(
+            Arg::new("zabbix_host")
+                .long("zabbix-host")
+                .value_name("NAME")
+                .takes_value(true)
+                .requires("zabbix_server")
+                .help("Host the --zabbix-server metrics are reported for, as configured in \
+                      Zabbix"),
+        )
+        .arg(
+            Arg::new("heartbeat_url")
+                .long("heartbeat-url")
+                .value_name("URL")
+                .takes_value(true)
+                .help("Ping this Healthchecks.io or Dead Man's Snitch URL after every successful \
+                      run, or every poll with --watch-root-stall or --watch-credits, so the \
+                      service alerts when this monitor stops"),
+        )
+        .arg(
+            Arg::new("watch_root_stall")
+                .long("watch-root-stall")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Run until interrupted, alerting when the cluster's root slot stops \
+                      advancing for SECONDS and again when it resumes"),
+        )
+        .arg(
+            Arg::new("watch_credits")
+                .long("watch-credits")
+                .value_name("VOTE_ADDRESS")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Run until interrupted, alerting when the credit rate of VOTE_ADDRESS drops \
+                      well below its recent average, even if it is not delinquent, or when it is \
+                      closed or fully unstaked"),
+        )
+        .arg(
+            Arg::new("credit_stall_samples")
+                .long("credit-stall-samples")
+                .value_name("NUM")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .default_value("12")
+                .requires("watch_credits")
+                .help("Alert when the --watch-credits credits do not increase for NUM consecutive \
+                      samples, taken every 5 seconds"),
+        )
+        .arg(
+            Arg::new("vote_root_stall")
+                .long("vote-root-stall")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u32>)
+                .default_value("60")
+                .requires("watch_credits")
+                .help("Alert when the root of the --watch-credits validator does not advance for \
+                      SECONDS while its votes keep landing"),
+        )
+        .arg(
+            Arg::new("watch_commitment")
+                .long("watch-commitment")
+                .value_name("COMMITMENT_LEVEL")
+                .takes_value(true)
+                .possible_values(["processed", "confirmed", "finalized"])
+                .default_value("confirmed")
+                .requires("watch_credits")
+                .help("Commitment level of the credits sampled by --watch-credits"),
+        )
+        .arg(
+            Arg::new("fire_test_alert")
+                .long("fire-test-alert")
+                .value_name("TYPE")
                 .takes_value(true)
-                .validator(|s| is_url_or_moniker(s))
-                .help("JSON RPC URL for the cluster [default: value from configuration file]"),
+                .possible_values(TestAlert::ALL.map(|test_alert| test_alert.name()))
+                .help("Send a test alert of TYPE through the notifier and exit, to check that \
+                      alerts reach the on-call team. With --fleet one is sent for every member, \
+                      through its group's route"),
         )
         .arg(
-            Arg::new("num")
-                .short('n')
-                .long("num")
-                .value_name("N")
+            Arg::new("test_alert_vote")
+                .long("test-alert-vote")
+                .value_name("VOTE_ADDRESS")
                 .takes_value(true)
-                .validator(|s| is_parsable::<usize>(s))
-                .help("Limit output to the top N validators [default: all validators]"),
+                .requires("fire_test_alert")
+                .validator(is_pubkey)
+                .help("Vote account the test alert is about, without --fleet"),
         )
         .arg(
-            Arg::new("max_percentile")
-                .short('p')
-                .long("percentile")
-                .value_name("P")
+            Arg::new("simulate")
+                .long("simulate")
+                .value_name("NUM_VALIDATORS")
                 .takes_value(true)
-                .validator(|s| is_parsable::<u8>(s))
-                .default_value("0")
-                .help("Limit output to the validators in the Pth percentile [default: all validators]"),
+                .validator(is_parsable::<usize>)
+                .help("Dry run: score a synthetic cluster of NUM_VALIDATORS validators, in epoch \
+                      500, without contacting the RPC node"),
         )
         .arg(
-            Arg::new("ignore_commission")
-                .short('i')
-                .long("ignore-commission")
-                .help("Ignore validator commission")
+            Arg::new("simulate_seed")
+                .long("simulate-seed")
+                .value_name("SEED")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value("0")
+                .requires("simulate")
+                .help("Seed of the --simulate cluster, the same seed produces the same cluster"),
         )
         .arg(
             Arg::new("epoch")
                 .index(1)
                 .value_name("EPOCH")
                 .takes_value(true)
-                .validator(|s| is_parsable::<i64>(s))
+                .validator(is_parsable::<i64>)
                 .help("Epoch to process. Negative values are permitted, e.g. -1 means the previous epoch \
                       [default: the current, incomplete, epoch]"),
         )
-        .get_matches();
+        .group(ArgGroup::new("report").args(&REPORT_ARGS))
+}
+
+/// The clients and settings shared by every mode
+struct Context {
+    rpc_client: RpcClient,
+    epoch_info_handle: EpochInfoHandle,
+    /// Shared by every HTTP request other than RPC requests
+    http_client: Client,
+    locale: Locale,
+    #[cfg(feature = "notifier")]
+    notifier: Notifier,
+}
+
+fn num_of(matches: &ArgMatches) -> usize {
+    matches
+        .value_of("num")
+        .map(|s| s.parse::<usize>().unwrap())
+        .unwrap_or(usize::MAX)
+}
+
+fn missing_commission_of(matches: &ArgMatches) -> MissingCommission {
+    match matches.value_of("missing_commission").unwrap() {
+        "skip" => MissingCommission::Skip,
+        "fail" => MissingCommission::Fail,
+        _ => MissingCommission::UseCurrent,
+    }
+}
+
+/// Loads the `--block-production-cache`, if any, starting afresh if it can't be read
+fn load_block_production_cache(matches: &ArgMatches) -> BlockProductionCache {
+    matches
+        .value_of("block_production_cache")
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .map(|value| BlockProductionCache::from_json(&value, DEFAULT_LIVE_TTL_MS))
+        .unwrap_or_default()
+}
+
+fn save_block_production_cache(
+    matches: &ArgMatches,
+    mut block_production_cache: BlockProductionCache,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = matches.value_of("block_production_cache") {
+        block_production_cache.prune();
+        std::fs::write(path, block_production_cache.to_json().to_string())
+            .map_err(|err| format!("Unable to write {}: {}", path, err))?;
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let exit_code = run().await.unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        ExitCode::from_error(&*err)
+    });
+    std::process::exit(exit_code.code());
+}
+
+async fn run() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let app_version = &*app_version();
+    let matches = match app(app_version).try_get_matches() {
+        Ok(matches) => matches,
+        // --help and --version
+        Err(err) if !err.use_stderr() => err.exit(),
+        Err(err) => {
+            err.print()?;
+            return Ok(ExitCode::ConfigError);
+        }
+    };
 
     let cli_config = if let Some(config_file) = matches.value_of("config_file") {
         solana_cli_config::Config::load(config_file).unwrap_or_default()
@@ -103,171 +1241,1656 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .unwrap_or(&cli_config.json_rpc_url),
     );
     let epoch = matches.value_of("epoch").map(|s| s.parse::<i64>().unwrap());
-    let num = matches
-        .value_of("num")
-        .map(|s| s.parse::<usize>().unwrap())
-        .unwrap_or(usize::MAX);
-    let max_percentile = matches
-        .value_of("max_percentile")
-        .map(|s| s.parse::<u8>().unwrap())
+    let rpc_timeout = matches
+        .value_of("rpc_timeout")
+        .map(|s| Duration::from_secs(s.parse::<u64>().unwrap()))
         .unwrap();
-    let ignore_commission = matches.is_present("ignore_commission");
+    let rpc_headers = matches
+        .values_of("rpc_header")
+        .map(|values| values.map(|s| parse_header(s).unwrap()).collect())
+        .unwrap_or_else(HeaderMap::new);
+    let fleet = matches
+        .value_of("fleet")
+        .map(|path| {
+            std::fs::read_to_string(path)
+                .map_err(|err| format!("Unable to read {}: {}", path, err))
+                .and_then(|contents| {
+                    serde_json::from_str(&contents)
+                        .map_err(|err| format!("Invalid JSON in {}: {}", path, err))
+                })
+                .and_then(|value| Fleet::from_json(&value))
+                .map_err(Error::Config)
+        })
+        .transpose()?;
+    let proxy = matches
+        .value_of("proxy")
+        .map(|s| reqwest::Proxy::all(s).unwrap());
+    let http_client = http_client_builder(proxy.as_ref()).build()?;
+    let heartbeat = matches
+        .value_of("heartbeat_url")
+        .map(|url| Heartbeat::new(url.to_string(), http_client.clone()));
+    let keypair_path = matches
+        .value_of("keypair")
+        .unwrap_or(&cli_config.keypair_path);
 
     solana_logger::setup_with_default("warn");
-    let notifier = Notifier::default();
 
-    info!("JSON RPC URL: {}", json_rpc_url);
+    let fleet = match matches.values_of("watchlist") {
+        Some(watchlists) => {
+            let mut fleet = fleet.unwrap_or_default();
+            for watchlist in watchlists {
+                let added = fleet.add_members(load_watchlist(&http_client, watchlist).await?);
+                info!("{}: {} validators added to the fleet", watchlist, added);
+            }
+            Some(fleet)
+        }
+        None => fleet,
+    };
 
-    let rpc_client =
-        RpcClient::new_with_commitment(json_rpc_url.clone(), CommitmentConfig::finalized());
+    info!("JSON RPC URL: {}", json_rpc_url);
+    let rpc_client = if rpc_headers.is_empty() && proxy.is_none() {
+        RpcClient::new_with_timeout_and_commitment(
+            json_rpc_url.clone(),
+            rpc_timeout,
+            CommitmentConfig::finalized(),
+        )
+    } else {
+        rpc_client_with_http_client(
+            json_rpc_url.clone(),
+            http_client_builder(proxy.as_ref())
+                .default_headers(rpc_headers)
+                .timeout(rpc_timeout)
+                .build()?,
+            CommitmentConfig::finalized(),
+        )
+    };
+    let context = Context {
+        rpc_client,
+        epoch_info_handle: EpochInfoHandle::default(),
+        #[cfg(feature = "notifier")]
+        notifier: Notifier::from_env(http_client.clone()),
+        http_client,
+        locale: matches
+            .value_of("locale")
+            .map(Locale::from_locale)
+            .unwrap_or_else(Locale::from_env),
+    };
 
-    let epoch_info = rpc_client.get_epoch_info().await?;
+    if matches.is_present("fire_test_alert") {
+        return process_test_alert(&context, &matches, fleet.as_ref()).await;
+    }
+    if matches.is_present("simulate") {
+        return process_simulate(&context, &matches, epoch);
+    }
+    if matches.is_present("check") {
+        return process_check(&context, &matches).await;
+    }
+    if matches.is_present("watch_root_stall") || matches.is_present("watch_credits") {
+        return process_watch(&context, &matches, app_version, heartbeat.as_ref()).await;
+    }
 
+    let epoch_info = context.epoch_info_handle.get(&context.rpc_client).await?;
     let epoch = match epoch {
         Some(epoch) if epoch < 0 => epoch_info
             .epoch
             .checked_sub(epoch.unsigned_abs())
-            .ok_or_else(|| format!("Invalid relative epoch value: {}", epoch))?,
+            .ok_or_else(|| Error::Config(format!("Invalid relative epoch value: {}", epoch)))?,
         Some(epoch) => epoch as u64,
         None => epoch_info.epoch,
     };
 
-    println!("Epoch {}", epoch);
+    // The reports are mutually exclusive, see the "report" argument group
+    let exit_code = if matches.is_present("leader_schedule_ics") {
+        process_leader_schedule_ics(&context, &matches).await?
+    } else if matches.is_present("skipped_slots") {
+        process_skipped_slots(&context, &matches).await?
+    } else if matches.is_present("leader_slot_distribution") {
+        process_leader_slot_distribution(&context, &matches).await?
+    } else if matches.is_present("plan_maintenance") {
+        process_maintenance(&context, &matches).await?
+    } else if matches.is_present("upgrade_target") {
+        process_upgrade_target(&context, &matches).await?
+    } else if matches.is_present("feature_gates") {
+        process_feature_gates(&context).await?
+    } else if matches.is_present("delegation_changes") {
+        process_delegation_changes(&context, &matches).await?
+    } else if matches.is_present("skip_rate_history") {
+        process_skip_rate_history(&context, &matches).await?
+    } else if let Some(fleet) = fleet {
+        process_fleet(&context, &matches, &fleet).await?
+    } else if matches.is_present("compare") {
+        process_compare(&context, &matches).await?
+    } else if matches.is_present("sla") {
+        process_sla(&context, &matches).await?
+    } else if matches.is_present("rewards_breakdown") {
+        process_rewards_breakdown(&context, &matches, epoch, &epoch_info).await?
+    } else if matches.is_present("commission_income") {
+        process_commission_income(&context, &matches, &epoch_info).await?
+    } else if matches.is_present("score_epochs") {
+        process_score_epochs(&context, &matches, epoch).await?
+    } else if matches.is_present("commission_history") {
+        process_commission_history(&context, &matches, &epoch_info).await?
+    } else if matches.is_present("audit_new_validator") {
+        process_audit_new_validator(&context, &matches).await?
+    } else if matches.is_present("key_hygiene") {
+        process_key_hygiene(&context, &matches).await?
+    } else if matches.is_present("fork_votes") {
+        process_fork_votes(&context, &matches).await?
+    } else if matches.is_present("vote_latency") {
+        process_vote_latency(&context, &matches).await?
+    } else if matches.is_present("stake_countdown") {
+        process_stake_countdown(&context, &matches).await?
+    } else if matches.is_present("stake_account_rewards") {
+        process_stake_account_rewards(&context, &matches, &epoch_info).await?
+    } else {
+        process_leaderboard(&context, &matches, keypair_path, epoch, &epoch_info).await?
+    };
+
+    if let Some(heartbeat) = heartbeat {
+        heartbeat.ping().await;
+    }
+    Ok(exit_code)
+}
+
+// `context` is only used to send the alerts
+#[allow(unused_variables)]
+async fn process_test_alert(
+    context: &Context,
+    matches: &ArgMatches,
+    fleet: Option<&Fleet>,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let test_alert = matches
+        .value_of("fire_test_alert")
+        .unwrap()
+        .parse::<TestAlert>()
+        .map_err(Error::Config)?;
+    match fleet {
+        Some(fleet) => {
+            for member in &fleet.members {
+                let msg = test_alert.message(&member.vote_pubkey);
+                println!("{}", msg);
+                #[cfg(feature = "notifier")]
+                Notifier::new(
+                    fleet
+                        .alert_route(&member.vote_pubkey)
+                        .map(|route| route.to_string()),
+                    context.http_client.clone(),
+                )
+                .send(&msg)
+                .await;
+            }
+        }
+        None => {
+            let msg =
+                test_alert.message(&pubkey_of(matches, "test_alert_vote").unwrap_or_default());
+            println!("{}", msg);
+            #[cfg(feature = "notifier")]
+            context.notifier.send(&msg).await;
+        }
+    }
+    Ok(ExitCode::Ok)
+}
 
-    let inflation = {
-        let rpc_inflation_governor = rpc_client.get_inflation_governor().await?;
+fn process_simulate(
+    context: &Context,
+    matches: &ArgMatches,
+    epoch: Option<i64>,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let config = SimulationConfig {
+        validators: matches
+            .value_of("simulate")
+            .map(|s| s.parse::<usize>().unwrap())
+            .unwrap(),
+        seed: matches
+            .value_of("simulate_seed")
+            .map(|s| s.parse::<u64>().unwrap())
+            .unwrap(),
+        ..SimulationConfig::default()
+    };
+    let epoch = match epoch {
+        Some(epoch) if epoch < 0 => config.epoch.saturating_sub(epoch.unsigned_abs()),
+        Some(epoch) => (epoch as u64).min(config.epoch),
+        None => config.epoch,
+    };
+    let validators_by_staker_credits = score_validators(
+        synthetic_vote_accounts(&config),
+        epoch,
+        None,
+        matches.is_present("ignore_commission"),
+        missing_commission_of(matches),
+    )?;
+    println!(
+        "Simulated cluster of {} validators, epoch {}:",
+        config.validators, epoch
+    );
+    println!(
+        "{}",
+        format_validators_by_credit_score(
+            &validators_by_staker_credits,
+            num_of(matches),
+            matches
+                .value_of("max_percentile")
+                .map(|s| s.parse::<u8>().unwrap())
+                .unwrap(),
+            None,
+            None,
+            &context.locale,
+        )
+    );
+    Ok(ExitCode::Ok)
+}
 
-        let mut inflation = Inflation::default();
-        inflation.initial = rpc_inflation_governor.initial;
-        inflation.terminal = rpc_inflation_governor.terminal;
-        inflation.taper = rpc_inflation_governor.taper;
-        inflation.foundation = rpc_inflation_governor.foundation;
-        inflation.foundation_term = rpc_inflation_governor.foundation_term;
-        inflation
+async fn process_check(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkey = pubkey_of(matches, "check").unwrap();
+    let check_thresholds = CheckThresholds {
+        warn_skip_rate: matches
+            .value_of("check_warn_skip_rate")
+            .map(|s| s.parse::<f64>().unwrap())
+            .unwrap(),
+        crit_skip_rate: matches
+            .value_of("check_crit_skip_rate")
+            .map(|s| s.parse::<f64>().unwrap())
+            .unwrap(),
+        warn_vote_distance: matches
+            .value_of("check_warn_vote_distance")
+            .map(|s| s.parse::<u64>().unwrap())
+            .unwrap(),
+        crit_vote_distance: matches
+            .value_of("check_crit_vote_distance")
+            .map(|s| s.parse::<u64>().unwrap())
+            .unwrap(),
     };
+    let check_result = check_vote_account(&context.rpc_client, &vote_pubkey, &check_thresholds)
+        .await
+        .unwrap_or_else(|err| CheckResult::unknown(err.to_string()));
+    println!("{}", check_result);
+    Ok(check_result.status.into())
+}
 
-    let stake_history = {
-        let stake_history_account = rpc_client.get_account(&stake_history::id()).await?;
-        from_account::<StakeHistory, _>(&stake_history_account)
-            .ok_or("Failed to deserialize stake history")?
+/// Polls the cluster root and the `--watch-credits` vote account until interrupted
+async fn process_watch(
+    context: &Context,
+    matches: &ArgMatches,
+    app_version: &str,
+    heartbeat: Option<&Heartbeat>,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let rpc_client = &context.rpc_client;
+    #[cfg(feature = "notifier")]
+    let notifier = &context.notifier;
+    let watch_root_stall = matches
+        .value_of("watch_root_stall")
+        .map(|s| s.parse::<u64>().unwrap());
+    let watch_credits = pubkey_of(matches, "watch_credits");
+    let credit_stall_samples = matches
+        .value_of("credit_stall_samples")
+        .map(|s| s.parse::<usize>().unwrap())
+        .unwrap();
+    let vote_root_stall = matches
+        .value_of("vote_root_stall")
+        .map(|s| s.parse::<u32>().unwrap())
+        .unwrap();
+    let watch_commitment = CommitmentConfig {
+        commitment: matches
+            .value_of("watch_commitment")
+            .map(|s| s.parse::<CommitmentLevel>().unwrap())
+            .unwrap(),
     };
+    let metrics_textfile = matches.value_of("metrics_textfile").map(Path::new);
+    let state_file = matches.value_of("state_file");
+    let zabbix_server = matches.value_of("zabbix_server").map(|server| {
+        if server.contains(':') {
+            server.to_string()
+        } else {
+            format!("{}:{}", server, DEFAULT_ZABBIX_PORT)
+        }
+    });
+    let zabbix_host = matches.value_of("zabbix_host");
+    #[cfg(feature = "notifier")]
+    let mut digest = matches
+        .value_of("digest")
+        .map(|s| Digest::new(s.parse::<DigestPeriod>().unwrap()));
 
-    let estimated_total_supply = rpc_client.supply().await?.value.total;
+    let mut root_stall_detector = watch_root_stall
+        .map(|root_stall_threshold| RootStallDetector::new(root_stall_threshold as i64 * 1000));
+    let mut credit_rate_detector = EwmaAnomalyDetector::new(0.1, 3., 10);
+    let mut credit_stall_detector = CreditStallDetector::new(credit_stall_samples);
+    let mut root_advance_detector = RootAdvanceDetector::new(i64::from(vote_root_stall) * 1000);
+    let mut vote_account_presence_detector = VoteAccountPresenceDetector::default();
+    let mut last_credits_sample = None;
+    let (mut polls, mut failed_polls) = (0u64, 0u64);
+    let mut recent_events = VecDeque::new();
+    loop {
+        let mut events = vec![];
+        let mut metric_set = MetricSet::default();
+        let mut poll_failed = false;
 
-    let slots_per_year = 78892314.984; // hard coded value from genesis.tar.gz
-    let inflation_activation_slot = 64800004; // slot when the `7XRJcS5Ud5vxGB54JbK9N2vBZVwnwdBNeJW1ibRgD9gx`
-                                              // feature was activated on mainnet
+        if let Some(root_stall_detector) = &mut root_stall_detector {
+            match rpc_client.get_slot().await {
+                Ok(root_slot) => {
+                    metric_set.gauge(
+                        "solana_credit_score_root_slot",
+                        "Root slot of the cluster",
+                        &[],
+                        root_slot as f64,
+                    );
+                    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+                    if let Some(event) = root_stall_detector.observe(root_slot, now_ms) {
+                        events.push((event.severity(), event.to_string()));
+                    }
+                }
+                Err(err) => {
+                    warn!("Failed to fetch the root slot: {}", err);
+                    poll_failed = true;
+                }
+            }
+        }
 
-    let first_slot_in_epoch = epoch_info.absolute_slot - epoch_info.slot_index;
+        // A closed or unstaked vote account is an event of its own, not missing credits
+        let vote_account_listed = match watch_credits {
+            Some(vote_pubkey) => {
+                match get_vote_account_presence(rpc_client, &vote_pubkey, watch_commitment).await {
+                    Ok(presence) => {
+                        if let Some(event) = vote_account_presence_detector.observe(presence) {
+                            let severity = event.severity();
+                            if severity == CheckStatus::Critical {
+                                events.push((
+                                    severity,
+                                    format!("{} {}: {}", severity, vote_pubkey, event),
+                                ));
+                            } else {
+                                events.push((severity, format!("{}: {}", vote_pubkey, event)));
+                            }
+                        }
+                        presence == VoteAccountPresence::Listed
+                    }
+                    Err(err) => {
+                        warn!("Failed to fetch the vote account {}: {}", vote_pubkey, err);
+                        poll_failed = true;
+                        false
+                    }
+                }
+            }
+            None => false,
+        };
 
-    let inflation_num_slots =
-        first_slot_in_epoch - (inflation_activation_slot - epoch_info.slots_in_epoch);
+        if let (Some(vote_pubkey), true) = (watch_credits, vote_account_listed) {
+            match get_vote_progress_with_commitment(rpc_client, &vote_pubkey, watch_commitment)
+                .await
+            {
+                Ok(vote_progress) => {
+                    let VoteProgress { slot, credits, .. } = vote_progress;
+                    let vote_pubkey_label = vote_pubkey.to_string();
+                    let labels = [("vote_pubkey", vote_pubkey_label.as_str())];
+                    metric_set.gauge(
+                        "solana_credit_score_vote_credits",
+                        "Total vote credits of the vote account",
+                        &labels,
+                        credits as f64,
+                    );
+                    if let Some(vote_distance) = vote_progress.vote_distance() {
+                        metric_set.gauge(
+                            "solana_credit_score_vote_distance",
+                            "Slots between the current slot and the last vote",
+                            &labels,
+                            vote_distance as f64,
+                        );
+                    }
+                    if let Some(root_distance) = vote_progress.root_distance() {
+                        metric_set.gauge(
+                            "solana_credit_score_root_distance",
+                            "Slots between the current slot and the vote account's root",
+                            &labels,
+                            root_distance as f64,
+                        );
+                    }
+                    // Every validator's credits stall while the cluster is halted
+                    let cluster_halted = root_stall_detector
+                        .as_ref()
+                        .is_some_and(|root_stall_detector| root_stall_detector.is_stalled());
+                    if !cluster_halted {
+                        if let Some(event) = credit_stall_detector.observe(credits) {
+                            events.push((event.severity(), format!("{}: {}", vote_pubkey, event)));
+                        }
+                        let now_ms =
+                            SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+                        if let Some(event) = root_advance_detector.observe(&vote_progress, now_ms) {
+                            events.push((event.severity(), format!("{}: {}", vote_pubkey, event)));
+                        }
+                    }
 
-    let inflation_rate = inflation.validator(inflation_num_slots as f64 / slots_per_year);
-    let epoch_duration_in_years = epoch_info.slots_in_epoch as f64 / slots_per_year;
-    let estimated_total_epoch_reward =
-        (inflation_rate * estimated_total_supply as f64 * epoch_duration_in_years) as u64;
+                    match last_credits_sample {
+                        Some((last_slot, last_credits))
+                            if slot >= last_slot + CREDIT_SAMPLE_SLOTS =>
+                        {
+                            let rate = credits.saturating_sub(last_credits) as f64
+                                / (slot - last_slot) as f64;
+                            if let Some(event) = credit_rate_detector.observe(rate) {
+                                events.push((
+                                    event.severity(),
+                                    format!("{}: {}", vote_pubkey, event),
+                                ));
+                            }
+                            last_credits_sample = Some((slot, credits));
+                        }
+                        Some(_) => {}
+                        None => last_credits_sample = Some((slot, credits)),
+                    }
+                }
+                Err(err) => {
+                    warn!("Failed to fetch the credits of {}: {}", vote_pubkey, err);
+                    poll_failed = true;
+                }
+            }
+        }
 
-    let validators_by_staker_credits = solana_credit_score::get_validators_by_credit_score(
-        &rpc_client,
-        &epoch_info,
-        epoch,
-        ignore_commission,
+        if let Some(root_stall_detector) = &root_stall_detector {
+            metric_set.gauge(
+                "solana_credit_score_cluster_halted",
+                "1 while the cluster's root slot is not advancing",
+                &[],
+                u8::from(root_stall_detector.is_stalled()).into(),
+            );
+        }
+        if let Some(vote_pubkey) = watch_credits {
+            let vote_pubkey_label = vote_pubkey.to_string();
+            let labels = [("vote_pubkey", vote_pubkey_label.as_str())];
+            metric_set.gauge(
+                "solana_credit_score_credits_stalled",
+                "1 while the vote account's credits are not increasing",
+                &labels,
+                u8::from(credit_stall_detector.is_stalled()).into(),
+            );
+            metric_set.gauge(
+                "solana_credit_score_vote_root_stalled",
+                "1 while the vote account's root is not advancing although it votes",
+                &labels,
+                u8::from(root_advance_detector.is_stalled()).into(),
+            );
+            metric_set.gauge(
+                "solana_credit_score_vote_account_missing",
+                "1 while the vote account is closed or no longer among the vote accounts",
+                &labels,
+                u8::from(vote_account_presence_detector.is_missing()).into(),
+            );
+        }
+        if let Some(metrics_textfile) = metrics_textfile {
+            if let Err(err) = metric_set.write_textfile(metrics_textfile) {
+                warn!("Unable to write {}: {}", metrics_textfile.display(), err);
+            }
+        }
+        if let (Some(zabbix_server), Some(zabbix_host)) = (&zabbix_server, zabbix_host) {
+            match zabbix_send(zabbix_server, zabbix_host, &metric_set).await {
+                Ok(info) => debug!("Zabbix server {}: {}", zabbix_server, info),
+                Err(err) => warn!("Unable to push metrics to Zabbix: {}", err),
+            }
+        }
+
+        #[cfg(feature = "notifier")]
+        if let Some(digest) = &mut digest {
+            let epoch = match digest.period() {
+                DigestPeriod::Epoch => match rpc_client.get_epoch_info().await {
+                    Ok(epoch_info) => Some(epoch_info.epoch),
+                    Err(err) => {
+                        warn!("Failed to fetch the epoch: {}", err);
+                        None
+                    }
+                },
+                DigestPeriod::Daily => Some(0),
+            };
+            if let Some(epoch) = epoch {
+                let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+                if let Some(msg) = digest.advance(now_ms, epoch) {
+                    notifier.send(&msg).await;
+                }
+            }
+        }
+        for (_, event) in &events {
+            println!("{}", event);
+            if recent_events.len() == STATE_RECENT_EVENTS {
+                recent_events.pop_front();
+            }
+            recent_events.push_back(event.clone());
+        }
+        polls += 1;
+        failed_polls += u64::from(poll_failed);
+        if let Some(state_file) = state_file {
+            let open_incidents = [
+                (
+                    "clusterHalted",
+                    root_stall_detector
+                        .as_ref()
+                        .is_some_and(|root_stall_detector| root_stall_detector.is_stalled()),
+                ),
+                ("creditsStalled", credit_stall_detector.is_stalled()),
+                ("voteRootStalled", root_advance_detector.is_stalled()),
+                (
+                    "voteAccountMissing",
+                    vote_account_presence_detector.is_missing(),
+                ),
+            ]
+            .into_iter()
+            .filter(|(_, open)| *open)
+            .map(|(incident, _)| incident)
+            .collect::<Vec<_>>();
+            #[allow(unused_mut)]
+            let mut state = json!({
+                "timeMs": SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64,
+                "version": app_version,
+                "polls": polls,
+                "failedPolls": failed_polls,
+                "lastPollFailed": poll_failed,
+                "openIncidents": open_incidents,
+                "lastSamples": metric_set.samples().map(|(name, labels, value)| json!({
+                    "name": name,
+                    "labels": labels
+                        .iter()
+                        .map(|(name, value)| (name.clone(), json!(value)))
+                        .collect::<serde_json::Map<_, _>>(),
+                    "value": value,
+                })).collect::<Vec<_>>(),
+                "lastCreditsSample": last_credits_sample
+                    .map(|(slot, credits)| json!({ "slot": slot, "credits": credits })),
+                "recentEvents": recent_events,
+                "detectors": {
+                    "rootStall": format!("{:?}", root_stall_detector),
+                    "creditRate": format!("{:?}", credit_rate_detector),
+                    "creditStall": format!("{:?}", credit_stall_detector),
+                    "rootAdvance": format!("{:?}", root_advance_detector),
+                    "voteAccountPresence": format!("{:?}", vote_account_presence_detector),
+                },
+            });
+            #[cfg(feature = "notifier")]
+            {
+                state["digest"] = format!("{:?}", digest).into();
+            }
+            if let Err(err) = std::fs::write(state_file, with_schema_version(state).to_string()) {
+                warn!("Unable to write {}: {}", state_file, err);
+            }
+        }
+        #[cfg(feature = "notifier")]
+        for (severity, event) in events {
+            match &mut digest {
+                Some(digest) if severity < CheckStatus::Critical => digest.push(event),
+                _ => notifier.send(&event).await,
+            }
+        }
+        if let (Some(heartbeat), false) = (heartbeat, poll_failed) {
+            heartbeat.ping().await;
+        }
+        tokio::time::sleep(WATCH_POLL_INTERVAL).await;
+    }
+}
+
+async fn process_leader_schedule_ics(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // `--leader-schedule-ics` requires `--identity`
+    let identity = pubkey_of(matches, "identity").unwrap();
+    let leader_schedule_ics = matches.value_of("leader_schedule_ics").unwrap();
+    let slot_clock = get_slot_clock(&context.rpc_client).await?;
+    let leader_slots =
+        get_leader_slots(&context.rpc_client, &context.epoch_info_handle, &identity).await?;
+    let leader_windows = leader_windows(&leader_slots, &slot_clock);
+    std::fs::write(
+        leader_schedule_ics,
+        leader_windows_to_ics(&identity, &leader_windows),
+    )
+    .map_err(|err| format!("Unable to write {}: {}", leader_schedule_ics, err))?;
+    Ok(ExitCode::Ok)
+}
+
+async fn process_skipped_slots(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // `--skipped-slots` requires `--identity`
+    let identity = pubkey_of(matches, "identity").unwrap();
+    for skip_event in get_skip_events(
+        &context.rpc_client,
+        &context.epoch_info_handle,
+        &identity,
+        8,
+        50.,
+    )
+    .await?
+    {
+        println!("{}", skip_event);
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_leader_slot_distribution(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // `--leader-slot-distribution` requires `--identity`
+    let identity = pubkey_of(matches, "identity").unwrap();
+    print!(
+        "{}",
+        get_leader_slot_distribution(
+            &context.rpc_client,
+            &context.epoch_info_handle,
+            &identity,
+            10
+        )
+        .await?
+    );
+    Ok(ExitCode::Ok)
+}
+
+async fn process_maintenance(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // `--plan-maintenance` requires `--identity`
+    let identity = pubkey_of(matches, "identity").unwrap();
+    let maintenance_minutes = matches
+        .value_of("plan_maintenance")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let maintenance_deadline = matches
+        .value_of("maintenance_deadline")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let quiet_hours = matches
+        .value_of("quiet_hours")
+        .map(|s| parse_quiet_hours(s).unwrap());
+
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    let maintenance_windows = plan_maintenance(
+        &context.rpc_client,
+        &context.epoch_info_handle,
+        &identity,
+        &MaintenancePlanConfig {
+            duration_ms: maintenance_minutes as i64 * 60_000,
+            deadline_ms: now_ms + maintenance_deadline as i64 * 3_600_000,
+            margin_slots: 4,
+            quiet_hours,
+        },
+    )
+    .await?;
+
+    println!("Maintenance windows for {}:", identity);
+    for window in maintenance_windows {
+        println!(
+            "  slots {}-{}: {} to {} ({} slots of slack)",
+            window.start_slot,
+            window.end_slot,
+            context
+                .locale
+                .format_date_time(&UtcDateTime::from_unix_timestamp(
+                    window.start_time_ms.div_euclid(1000)
+                )),
+            context
+                .locale
+                .format_date_time(&UtcDateTime::from_unix_timestamp(
+                    window.end_time_ms.div_euclid(1000)
+                )),
+            window.slack_slots,
+        );
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_upgrade_target(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let upgrade_target = matches
+        .value_of("upgrade_target")
+        .map(|s| SoftwareVersion::parse(s).unwrap())
+        .unwrap();
+    let upgrade_threshold = matches
+        .value_of("upgrade_threshold")
+        .map(|s| s.parse::<f64>().unwrap())
+        .unwrap();
+    let cluster_versions = get_cluster_versions(&context.rpc_client).await?;
+    print!("{}", cluster_versions);
+    println!(
+        "{}",
+        cluster_versions.upgrade_advice(upgrade_target, upgrade_threshold)
+    );
+    Ok(ExitCode::Ok)
+}
+
+async fn process_feature_gates(context: &Context) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let slot_clock = get_slot_clock(&context.rpc_client).await?;
+    for feature_gate in get_feature_gates(
+        &context.rpc_client,
+        &slot_clock,
+        &credit_affecting_features(),
+    )
+    .await?
+    {
+        if let FeatureStatus::Pending {
+            estimated_time_ms, ..
+        } = feature_gate.status
+        {
+            println!(
+                "{} (estimated {})",
+                feature_gate,
+                context
+                    .locale
+                    .format_date_time(&UtcDateTime::from_unix_timestamp(
+                        estimated_time_ms.div_euclid(1000)
+                    ))
+            );
+        }
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_delegation_changes(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkey = pubkey_of(matches, "delegation_changes").unwrap();
+    let delegation_change_min_stake = matches
+        .value_of("delegation_change_min_sol")
+        .map(|s| sol_to_lamports(s.parse::<f64>().unwrap()))
+        .unwrap();
+    for delegation_change in get_delegation_changes(
+        &context.rpc_client,
+        &vote_pubkey,
+        &get_known_stake_pools(&context.rpc_client).await?,
+        delegation_change_min_stake,
+    )
+    .await?
+    {
+        println!("{}", delegation_change);
+        #[cfg(feature = "notifier")]
+        context.notifier.send(&delegation_change.to_string()).await;
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_skip_rate_history(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // `--skip-rate-history` requires `--identity`
+    let identity = pubkey_of(matches, "identity").unwrap();
+    let skip_rate_history_epochs = matches
+        .value_of("skip_rate_history")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let mut block_production_cache = load_block_production_cache(matches);
+    for epoch_skip_rate in skip_rate_history(
+        &context.rpc_client,
+        &context.epoch_info_handle,
+        &mut block_production_cache,
+        &identity,
+        skip_rate_history_epochs,
+    )
+    .await?
+    {
+        println!("{}", epoch_skip_rate);
+    }
+    save_block_production_cache(matches, block_production_cache)?;
+    Ok(ExitCode::Ok)
+}
+
+/// Reports on the `--fleet` and `--watchlist` validators, alerting on each delinquent one
+async fn process_fleet(
+    context: &Context,
+    matches: &ArgMatches,
+    fleet: &Fleet,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut block_production_cache = load_block_production_cache(matches);
+    let vote_accounts = VoteAccountsSnapshot::fetch(&context.rpc_client).await?;
+    let statuses = get_fleet_status(
+        &context.rpc_client,
+        &context.epoch_info_handle,
+        &mut block_production_cache,
+        &vote_accounts,
+        fleet,
+    )
+    .await?;
+    save_block_production_cache(matches, block_production_cache)?;
+
+    println!("Fleet: {}", FleetMetrics::new(&statuses));
+    for (group, metrics) in fleet.metrics_by_group(&statuses) {
+        if !group.is_empty() {
+            println!("  {}: {}", group, metrics);
+        }
+    }
+    let mut exit_code = ExitCode::Ok;
+    for status in statuses.iter().filter(|status| status.delinquent) {
+        exit_code = ExitCode::Critical;
+        let label = fleet
+            .find(&status.vote_pubkey)
+            .map(|member| member.label.as_str())
+            .unwrap_or_default();
+        let msg = format!("{} ({}) is delinquent", label, status.vote_pubkey);
+        println!("{}", msg);
+        #[cfg(feature = "notifier")]
+        Notifier::new(
+            fleet
+                .alert_route(&status.vote_pubkey)
+                .map(|route| route.to_string()),
+            context.http_client.clone(),
+        )
+        .send(&msg)
+        .await;
+    }
+    Ok(exit_code)
+}
+
+async fn process_compare(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let compare = pubkeys_of(matches, "compare").unwrap();
+    let history_epochs = matches
+        .value_of("history_epochs")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let mut block_production_cache = load_block_production_cache(matches);
+    print!(
+        "{}",
+        compare_validators(
+            &context.rpc_client,
+            &context.epoch_info_handle,
+            &mut block_production_cache,
+            &compare[0],
+            &compare[1],
+            history_epochs
+        )
+        .await?
+    );
+    save_block_production_cache(matches, block_production_cache)?;
+    Ok(ExitCode::Ok)
+}
+
+async fn process_sla(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let path = matches.value_of("sla").unwrap();
+    let sla = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {}: {}", path, err))
+        .and_then(|contents| {
+            serde_json::from_str(&contents)
+                .map_err(|err| format!("Invalid JSON in {}: {}", path, err))
+        })
+        .and_then(|value| Sla::from_json(&value))
+        .map_err(Error::Config)?;
+    // `--sla` requires `--sla-vote`
+    let vote_pubkey = pubkey_of(matches, "sla_vote").unwrap();
+    let sla_epochs = matches
+        .value_of("sla_epochs")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let vote_latency_samples = matches
+        .value_of("vote_latency_samples")
+        .map(|s| s.parse::<usize>().unwrap())
+        .unwrap();
+
+    let mut block_production_cache = load_block_production_cache(matches);
+    let reports = get_sla_reports(
+        &context.rpc_client,
+        &context.epoch_info_handle,
+        &mut block_production_cache,
+        &vote_pubkey,
+        &sla,
+        sla_epochs,
+        sla.max_vote_latency.map(|_| vote_latency_samples),
     )
     .await?;
+    save_block_production_cache(matches, block_production_cache)?;
+
+    let mut exit_code = ExitCode::Ok;
+    for report in reports {
+        println!("{}", report);
+        if !report.compliant() {
+            exit_code = ExitCode::Critical;
+        }
+    }
+    Ok(exit_code)
+}
+
+async fn process_rewards_breakdown(
+    context: &Context,
+    matches: &ArgMatches,
+    epoch: Epoch,
+    epoch_info: &EpochInfo,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkey = pubkey_of(matches, "rewards_breakdown").unwrap();
+    let completed_epoch = epoch.min(epoch_info.epoch.saturating_sub(1));
+    println!(
+        "{}",
+        get_epoch_rewards_breakdown(&context.rpc_client, &vote_pubkey, completed_epoch).await?
+    );
+    Ok(ExitCode::Ok)
+}
+
+async fn process_commission_income(
+    context: &Context,
+    matches: &ArgMatches,
+    epoch_info: &EpochInfo,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkeys = pubkeys_of(matches, "commission_income").unwrap();
+    // `--commission-income` requires `--commission-income-ledger`
+    let path = matches.value_of("commission_income_ledger").unwrap();
+    let commission_income_epochs = matches
+        .value_of("commission_income_epochs")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+
+    let mut ledger = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .map(|value| CommissionIncomeLedger::from_json(&value))
+        .unwrap_or_default();
+    ledger
+        .update(
+            &context.rpc_client,
+            &vote_pubkeys,
+            epoch_info.epoch.saturating_sub(commission_income_epochs)..epoch_info.epoch,
+        )
+        .await?;
+    std::fs::write(path, ledger.to_json().to_string())
+        .map_err(|err| format!("Unable to write {}: {}", path, err))?;
 
-    let staker_credits = validators_by_staker_credits
-        .iter()
-        .map(|(staker_credits, ..)| *staker_credits as f64)
-        .collect::<Vec<_>>();
+    for vote_pubkey in &vote_pubkeys {
+        println!("Commission income of {}:", vote_pubkey);
+        for monthly_income in ledger.monthly_income(vote_pubkey) {
+            println!("  {}", monthly_income);
+        }
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_score_epochs(
+    context: &Context,
+    matches: &ArgMatches,
+    epoch: Epoch,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let score_epochs_num = matches
+        .value_of("score_epochs")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let score_concurrency = matches
+        .value_of("score_concurrency")
+        .map(|s| s.parse::<usize>().unwrap())
+        .unwrap();
+    let num = num_of(matches);
 
-    let total_activated_stake = if epoch == epoch_info.epoch {
-        validators_by_staker_credits
+    let vote_accounts = VoteAccountsSnapshot::fetch(&context.rpc_client).await?;
+    let mut epoch_scores = Box::pin(score_epochs(
+        &context.rpc_client,
+        &context.epoch_info_handle,
+        &vote_accounts,
+        (epoch + 1).saturating_sub(score_epochs_num)..epoch + 1,
+        matches.is_present("ignore_commission"),
+        missing_commission_of(matches),
+        score_concurrency,
+        |progress| eprintln!("{}", progress),
+    ));
+    while let Some(epoch_scores) = epoch_scores.next().await {
+        let epoch_scores = epoch_scores?;
+        println!(
+            "Epoch {}: {} validators scored",
+            epoch_scores.epoch,
+            epoch_scores.validators_by_staker_credits.len()
+        );
+        for (rank, (staker_credits, vote_pubkey, _)) in epoch_scores
+            .validators_by_staker_credits
             .iter()
-            .map(|(.., activated_stake)| *activated_stake as u64)
-            .sum::<u64>()
+            .take(num)
+            .enumerate()
+        {
+            println!("  {}. {} {}", rank + 1, vote_pubkey, staker_credits);
+        }
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_commission_history(
+    context: &Context,
+    matches: &ArgMatches,
+    epoch_info: &EpochInfo,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let commission_history_epochs = matches
+        .value_of("commission_history")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let commission_history = get_commission_history(
+        &context.rpc_client,
+        &mut EpochCommissionsCache::default(),
+        (epoch_info.epoch + 1).saturating_sub(commission_history_epochs)..epoch_info.epoch + 1,
+    )
+    .await?;
+    for (vote_pubkey, history) in &commission_history {
+        for (epoch, previous_commission, commission) in commission_changes(history) {
+            println!(
+                "{}: commission changed from {}% to {}% in epoch {}",
+                vote_pubkey, previous_commission, commission, epoch
+            );
+        }
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_audit_new_validator(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkey = pubkey_of(matches, "audit_new_validator").unwrap();
+    println!(
+        "{}",
+        audit_new_validator(&context.rpc_client, &vote_pubkey).await?
+    );
+    Ok(ExitCode::Ok)
+}
+
+async fn process_key_hygiene(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkey = pubkey_of(matches, "key_hygiene").unwrap();
+    let voter_rotation_epochs = matches
+        .value_of("voter_rotation_epochs")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    for issue in check_key_hygiene(&context.rpc_client, &vote_pubkey, voter_rotation_epochs).await?
+    {
+        println!("{}", issue);
+        #[cfg(feature = "notifier")]
+        context.notifier.send(&issue.to_string()).await;
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_fork_votes(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkey = pubkey_of(matches, "fork_votes").unwrap();
+    let fork_vote_alert_percent = matches
+        .value_of("fork_vote_alert_percent")
+        .map(|s| s.parse::<f64>().unwrap())
+        .unwrap();
+    let fork_vote_stats = get_fork_vote_stats(&context.rpc_client, &vote_pubkey).await?;
+    println!("{}", fork_vote_stats);
+    if fork_vote_stats.fork_vote_percent() > fork_vote_alert_percent {
+        #[cfg(feature = "notifier")]
+        context.notifier.send(&fork_vote_stats.to_string()).await;
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_vote_latency(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vote_pubkey = pubkey_of(matches, "vote_latency").unwrap();
+    let vote_latency_samples = matches
+        .value_of("vote_latency_samples")
+        .map(|s| s.parse::<usize>().unwrap())
+        .unwrap();
+    let epoch_schedule = context.rpc_client.get_epoch_schedule().await?;
+    println!("Vote latency of {}:", vote_pubkey);
+    for histogram in get_vote_latency_histograms(
+        &context.rpc_client,
+        &epoch_schedule,
+        &vote_pubkey,
+        vote_latency_samples,
+    )
+    .await?
+    {
+        println!("  {}", histogram);
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_stake_countdown(
+    context: &Context,
+    matches: &ArgMatches,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // `--stake-countdown` requires `--stake-account`
+    let stake_accounts = pubkeys_of(matches, "stake_account").unwrap();
+    for countdown in get_stake_activation_countdowns(&context.rpc_client, &stake_accounts).await? {
+        println!("{}", countdown);
+    }
+    Ok(ExitCode::Ok)
+}
+
+async fn process_stake_account_rewards(
+    context: &Context,
+    matches: &ArgMatches,
+    epoch_info: &EpochInfo,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let stake_account_rewards = matches.value_of("stake_account_rewards").unwrap();
+    // `--stake-account-rewards` requires `--stake-account`
+    let stake_accounts = pubkeys_of(matches, "stake_account").unwrap();
+    let rewards_epochs = matches
+        .value_of("rewards_epochs")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let rewards = get_stake_account_rewards(
+        &context.rpc_client,
+        &stake_accounts,
+        epoch_info.epoch.saturating_sub(rewards_epochs)..epoch_info.epoch,
+    )
+    .await?;
+    let contents = if stake_account_rewards.ends_with(".json") {
+        stake_account_rewards_to_json(&rewards).to_string()
+    } else {
+        stake_account_rewards_to_csv(&rewards)
+    };
+    std::fs::write(stake_account_rewards, contents)
+        .map_err(|err| format!("Unable to write {}: {}", stake_account_rewards, err))?;
+    Ok(ExitCode::Ok)
+}
+
+/// Scores the validators in `epoch` and prints, publishes and alerts on the leaderboard
+async fn process_leaderboard(
+    context: &Context,
+    matches: &ArgMatches,
+    keypair_path: &str,
+    epoch: Epoch,
+    epoch_info: &EpochInfo,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let rpc_client = &context.rpc_client;
+    let epoch_info_handle = &context.epoch_info_handle;
+    #[cfg(feature = "notifier")]
+    let notifier = &context.notifier;
+    let num = num_of(matches);
+    let max_percentile = matches
+        .value_of("max_percentile")
+        .map(|s| s.parse::<u8>().unwrap())
+        .unwrap();
+    let ignore_commission = matches.is_present("ignore_commission");
+    let missing_commission = missing_commission_of(matches);
+    let exclude_incomplete = matches.is_present("exclude_incomplete");
+    let min_voting_epochs = matches
+        .value_of("min_voting_epochs")
+        .map(|s| s.parse::<u64>().unwrap());
+    let vote_age = matches.is_present("vote_age");
+    let completeness = matches.is_present("completeness") || exclude_incomplete;
+    let yield_ranking = matches.is_present("yield_ranking");
+    let cohorts = matches.is_present("cohorts");
+    let epoch_weights = match matches.value_of("half_life") {
+        Some(half_life) => Some(EpochWeights::Exponential {
+            half_life_epochs: half_life.parse::<f64>().unwrap(),
+        }),
+        None => matches
+            .value_of("epoch_weights")
+            .map(|s| parse_epoch_weights(s).unwrap()),
+    };
+    let history_epochs = matches
+        .value_of("history_epochs")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap();
+    let deadline = matches
+        .value_of("deadline")
+        .map(|s| Duration::from_secs(s.parse::<u64>().unwrap()));
+    let mev = matches.is_present("mev");
+    let clients = matches.is_present("clients");
+    let stake_pools = matches.is_present("stake_pools");
+    let version_penalty = matches.value_of("version_penalty").map(|s| VersionPenalty {
+        max_patch_releases_behind: matches
+            .value_of("max_patch_releases_behind")
+            .map(|s| s.parse::<u64>().unwrap())
+            .unwrap(),
+        penalty_percent: s.parse::<u8>().unwrap(),
+    });
+    let feed = matches.value_of("feed");
+    let rank_events_vote_pubkey = pubkey_of(matches, "rank_events");
+    let rank_top = matches
+        .value_of("rank_top")
+        .map(|s| s.parse::<usize>().unwrap())
+        .unwrap();
+    let rank_percentiles = matches
+        .value_of("rank_percentiles")
+        .map(|s| parse_percentiles(s).unwrap())
+        .unwrap();
+    let feed_min_percentile = matches
+        .value_of("feed_min_percentile")
+        .map(|s| s.parse::<u8>().unwrap())
+        .unwrap();
+    let quorum_peers = matches
+        .values_of("quorum_peer")
+        .map(|values| values.map(|s| s.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let quorum_config = QuorumConfig {
+        quorum: matches
+            .value_of("quorum")
+            .map(|s| s.parse::<usize>().unwrap())
+            .unwrap_or(quorum_peers.len().div_ceil(2) + 1),
+        tolerance_percent: matches
+            .value_of("quorum_tolerance")
+            .map(|s| s.parse::<f64>().unwrap())
+            .unwrap(),
+    };
+    let quorum_signer_state_path = matches.value_of("quorum_signer_state");
+    let mut quorum_signers = pubkeys_of(matches, "quorum_signer").map(|quorum_signers| {
+        quorum_signer_state_path
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .and_then(|value| TrustedSigners::from_json(&value).ok())
+            .unwrap_or_else(|| TrustedSigners::new(&quorum_signers))
+    });
+    let publish_target = if matches.is_present("publish") {
+        Some(match pubkey_of(matches, "publish_account") {
+            Some(account) => PublishTarget::DataAccount {
+                program_id: pubkey_of(matches, "publish_program").unwrap(),
+                account,
+            },
+            None => PublishTarget::Memo,
+        })
     } else {
-        stake_history
-            .get(epoch)
-            .ok_or_else(|| format!("Stake history unavailable for epoch {}", epoch))?
-            .effective
+        None
     };
+    let dry_run = matches.is_present("dry_run");
+    #[cfg(feature = "notifier")]
+    let notification_locale = matches
+        .value_of("notification_locale")
+        .map(Locale::from_locale)
+        .unwrap_or_default();
+
+    // Raised when the scores are not confirmed by a quorum
+    let mut exit_code = ExitCode::Ok;
+
+    println!("Epoch {}", epoch);
+
+    let first_slot_in_scored_epoch = epoch_info.absolute_slot
+        - epoch_info.slot_index
+        - (epoch_info.epoch - epoch.min(epoch_info.epoch)) * epoch_info.slots_in_epoch;
+    for feature_gate in get_credit_affecting_activations(
+        rpc_client,
+        first_slot_in_scored_epoch..first_slot_in_scored_epoch + epoch_info.slots_in_epoch,
+    )
+    .await?
+    {
+        println!(
+            "Note: {}. Credits in this epoch are not comparable with earlier epochs",
+            feature_gate
+        );
+    }
+
+    let estimated_total_epoch_reward =
+        get_estimated_total_epoch_reward(rpc_client, epoch_info_handle).await?;
 
-    let total_points = validators_by_staker_credits
-        .iter()
-        .map(|(staker_credits, .., activated_stake)| {
-            u128::from(*staker_credits) * u128::from(*activated_stake)
+    let (vote_accounts, mut validators_by_staker_credits) =
+        with_timeout("Scoring validators", deadline, async {
+            let vote_accounts = VoteAccountsSnapshot::fetch(rpc_client).await?;
+            let validators_by_staker_credits = get_validators_by_credit_score_with_vote_accounts(
+                rpc_client,
+                epoch_info_handle,
+                &vote_accounts,
+                epoch,
+                ignore_commission,
+                missing_commission,
+            )
+            .await?;
+            Ok((vote_accounts, validators_by_staker_credits))
         })
-        .sum::<u128>();
-
-    let top_staker_credits = staker_credits.first().copied().unwrap_or_default();
-
-    let staker_credits = criterion_stats::Distribution::from(staker_credits.into_boxed_slice());
-    let staker_credit_percentiles = staker_credits.percentiles();
-
-    let mut p = 100u8;
-    let msg = validators_by_staker_credits
-        .into_iter()
-        .take(num)
-        .enumerate()
-        .filter_map(|(i, (staker_credits, vote_pubkey, activated_stake))| {
-            while p > 0 {
-                let percentile_credits = staker_credit_percentiles.at(p as f64);
-                if staker_credits as f64 >= percentile_credits {
-                    break;
-                }
-                p = p.saturating_sub(1);
-            }
+        .await?;
 
-            if p < max_percentile {
-                None
-            } else {
-                let percent_of_top_staker = staker_credits as f64 * 100. / top_staker_credits;
+    let score_completeness = if completeness {
+        Some(
+            get_score_completeness(
+                rpc_client,
+                epoch_info_handle,
+                &vote_accounts,
+                epoch,
+                ignore_commission,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+    if let (true, Some(score_completeness)) = (exclude_incomplete, &score_completeness) {
+        validators_by_staker_credits.retain(|(_, vote_pubkey, _)| {
+            score_completeness
+                .get(vote_pubkey)
+                .is_some_and(|completeness| completeness.is_complete())
+        });
+    }
+
+    let first_epochs_with_credits = vote_accounts.first_epochs_with_credits();
+    if let Some(min_voting_epochs) = min_voting_epochs {
+        validators_by_staker_credits.retain(|(_, vote_pubkey, _)| {
+            first_epochs_with_credits
+                .get(vote_pubkey)
+                .is_some_and(|first_epoch| {
+                    *first_epoch <= epoch && epoch - first_epoch + 1 >= min_voting_epochs
+                })
+        });
+    }
+
+    // Jito-Agave is told apart from Agave by its tip distribution account
+    let mev_commissions = if mev || clients {
+        let vote_pubkeys = validators_by_staker_credits
+            .iter()
+            .map(|(_, vote_pubkey, _)| *vote_pubkey)
+            .collect::<Vec<_>>();
+        Some(get_mev_commissions(rpc_client, &vote_pubkeys, epoch).await?)
+    } else {
+        None
+    };
+
+    let client_diversity = if clients || version_penalty.is_some() {
+        Some(get_client_diversity(rpc_client, mev_commissions.as_ref()).await?)
+    } else {
+        None
+    };
+    if let (Some(version_penalty), Some(client_diversity)) = (version_penalty, &client_diversity) {
+        validators_by_staker_credits =
+            version_penalty.apply(&validators_by_staker_credits, client_diversity);
+    }
+    let pool_stake = if stake_pools {
+        Some(
+            get_pool_stake_by_vote_account(rpc_client, &get_known_stake_pools(rpc_client).await?)
+                .await?,
+        )
+    } else {
+        None
+    };
 
-                let credits_behind =
-                    (top_staker_credits.floor() as u64).saturating_sub(staker_credits);
+    let eligibility_criteria = EligibilityCriteria {
+        min_percentile: feed_min_percentile,
+        ..EligibilityCriteria::default()
+    };
+    let quorum_reached = if quorum_peers.is_empty() {
+        true
+    } else {
+        let (peer_feeds, unavailable_peer_feeds) = fetch_score_feeds(
+            &context.http_client,
+            &quorum_peers,
+            epoch,
+            quorum_signers.as_mut(),
+        )
+        .await;
+        if let (Some(quorum_signers), Some(path)) = (&quorum_signers, quorum_signer_state_path) {
+            std::fs::write(path, quorum_signers.to_json().to_string())
+                .map_err(|err| format!("Unable to write {}: {}", path, err))?;
+        }
+        let quorum_outcome = score_quorum(
+            &ScoreFeed::new(epoch, &validators_by_staker_credits, &eligibility_criteria),
+            &peer_feeds,
+            &unavailable_peer_feeds,
+            &quorum_config,
+        );
+        println!("{}", quorum_outcome);
+        if !quorum_outcome.reached() {
+            warn!("Scores not confirmed by a quorum, not publishing or alerting on them");
+            exit_code = exit_code.max(ExitCode::Warning);
+        }
+        quorum_outcome.reached()
+    };
 
-                let current_epoch_estimates = if epoch_info.epoch == epoch {
-                    let points = u128::from(activated_stake) * u128::from(staker_credits);
+    if let (Some(vote_pubkey), Some(previous_epoch), true) = (
+        rank_events_vote_pubkey,
+        epoch.checked_sub(1),
+        quorum_reached,
+    ) {
+        let previous_validators_by_staker_credits =
+            get_validators_by_credit_score_with_vote_accounts(
+                rpc_client,
+                epoch_info_handle,
+                &vote_accounts,
+                previous_epoch,
+                ignore_commission,
+                missing_commission,
+            )
+            .await?;
+        for event in rank_events(
+            LeaderboardPosition::new(&previous_validators_by_staker_credits, &vote_pubkey),
+            LeaderboardPosition::new(&validators_by_staker_credits, &vote_pubkey),
+            rank_top,
+            &rank_percentiles,
+        ) {
+            let msg = format!("{} in epoch {}: {}", vote_pubkey, epoch, event);
+            println!("{}", msg);
+            #[cfg(feature = "notifier")]
+            notifier.send(&msg).await;
+        }
+    }
 
-                    let estimated_epoch_reward =
-                        (estimated_total_epoch_reward as u128 * points / total_points) as u64;
+    // The feed is written even without a quorum: the scorers decide on the quorum by reading
+    // each other's feeds, so withholding it would keep every scorer from ever reaching one
+    let publish_target = publish_target.filter(|_| quorum_reached);
+    if feed.is_some() || publish_target.is_some() {
+        let mut wallet_manager = None;
+        let signer = signer_from_path(matches, keypair_path, "keypair", &mut wallet_manager)
+            .map_err(|err| Error::Config(format!("Unable to load {}: {}", keypair_path, err)))?;
+        let key_rotation = matches
+            .value_of("previous_keypair")
+            .map(|previous_keypair_path| {
+                signer_from_path(
+                    matches,
+                    previous_keypair_path,
+                    "previous_keypair",
+                    &mut wallet_manager,
+                )
+                .map_err(|err| {
+                    Error::Config(format!("Unable to load {}: {}", previous_keypair_path, err))
+                })
+                .and_then(|previous_signer| {
+                    KeyRotation::new(&*previous_signer, &signer.pubkey(), epoch)
+                })
+            })
+            .transpose()?;
+        let mut score_feed =
+            ScoreFeed::new(epoch, &validators_by_staker_credits, &eligibility_criteria);
+        if let Some(mev_commissions) = &mev_commissions {
+            score_feed.set_mev_commissions(mev_commissions);
+        }
+        if let Some(client_diversity) = &client_diversity {
+            score_feed.set_versions(&client_diversity.versions());
+        }
+        score_feed.set_first_epochs_with_credits(&first_epochs_with_credits);
 
-                    let expected_epoch_reward = u128::from(estimated_total_epoch_reward)
-                        * u128::from(activated_stake)
-                        / u128::from(total_activated_stake);
+        if let Some(feed) = feed {
+            let mut signed_feed = score_feed.sign(&*signer)?;
+            if let Some(key_rotation) = &key_rotation {
+                add_key_rotation(&mut signed_feed, key_rotation);
+            }
+            std::fs::write(feed, signed_feed.to_string())
+                .map_err(|err| format!("Unable to write {}: {}", feed, err))?;
+        }
 
-                    format!(
-                        "| estimated {} (expected: {})",
-                        Sol(estimated_epoch_reward),
-                        Sol(expected_epoch_reward as u64),
+        if let Some(publish_target) = publish_target {
+            match publish_score_summary(rpc_client, publish_target, &*signer, &score_feed, dry_run)
+                .await?
+            {
+                PublishOutcome::DryRun(transaction) => {
+                    println!(
+                        "Dry run, transaction {} not sent. Summary: {}",
+                        transaction.signatures[0],
+                        score_summary(&score_feed),
                     )
+                }
+                PublishOutcome::Sent(signature) => {
+                    println!("Published score summary: {}", signature)
+                }
+            }
+        }
+    }
+
+    let reward_estimate = {
+        let total_activated_stake = validators_by_staker_credits
+            .iter()
+            .map(|(.., activated_stake)| activated_stake.lamports())
+            .sum::<u64>();
+
+        let total_points = validators_by_staker_credits
+            .iter()
+            .map(|(staker_credits, .., activated_stake)| {
+                u128::from(*staker_credits) * u128::from(*activated_stake)
+            })
+            .sum::<u128>();
+
+        RewardEstimate {
+            estimated_total_epoch_reward,
+            total_activated_stake,
+            total_points,
+        }
+    };
+
+    let mut annotations = BTreeMap::<_, Vec<String>>::new();
+    if mev {
+        for (vote_pubkey, mev_commission) in mev_commissions.iter().flatten() {
+            annotations
+                .entry(*vote_pubkey)
+                .or_default()
+                .push(mev_commission.to_string());
+        }
+    }
+    if vote_age {
+        for vote_account in vote_accounts.vote_account_credits() {
+            // A full history doesn't tell when the vote account started voting
+            if let (Some(first_epoch), true) = (
+                vote_account.first_epoch_with_credits(),
+                vote_account.epoch_credits.len() < MAX_EPOCH_CREDITS_HISTORY,
+            ) {
+                annotations
+                    .entry(vote_account.vote_pubkey)
+                    .or_default()
+                    .push(format!("voting since epoch {}", first_epoch));
+            }
+        }
+    }
+    if let Some(pool_stake) = &pool_stake {
+        for (_, vote_pubkey, activated_stake) in &validators_by_staker_credits {
+            if let Some(stake_by_pool) = pool_stake.get(vote_pubkey) {
+                let activated_stake = activated_stake.lamports().max(1) as f64;
+                let pools = stake_by_pool
+                    .iter()
+                    .map(|(name, stake)| {
+                        format!("{} {:.1}%", name, *stake as f64 * 100. / activated_stake)
+                    })
+                    .collect::<Vec<_>>();
+                annotations
+                    .entry(*vote_pubkey)
+                    .or_default()
+                    .push(format!("pool stake: {}", pools.join(", ")));
+            }
+        }
+    }
+    for (vote_pubkey, completeness) in score_completeness.iter().flatten() {
+        if !completeness.is_complete() {
+            annotations
+                .entry(*vote_pubkey)
+                .or_default()
+                .push(completeness.to_string());
+        }
+    }
+    if let Some(client_diversity) = &client_diversity {
+        if clients {
+            print!("{}", client_diversity);
+        }
+        let majority_version = client_diversity.majority_version();
+        for (vote_pubkey, validator) in &client_diversity.validators {
+            let annotations = annotations.entry(*vote_pubkey).or_default();
+            if clients {
+                annotations.push(validator.client.to_string());
+            }
+            if let Some(version_penalty) = version_penalty {
+                let version = validator.version.as_deref().unwrap_or("unknown");
+                let outdated = SoftwareVersion::parse(version)
+                    .zip(majority_version)
+                    .is_some_and(|(version, majority_version)| {
+                        version_penalty.is_outdated(&version, &majority_version)
+                    });
+                annotations.push(if outdated {
+                    format!("v{} outdated", version)
                 } else {
-                    "".into()
-                };
-
-                #[allow(clippy::to_string_in_format_args)]
-                let vote_pubkey_str = vote_pubkey.to_string();
-
-                Some(format!(
-                    "{:>4}. {:<44} ({:>6.2}%) ({:>3}th percentile){} {}",
-                    i + 1,
-                    vote_pubkey_str,
-                    percent_of_top_staker,
-                    p,
-                    if credits_behind > 0 {
-                        format!(" [-{} credits]", credits_behind)
-                    } else {
-                        "".into()
-                    },
-                    current_epoch_estimates,
-                ))
+                    format!("v{}", version)
+                });
             }
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+        }
+    }
+
+    let msg = format_validators_by_credit_score(
+        &validators_by_staker_credits,
+        num,
+        max_percentile,
+        (epoch == epoch_info.epoch).then_some(&reward_estimate),
+        Some(&annotations),
+        &context.locale,
+    );
 
     println!("{}", msg);
-    notifier.send(&format!("```{}```", msg)).await;
-    Ok(())
+    if let Some(epoch_weights) = epoch_weights {
+        let scoring_config = ScoringConfig {
+            num_epochs: history_epochs,
+            epoch_weights,
+            ignore_commission,
+            tvc_activation_epoch: get_timely_vote_credits_activation_epoch(rpc_client).await?,
+        };
+        println!(
+            "Averaged over {} epochs, weighted {}:",
+            scoring_config.num_epochs(),
+            scoring_config.epoch_weights
+        );
+        if let Some(tvc_activation_epoch) = scoring_config.tvc_activation_epoch {
+            if tvc_activation_epoch
+                > epoch.saturating_sub(scoring_config.num_epochs().saturating_sub(1))
+                && tvc_activation_epoch <= epoch
+            {
+                println!(
+                    "Note: credits before epoch {} are scaled by {} to match timely vote credits",
+                    tvc_activation_epoch,
+                    MAX_CREDITS_PER_VOTE_TVC / MAX_CREDITS_PER_VOTE_BEFORE_TVC
+                );
+            }
+        }
+        println!(
+            "{}",
+            format_validators_by_smoothed_score(
+                &get_validators_by_smoothed_score(&vote_accounts, epoch, &scoring_config),
+                num,
+            )
+        );
+    }
+    if cohorts {
+        println!("Credits by stake cohort:");
+        println!(
+            "{}",
+            format_stake_cohorts(
+                &stake_cohorts(&validators_by_staker_credits, &default_stake_buckets()),
+                &context.locale,
+            )
+        );
+    }
+    if yield_ranking {
+        println!("Projected delegator yield:");
+        println!(
+            "{}",
+            format_validators_by_yield(
+                &validators_by_yield(
+                    &validators_by_staker_credits,
+                    &reward_estimate,
+                    SLOTS_PER_YEAR / epoch_info.slots_in_epoch as f64,
+                ),
+                num,
+            )
+        );
+    }
+    #[cfg(feature = "notifier")]
+    if quorum_reached {
+        notifier
+            .send(&format!(
+                "```{}```",
+                format_validators_by_credit_score(
+                    &validators_by_staker_credits,
+                    num,
+                    max_percentile,
+                    (epoch == epoch_info.epoch).then_some(&reward_estimate),
+                    Some(&annotations),
+                    &notification_locale,
+                )
+            ))
+            .await;
+    }
+    Ok(exit_code)
 }
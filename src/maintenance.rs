@@ -0,0 +1,191 @@
+//! Planning of validator maintenance windows around leader slots and epoch boundaries
+use {
+    crate::{
+        epoch_info::EpochInfoHandle,
+        error::Error,
+        leader_schedule::{get_leader_slots_in_epoch, get_slot_clock},
+        slot_clock::SlotClock,
+    },
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    std::ops::Range,
+};
+
+const MS_PER_HOUR: i64 = 3_600_000;
+const MS_PER_DAY: i64 = 24 * MS_PER_HOUR;
+
+/// Hours of the day, in UTC, during which maintenance should not be scheduled. `start_hour` may
+/// be greater than `end_hour` for a range spanning midnight, e.g. 22 to 6
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl QuietHours {
+    /// Returns the quiet periods, as Unix time ranges in milliseconds, overlapping `range_ms`
+    fn periods_ms(&self, range_ms: Range<i64>) -> Vec<Range<i64>> {
+        let start_ms = i64::from(self.start_hour % 24) * MS_PER_HOUR;
+        let mut end_ms = i64::from(self.end_hour % 24) * MS_PER_HOUR;
+        if end_ms <= start_ms {
+            end_ms += MS_PER_DAY;
+        }
+
+        // Start a day early to cover a quiet period spanning midnight into the range
+        let mut day_ms = range_ms.start.div_euclid(MS_PER_DAY) * MS_PER_DAY - MS_PER_DAY;
+        let mut periods = vec![];
+        while day_ms < range_ms.end {
+            let period = day_ms + start_ms..day_ms + end_ms;
+            if period.end > range_ms.start && period.start < range_ms.end {
+                periods.push(period);
+            }
+            day_ms += MS_PER_DAY;
+        }
+        periods
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenancePlanConfig {
+    /// Desired length of the maintenance, in milliseconds
+    pub duration_ms: i64,
+    /// Unix time, in milliseconds, by which the maintenance must be complete
+    pub deadline_ms: i64,
+    /// Slots to keep clear before and after every leader slot and epoch boundary
+    pub margin_slots: u64,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub start_slot: Slot,
+    pub end_slot: Slot,
+    pub start_time_ms: i64,
+    pub end_time_ms: i64,
+    /// Free slots available in the gap around this window beyond the requested duration
+    pub slack_slots: u64,
+}
+
+fn merge_ranges(mut ranges: Vec<Range<Slot>>) -> Vec<Range<Slot>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<Slot>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Returns candidate maintenance windows between the `slot_clock` reference slot and the
+/// deadline, ranked from most to least slack.
+///
+/// Each window avoids `leader_slots`, `epoch_boundaries` (the first slot of each epoch) and the
+/// configured quiet hours, and is centered within its free gap
+pub fn plan_maintenance_windows(
+    slot_clock: &SlotClock,
+    leader_slots: &[Slot],
+    epoch_boundaries: &[Slot],
+    config: &MaintenancePlanConfig,
+) -> Vec<MaintenanceWindow> {
+    let first_slot = slot_clock.reference_slot;
+    let last_slot = slot_clock.slot_at(config.deadline_ms);
+    if last_slot <= first_slot {
+        return vec![];
+    }
+    let duration_slots = (config.duration_ms.max(0) as u64).div_ceil(slot_clock.ms_per_slot);
+
+    let mut blocked = leader_slots
+        .iter()
+        .chain(epoch_boundaries)
+        .map(|slot| slot.saturating_sub(config.margin_slots)..slot + config.margin_slots + 1)
+        .collect::<Vec<_>>();
+    if let Some(quiet_hours) = config.quiet_hours {
+        blocked.extend(
+            quiet_hours
+                .periods_ms(slot_clock.slot_time_ms(first_slot)..config.deadline_ms)
+                .into_iter()
+                .map(|period| slot_clock.slot_at(period.start)..slot_clock.slot_at(period.end)),
+        );
+    }
+
+    let mut gaps = vec![];
+    let mut gap_start = first_slot;
+    for range in merge_ranges(blocked) {
+        if range.start > gap_start {
+            gaps.push(gap_start..range.start.min(last_slot));
+        }
+        gap_start = gap_start.max(range.end);
+        if gap_start >= last_slot {
+            break;
+        }
+    }
+    if gap_start < last_slot {
+        gaps.push(gap_start..last_slot);
+    }
+
+    let mut windows = gaps
+        .into_iter()
+        .filter(|gap| gap.end > gap.start && gap.end - gap.start >= duration_slots)
+        .map(|gap| {
+            let slack_slots = gap.end - gap.start - duration_slots;
+            let start_slot = gap.start + slack_slots / 2;
+            let end_slot = start_slot + duration_slots;
+            MaintenanceWindow {
+                start_slot,
+                end_slot,
+                start_time_ms: slot_clock.slot_time_ms(start_slot),
+                end_time_ms: slot_clock.slot_time_ms(end_slot),
+                slack_slots,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    windows.sort_by(|a, b| {
+        b.slack_slots
+            .cmp(&a.slack_slots)
+            .then(a.start_slot.cmp(&b.start_slot))
+    });
+    windows
+}
+
+/// Plans maintenance windows for `identity` using the current and, when the deadline reaches
+/// into it, the next epoch's leader schedule
+pub async fn plan_maintenance(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    identity: &Pubkey,
+    config: &MaintenancePlanConfig,
+) -> Result<Vec<MaintenanceWindow>, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let slot_clock = get_slot_clock(rpc_client).await?;
+    let last_slot = slot_clock.slot_at(config.deadline_ms);
+
+    let mut epoch_start = epoch_info.absolute_slot - epoch_info.slot_index;
+    let mut leader_slots = vec![];
+    let mut epoch_boundaries = vec![];
+    // The leader schedule is only known up to one epoch ahead
+    for _ in 0..2 {
+        if epoch_start > last_slot {
+            break;
+        }
+        epoch_boundaries.push(epoch_start);
+        leader_slots.extend(get_leader_slots_in_epoch(rpc_client, epoch_start, identity).await?);
+        epoch_start += epoch_info.slots_in_epoch;
+    }
+    if epoch_start <= last_slot {
+        return Err(Error::Config(format!(
+            "Deadline is beyond the known leader schedule, which ends at slot {}",
+            epoch_start - 1
+        ))
+        .into());
+    }
+
+    Ok(plan_maintenance_windows(
+        &slot_clock,
+        &leader_slots,
+        &epoch_boundaries,
+        config,
+    ))
+}
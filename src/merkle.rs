@@ -0,0 +1,153 @@
+//! Merkle commitments over per-epoch score lists.
+//!
+//! The root can be published on chain while individual validators prove their score off chain
+//! with a `MerkleProof`. Interior nodes hash their children in sorted order, so a proof is just
+//! the list of sibling hashes from the leaf to the root.
+use solana_sdk::{
+    clock::Epoch,
+    hash::{hashv, Hash},
+    pubkey::Pubkey,
+};
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Returns the leaf committing to `vote_pubkey` earning `staker_credits` in `epoch`
+pub fn score_leaf(epoch: Epoch, vote_pubkey: &Pubkey, staker_credits: u64) -> Hash {
+    hashv(&[
+        LEAF_PREFIX,
+        &epoch.to_le_bytes(),
+        vote_pubkey.as_ref(),
+        &staker_credits.to_le_bytes(),
+    ])
+}
+
+fn hash_pair(a: &Hash, b: &Hash) -> Hash {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left.as_ref(), right.as_ref()])
+}
+
+/// Sibling hashes from a leaf up to, but excluding, the root
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MerkleProof(pub Vec<Hash>);
+
+impl MerkleProof {
+    /// Returns true if `leaf` is included in the tree with the given `root`
+    pub fn verify(&self, root: &Hash, leaf: &Hash) -> bool {
+        self.0
+            .iter()
+            .fold(*leaf, |node, sibling| hash_pair(&node, sibling))
+            == *root
+    }
+}
+
+pub struct ScoreMerkleTree {
+    epoch: Epoch,
+    /// All tree levels, from the leaves to the root
+    levels: Vec<Vec<Hash>>,
+    vote_pubkeys: Vec<Pubkey>,
+}
+
+impl ScoreMerkleTree {
+    /// Builds the tree over the (vote_pubkey, staker_credits) scores of `epoch`. Leaves are kept
+    /// in the order given; an unpaired node at the end of a level is promoted unchanged
+    pub fn new<I>(epoch: Epoch, scores: I) -> Self
+    where
+        I: IntoIterator<Item = (Pubkey, u64)>,
+    {
+        let (vote_pubkeys, leaves): (Vec<_>, Vec<_>) = scores
+            .into_iter()
+            .map(|(vote_pubkey, staker_credits)| {
+                (vote_pubkey, score_leaf(epoch, &vote_pubkey, staker_credits))
+            })
+            .unzip();
+
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(level);
+        }
+
+        Self {
+            epoch,
+            levels,
+            vote_pubkeys,
+        }
+    }
+
+    pub fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// The Merkle root, or the default hash for an empty score list
+    pub fn root(&self) -> Hash {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the leaf and inclusion proof for `vote_pubkey`
+    pub fn proof(&self, vote_pubkey: &Pubkey) -> Option<(Hash, MerkleProof)> {
+        let mut index = self.vote_pubkeys.iter().position(|pk| pk == vote_pubkey)?;
+        let leaf = self.levels[0][index];
+
+        let mut siblings = vec![];
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                siblings.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some((leaf, MerkleProof(siblings)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proofs() {
+        let scores = (0..5)
+            .map(|i| (Pubkey::new_unique(), i * 1_000))
+            .collect::<Vec<_>>();
+        let tree = ScoreMerkleTree::new(7, scores.clone());
+        assert_eq!(tree.epoch(), 7);
+
+        for (vote_pubkey, staker_credits) in &scores {
+            let (leaf, proof) = tree.proof(vote_pubkey).unwrap();
+            assert_eq!(leaf, score_leaf(7, vote_pubkey, *staker_credits));
+            assert!(proof.verify(&tree.root(), &leaf));
+
+            assert!(!proof.verify(
+                &tree.root(),
+                &score_leaf(7, vote_pubkey, staker_credits + 1)
+            ));
+            assert!(!proof.verify(&tree.root(), &score_leaf(8, vote_pubkey, *staker_credits)));
+        }
+        assert_eq!(tree.proof(&Pubkey::new_unique()), None);
+    }
+
+    #[test]
+    fn test_single_and_empty_tree() {
+        let vote_pubkey = Pubkey::new_unique();
+        let tree = ScoreMerkleTree::new(7, [(vote_pubkey, 1_000)]);
+        let (leaf, proof) = tree.proof(&vote_pubkey).unwrap();
+        assert_eq!(tree.root(), leaf);
+        assert_eq!(proof, MerkleProof::default());
+        assert!(proof.verify(&tree.root(), &leaf));
+
+        assert_eq!(ScoreMerkleTree::new(7, []).root(), Hash::default());
+    }
+}
@@ -0,0 +1,74 @@
+//! Detection of Jito MEV tip sharing and its commission
+use {
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    std::fmt,
+};
+
+/// The Jito tip distribution program
+pub mod tip_distribution_program {
+    solana_sdk::declare_id!("4R3gSG8BpU4t19KYj8CfnbtRpnT8gtk4dvTHxVRwc2r7");
+}
+
+const TIP_DISTRIBUTION_ACCOUNT_SEED: &[u8] = b"TIP_DISTRIBUTION_ACCOUNT";
+
+/// Anchor discriminator, `validator_vote_account` and `merkle_root_upload_authority`
+const MERKLE_ROOT_OFFSET: usize = 8 + 32 + 32;
+/// Borsh encoding of a `Some(MerkleRoot)`, excluding the option tag
+const MERKLE_ROOT_LEN: usize = 32 + 8 * 4;
+
+/// The share of MEV tips a validator keeps, as configured in its tip distribution account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MevCommission {
+    /// No tip distribution account, the validator doesn't share MEV tips with its stakers
+    NotRunningJito,
+    /// Commission in basis points
+    Bps(u16),
+}
+
+impl MevCommission {
+    pub fn runs_jito(&self) -> bool {
+        matches!(self, MevCommission::Bps(_))
+    }
+
+    pub fn bps(&self) -> Option<u16> {
+        match self {
+            MevCommission::NotRunningJito => None,
+            MevCommission::Bps(bps) => Some(*bps),
+        }
+    }
+}
+
+impl fmt::Display for MevCommission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MevCommission::NotRunningJito => write!(f, "no MEV tips"),
+            MevCommission::Bps(bps) => write!(f, "MEV commission {:.2}%", *bps as f64 / 100.),
+        }
+    }
+}
+
+/// Returns the address of the tip distribution account of `vote_pubkey` for `epoch`
+pub fn tip_distribution_address(vote_pubkey: &Pubkey, epoch: Epoch) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            TIP_DISTRIBUTION_ACCOUNT_SEED,
+            vote_pubkey.as_ref(),
+            &epoch.to_le_bytes(),
+        ],
+        &tip_distribution_program::id(),
+    )
+    .0
+}
+
+/// Returns the `validator_commission_bps` of a tip distribution account
+pub fn parse_tip_distribution_commission_bps(data: &[u8]) -> Option<u16> {
+    let offset = match data.get(MERKLE_ROOT_OFFSET)? {
+        0 => MERKLE_ROOT_OFFSET + 1,
+        1 => MERKLE_ROOT_OFFSET + 1 + MERKLE_ROOT_LEN,
+        _ => return None,
+    };
+    // Skip `epoch_created_at`
+    let offset = offset + 8;
+    let bps = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bps[0], bps[1]]))
+}
@@ -6,10 +6,13 @@ pub struct Notifier {
 }
 
 impl Notifier {
-    pub fn default() -> Self {
-        let slack_webhook = env::var("SLACK_WEBHOOK").ok();
+    pub fn from_env(client: Client) -> Self {
+        Self::new(env::var("SLACK_WEBHOOK").ok(), client)
+    }
+
+    pub fn new(slack_webhook: Option<String>, client: Client) -> Self {
         Notifier {
-            client: Client::new(),
+            client,
             slack_webhook,
         }
     }
@@ -0,0 +1,198 @@
+//! One-shot vetting of a validator applying to a delegation program
+use {
+    crate::{
+        cluster_versions::{get_client_diversity, SoftwareVersion, ValidatorClient},
+        commission_history::{commission_changes, get_commission_history, EpochCommissionsCache},
+        get_mev_commissions,
+        key_hygiene::{check_key_hygiene, KeyHygieneIssue},
+        stake_pools::{get_known_stake_pools, get_stake_attribution, StakeAttribution},
+    },
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    solana_vote_program::vote_state::{VoteState, MAX_EPOCH_CREDITS_HISTORY},
+    std::{fmt, net::SocketAddr},
+};
+
+/// Epochs of commission history included in an onboarding report
+pub const ONBOARDING_COMMISSION_HISTORY_EPOCHS: u64 = 10;
+
+/// Authorized voter age, in epochs, reported as not rotated in an onboarding report
+pub const ONBOARDING_MAX_VOTER_AGE_EPOCHS: u64 = 180;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnboardingReport {
+    pub vote_pubkey: Pubkey,
+    pub identity: Pubkey,
+    pub epoch: Epoch,
+    pub commission: u8,
+    /// Oldest epoch the vote account keeps credits for, `None` if it never earned any
+    pub first_epoch_with_credits: Option<Epoch>,
+    /// Number of epochs the vote account keeps credits for. With `MAX_EPOCH_CREDITS_HISTORY`
+    /// epochs the vote account may have earned credits before `first_epoch_with_credits`
+    pub credits_history_epochs: usize,
+    /// Gossip address of the identity, `None` if the node is not in gossip
+    pub gossip: Option<SocketAddr>,
+    /// The version as reported in gossip
+    pub version: Option<String>,
+    pub client: ValidatorClient,
+    /// The version run by the most Agave-based stake
+    pub majority_version: Option<SoftwareVersion>,
+    pub key_hygiene_issues: Vec<KeyHygieneIssue>,
+    pub stake_attribution: StakeAttribution,
+    /// (epoch, commission) over the last `ONBOARDING_COMMISSION_HISTORY_EPOCHS` epochs, oldest
+    /// first
+    pub commission_history: Vec<(Epoch, u8)>,
+}
+
+impl OnboardingReport {
+    pub fn in_gossip(&self) -> bool {
+        self.gossip.is_some()
+    }
+
+    /// True if the validator runs an older version than the majority of the stake
+    pub fn outdated(&self) -> bool {
+        self.version
+            .as_deref()
+            .and_then(SoftwareVersion::parse)
+            .zip(self.majority_version)
+            .is_some_and(|(version, majority_version)| {
+                self.client != ValidatorClient::Frankendancer && version < majority_version
+            })
+    }
+
+    /// Returns the findings that need a closer look before delegating
+    pub fn findings(&self) -> Vec<String> {
+        let mut findings = self
+            .key_hygiene_issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>();
+        if !self.in_gossip() {
+            findings.push(format!("Identity {} is not in gossip", self.identity));
+        }
+        if self.outdated() {
+            findings.push(format!(
+                "Version {} is behind the majority version {}",
+                self.version.as_deref().unwrap_or("unknown"),
+                self.majority_version
+                    .map(|version| version.to_string())
+                    .unwrap_or_default()
+            ));
+        }
+        for (epoch, previous_commission, commission) in commission_changes(&self.commission_history)
+        {
+            findings.push(format!(
+                "Commission changed from {}% to {}% in epoch {}",
+                previous_commission, commission, epoch
+            ));
+        }
+        if self.first_epoch_with_credits.is_none() {
+            findings.push("Never earned vote credits".to_string());
+        }
+        findings
+    }
+}
+
+impl fmt::Display for OnboardingReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Onboarding report for {}, epoch {}",
+            self.vote_pubkey, self.epoch
+        )?;
+        writeln!(f, "  Identity:      {}", self.identity)?;
+        writeln!(f, "  Commission:    {}%", self.commission)?;
+        match self.first_epoch_with_credits {
+            Some(epoch) if self.credits_history_epochs >= MAX_EPOCH_CREDITS_HISTORY => {
+                writeln!(f, "  Voting since:  epoch {} or earlier", epoch)?
+            }
+            Some(epoch) => writeln!(f, "  Voting since:  epoch {}", epoch)?,
+            None => writeln!(f, "  Voting since:  -")?,
+        }
+        match self.gossip {
+            Some(gossip) => writeln!(f, "  Gossip:        {}", gossip)?,
+            None => writeln!(f, "  Gossip:        not present")?,
+        }
+        writeln!(
+            f,
+            "  Version:       {} ({})",
+            self.version.as_deref().unwrap_or("unknown"),
+            self.client
+        )?;
+        writeln!(f, "  Stake:         {}", self.stake_attribution)?;
+        let findings = self.findings();
+        if findings.is_empty() {
+            write!(f, "  No findings")
+        } else {
+            write!(f, "  Findings:")?;
+            for finding in findings {
+                write!(f, "\n    {}", finding)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Runs every available check on the validator voting with `vote_pubkey`: key hygiene, software
+/// version, gossip presence, stake sources and commission history. Geolocation is not included,
+/// there's no source for it
+pub async fn audit_new_validator(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+) -> Result<OnboardingReport, Box<dyn std::error::Error>> {
+    let vote_account = rpc_client.get_account(vote_pubkey).await?;
+    let vote_state = VoteState::deserialize(&vote_account.data)
+        .map_err(|err| format!("Invalid vote account {}: {}", vote_pubkey, err))?;
+    let identity = vote_state.node_pubkey;
+    let epoch = rpc_client.get_epoch_info().await?.epoch;
+
+    let node = rpc_client
+        .get_cluster_nodes()
+        .await?
+        .into_iter()
+        .find(|node| node.pubkey == identity.to_string());
+    let version = node.as_ref().and_then(|node| node.version.clone());
+    let runs_jito = get_mev_commissions(rpc_client, &[*vote_pubkey], epoch)
+        .await?
+        .get(vote_pubkey)
+        .is_some_and(|mev_commission| mev_commission.runs_jito());
+
+    let first_epoch_with_credits = vote_state.epoch_credits().first().map(|(epoch, ..)| *epoch);
+
+    let commission_history = get_commission_history(
+        rpc_client,
+        &mut EpochCommissionsCache::default(),
+        (epoch + 1).saturating_sub(ONBOARDING_COMMISSION_HISTORY_EPOCHS)..epoch + 1,
+    )
+    .await?
+    .remove(vote_pubkey)
+    .unwrap_or_default();
+
+    Ok(OnboardingReport {
+        vote_pubkey: *vote_pubkey,
+        identity,
+        epoch,
+        commission: vote_state.commission,
+        first_epoch_with_credits,
+        credits_history_epochs: vote_state.epoch_credits().len(),
+        gossip: node.and_then(|node| node.gossip),
+        client: ValidatorClient::detect(version.as_deref(), runs_jito),
+        version,
+        majority_version: get_client_diversity(rpc_client, None)
+            .await?
+            .majority_version(),
+        key_hygiene_issues: check_key_hygiene(
+            rpc_client,
+            vote_pubkey,
+            ONBOARDING_MAX_VOTER_AGE_EPOCHS,
+        )
+        .await?,
+        stake_attribution: get_stake_attribution(
+            rpc_client,
+            vote_pubkey,
+            &get_known_stake_pools(rpc_client).await?,
+        )
+        .await?,
+        commission_history,
+    })
+}
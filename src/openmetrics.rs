@@ -0,0 +1,139 @@
+//! Rendering of metrics in the OpenMetrics text format, for the node_exporter textfile collector
+use std::{fmt::Write as _, fs, io, path::Path};
+
+struct Sample {
+    labels: Vec<(String, String)>,
+    value: f64,
+}
+
+struct Gauge {
+    name: String,
+    help: String,
+    samples: Vec<Sample>,
+}
+
+/// A set of gauges, rendered in the order they were first added
+#[derive(Default)]
+pub struct MetricSet {
+    gauges: Vec<Gauge>,
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Formats a sample value, with infinities spelled the way OpenMetrics expects
+fn format_value(value: f64) -> String {
+    match value {
+        f64::INFINITY => "+Inf".to_string(),
+        f64::NEG_INFINITY => "-Inf".to_string(),
+        value => value.to_string(),
+    }
+}
+
+impl MetricSet {
+    /// Adds a sample of the gauge `name`. Samples of the same gauge are grouped together, its
+    /// `help` is taken from the first sample
+    pub fn gauge(&mut self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        let sample = Sample {
+            labels: labels
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            value,
+        };
+        match self.gauges.iter_mut().find(|gauge| gauge.name == name) {
+            Some(gauge) => gauge.samples.push(sample),
+            None => self.gauges.push(Gauge {
+                name: name.to_string(),
+                help: help.to_string(),
+                samples: vec![sample],
+            }),
+        }
+    }
+
+    /// Iterates over every sample as (gauge name, labels, value)
+    pub fn samples(&self) -> impl Iterator<Item = (&str, &[(String, String)], f64)> {
+        self.gauges.iter().flat_map(|gauge| {
+            gauge
+                .samples
+                .iter()
+                .map(move |sample| (gauge.name.as_str(), sample.labels.as_slice(), sample.value))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gauges.is_empty()
+    }
+
+    /// Renders the set in the OpenMetrics text format, including the terminating `# EOF`
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for gauge in &self.gauges {
+            let _ = writeln!(text, "# HELP {} {}", gauge.name, gauge.help);
+            let _ = writeln!(text, "# TYPE {} gauge", gauge.name);
+            for sample in &gauge.samples {
+                let labels = sample
+                    .labels
+                    .iter()
+                    .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+                    .collect::<Vec<_>>();
+                let value = format_value(sample.value);
+                if labels.is_empty() {
+                    let _ = writeln!(text, "{} {}", gauge.name, value);
+                } else {
+                    let _ = writeln!(text, "{}{{{}}} {}", gauge.name, labels.join(","), value);
+                }
+            }
+        }
+        text.push_str("# EOF\n");
+        text
+    }
+
+    /// Writes the rendered set to `path`. The file is replaced atomically so the collector never
+    /// reads a partial file
+    pub fn write_textfile(&self, path: &Path) -> io::Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, self.render())?;
+        fs::rename(&tmp_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_label_value() {
+        assert_eq!(escape_label_value("plain"), "plain");
+        assert_eq!(
+            escape_label_value("a \"quoted\" C:\\path\nand more"),
+            "a \\\"quoted\\\" C:\\\\path\\nand more"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let mut metric_set = MetricSet::default();
+        assert!(metric_set.is_empty());
+        metric_set.gauge("credits", "Vote credits", &[("vote", "Vote\"1")], 42.);
+        metric_set.gauge("rank", "Rank", &[], f64::INFINITY);
+        metric_set.gauge("credits", "Ignored", &[("vote", "Vote2")], 0.5);
+        assert_eq!(
+            metric_set.render(),
+            "# HELP credits Vote credits\n\
+             # TYPE credits gauge\n\
+             credits{vote=\"Vote\\\"1\"} 42\n\
+             credits{vote=\"Vote2\"} 0.5\n\
+             # HELP rank Rank\n\
+             # TYPE rank gauge\n\
+             rank +Inf\n\
+             # EOF\n"
+        );
+        assert_eq!(metric_set.samples().count(), 3);
+    }
+}
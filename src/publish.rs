@@ -0,0 +1,116 @@
+//! Publishing of per-epoch score summaries on chain
+use {
+    crate::{
+        attestation::canonical_json, error::Error, feed::ScoreFeed, schema::with_schema_version,
+    },
+    serde_json::{json, Value},
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        hash::hash,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        pubkey::Pubkey,
+        signature::Signature,
+        signer::Signer,
+        transaction::Transaction,
+    },
+};
+
+/// The SPL Memo program
+pub mod memo_program {
+    solana_sdk::declare_id!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+}
+
+/// Where score summaries are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishTarget {
+    /// An SPL memo signed by the publishing authority. Consumers discover summaries through the
+    /// authority's transaction history
+    Memo,
+    /// An instruction to `program_id` that stores the summary in `account`. The instruction
+    /// accounts are `[account (writable), authority (signer)]` and its data is the summary
+    DataAccount { program_id: Pubkey, account: Pubkey },
+}
+
+pub enum PublishOutcome {
+    /// The signed transaction that would have been sent
+    DryRun(Transaction),
+    Sent(Signature),
+}
+
+/// Returns a compact summary of `score_feed`, small enough to fit in a single transaction.
+///
+/// The full feed is committed to by its hash, and individual scores by the feed's Merkle root so
+/// they can be proven off chain
+pub fn score_summary(score_feed: &ScoreFeed) -> Value {
+    let staker_credits = score_feed
+        .entries
+        .iter()
+        .map(|entry| entry.staker_credits)
+        .collect::<Vec<_>>();
+
+    with_schema_version(json!({
+        "epoch": score_feed.epoch,
+        "validators": score_feed.entries.len(),
+        "eligible": score_feed.entries.iter().filter(|entry| entry.eligible).count(),
+        "topScore": staker_credits.iter().max().copied().unwrap_or_default(),
+        "medianScore": staker_credits.get(staker_credits.len() / 2).copied().unwrap_or_default(),
+        "feedHash": hash(canonical_json(&score_feed.to_json()).as_bytes()).to_string(),
+        "merkleRoot": score_feed.merkle_tree().root().to_string(),
+    }))
+}
+
+pub fn publish_instruction(target: PublishTarget, authority: &Pubkey, data: &[u8]) -> Instruction {
+    match target {
+        PublishTarget::Memo => Instruction {
+            program_id: memo_program::id(),
+            accounts: vec![AccountMeta::new_readonly(*authority, true)],
+            data: data.to_vec(),
+        },
+        PublishTarget::DataAccount {
+            program_id,
+            account,
+        } => Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(account, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+            data: data.to_vec(),
+        },
+    }
+}
+
+/// Writes the summary of `score_feed` to `target`, paid for and signed by `authority`.
+///
+/// With `dry_run` the transaction is built and signed but not sent. Fails with `Error::Config` if
+/// `authority` can't sign, for example a hardware wallet that was unplugged or rejected the
+/// transaction
+pub async fn publish_score_summary(
+    rpc_client: &RpcClient,
+    target: PublishTarget,
+    authority: &dyn Signer,
+    score_feed: &ScoreFeed,
+    dry_run: bool,
+) -> Result<PublishOutcome, Box<dyn std::error::Error>> {
+    let summary = score_summary(score_feed).to_string();
+    let instruction = publish_instruction(target, &authority.pubkey(), summary.as_bytes());
+
+    let signers: Vec<&dyn Signer> = vec![authority];
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let mut transaction =
+        Transaction::new_unsigned(Message::new(&[instruction], Some(&authority.pubkey())));
+    transaction
+        .try_sign(&signers, recent_blockhash)
+        .map_err(|err| Error::Config(format!("Unable to sign the summary transaction: {}", err)))?;
+
+    if dry_run {
+        return Ok(PublishOutcome::DryRun(transaction));
+    }
+
+    Ok(PublishOutcome::Sent(
+        rpc_client
+            .send_and_confirm_transaction(&transaction)
+            .await?,
+    ))
+}
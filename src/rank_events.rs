@@ -0,0 +1,142 @@
+//! Events for changes of a validator's position in the credit leaderboard between epochs
+use {
+    crate::{amount::Lamports, report::Percentiles},
+    solana_sdk::pubkey::Pubkey,
+    std::fmt,
+};
+
+/// A validator's position in the output of `get_validators_by_credit_score`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderboardPosition {
+    /// Rank, from 1
+    pub rank: usize,
+    pub percentile: u8,
+}
+
+impl LeaderboardPosition {
+    /// Returns the position of `vote_pubkey` in `validators_by_staker_credits`, `None` if it's
+    /// not in the list
+    pub fn new(
+        validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+        vote_pubkey: &Pubkey,
+    ) -> Option<Self> {
+        let index = validators_by_staker_credits
+            .iter()
+            .position(|(_, pubkey, _)| pubkey == vote_pubkey)?;
+        let percentiles = Percentiles::new(
+            &validators_by_staker_credits
+                .iter()
+                .map(|(staker_credits, ..)| *staker_credits as f64)
+                .collect::<Vec<_>>(),
+        );
+        Some(Self {
+            rank: index + 1,
+            percentile: percentiles.rank(validators_by_staker_credits[index].0 as f64),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankEvent {
+    EnteredTopN {
+        n: usize,
+        rank: usize,
+    },
+    /// `rank` is `None` when the validator is no longer in the leaderboard
+    LeftTopN {
+        n: usize,
+        rank: Option<usize>,
+    },
+    /// The percentile rose to or above `boundary`
+    RoseAbovePercentile {
+        boundary: u8,
+        percentile: u8,
+    },
+    /// The percentile fell below `boundary`
+    FellBelowPercentile {
+        boundary: u8,
+        percentile: u8,
+    },
+}
+
+impl fmt::Display for RankEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RankEvent::EnteredTopN { n, rank } => {
+                write!(f, "Entered the top {} at rank {}", n, rank)
+            }
+            RankEvent::LeftTopN {
+                n,
+                rank: Some(rank),
+            } => {
+                write!(f, "Left the top {}, now rank {}", n, rank)
+            }
+            RankEvent::LeftTopN { n, rank: None } => {
+                write!(f, "Left the top {}, no longer ranked", n)
+            }
+            RankEvent::RoseAbovePercentile {
+                boundary,
+                percentile,
+            } => write!(
+                f,
+                "Rose to the {}th percentile, above the {}th",
+                percentile, boundary
+            ),
+            RankEvent::FellBelowPercentile {
+                boundary,
+                percentile,
+            } => write!(
+                f,
+                "Fell to the {}th percentile, below the {}th",
+                percentile, boundary
+            ),
+        }
+    }
+}
+
+/// Returns the events for the move of a validator from `previous` to `current`: entering or
+/// leaving the top `top_n`, and crossing any of `percentile_boundaries`. A validator missing
+/// from a leaderboard is treated as outside the top `top_n`, at the 0th percentile
+pub fn rank_events(
+    previous: Option<LeaderboardPosition>,
+    current: Option<LeaderboardPosition>,
+    top_n: usize,
+    percentile_boundaries: &[u8],
+) -> Vec<RankEvent> {
+    let in_top_n = |position: Option<LeaderboardPosition>| {
+        position.is_some_and(|position| position.rank <= top_n)
+    };
+    let percentile = |position: Option<LeaderboardPosition>| {
+        position
+            .map(|position| position.percentile)
+            .unwrap_or_default()
+    };
+    let rank = current.map(|position| position.rank);
+
+    let mut events = vec![];
+    match (in_top_n(previous), in_top_n(current)) {
+        (false, true) => events.push(RankEvent::EnteredTopN {
+            n: top_n,
+            // Only a ranked validator can be in the top `top_n`
+            rank: rank.unwrap_or_default(),
+        }),
+        (true, false) => events.push(RankEvent::LeftTopN { n: top_n, rank }),
+        _ => {}
+    }
+
+    let (previous_percentile, current_percentile) = (percentile(previous), percentile(current));
+    for boundary in percentile_boundaries {
+        if previous_percentile < *boundary && current_percentile >= *boundary {
+            events.push(RankEvent::RoseAbovePercentile {
+                boundary: *boundary,
+                percentile: current_percentile,
+            });
+        } else if previous_percentile >= *boundary && current_percentile < *boundary {
+            events.push(RankEvent::FellBelowPercentile {
+                boundary: *boundary,
+                percentile: current_percentile,
+            });
+        }
+    }
+    events
+}
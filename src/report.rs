@@ -0,0 +1,378 @@
+//! Human readable rendering of validator credit scores
+use {
+    crate::{amount::Lamports, format::Locale, score::SmoothedScore},
+    solana_sdk::{
+        native_token::{lamports_to_sol, LAMPORTS_PER_SOL},
+        pubkey::Pubkey,
+    },
+    std::collections::BTreeMap,
+};
+
+/// Sorted sample used to compute percentiles over a set of staker credits
+pub struct Percentiles(Vec<f64>);
+
+impl Percentiles {
+    pub fn new(samples: &[f64]) -> Self {
+        let mut samples = samples.to_vec();
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        Self(samples)
+    }
+
+    /// Returns the value at the `p`th percentile, linearly interpolating between samples
+    pub fn at(&self, p: f64) -> f64 {
+        if self.0.is_empty() {
+            return 0.;
+        }
+        let len = self.0.len() - 1;
+        if p >= 100. {
+            return self.0[len];
+        }
+
+        let rank = (p.max(0.) / 100.) * len as f64;
+        let integer = rank.floor();
+        let fraction = rank - integer;
+        let n = integer as usize;
+        let floor = self.0[n];
+        let ceiling = self.0[(n + 1).min(len)];
+        floor + (ceiling - floor) * fraction
+    }
+
+    /// Returns the highest whole percentile at or below `value`
+    pub fn rank(&self, value: f64) -> u8 {
+        (1..=100u8)
+            .rev()
+            .find(|p| value >= self.at(*p as f64))
+            .unwrap_or_default()
+    }
+}
+
+/// Inputs required to estimate the rewards of each validator in the current, incomplete, epoch
+pub struct RewardEstimate {
+    pub estimated_total_epoch_reward: u64,
+    pub total_activated_stake: u64,
+    pub total_points: u128,
+}
+
+/// Renders the output of `get_validators_by_credit_score` as one line per validator.
+///
+/// At most `num` validators are included, and only those in at least the `max_percentile`th
+/// percentile. When `annotations` is provided each line also shows the validator's annotations,
+/// such as its MEV commission or client. Numbers are written as `locale` says.
+pub fn format_validators_by_credit_score(
+    validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+    num: usize,
+    max_percentile: u8,
+    reward_estimate: Option<&RewardEstimate>,
+    annotations: Option<&BTreeMap<Pubkey, Vec<String>>>,
+    locale: &Locale,
+) -> String {
+    let number_format = &locale.number_format;
+    let staker_credits = validators_by_staker_credits
+        .iter()
+        .map(|(staker_credits, ..)| *staker_credits as f64)
+        .collect::<Vec<_>>();
+
+    let top_staker_credits = staker_credits.first().copied().unwrap_or_default();
+    let staker_credit_percentiles = Percentiles::new(&staker_credits);
+
+    let mut p = 100u8;
+    validators_by_staker_credits
+        .iter()
+        .take(num)
+        .enumerate()
+        .filter_map(|(i, (staker_credits, vote_pubkey, activated_stake))| {
+            let (staker_credits, activated_stake) = (*staker_credits, *activated_stake);
+            while p > 0 {
+                let percentile_credits = staker_credit_percentiles.at(p as f64);
+                if staker_credits as f64 >= percentile_credits {
+                    break;
+                }
+                p = p.saturating_sub(1);
+            }
+
+            if p < max_percentile {
+                None
+            } else {
+                let percent_of_top_staker = staker_credits as f64 * 100. / top_staker_credits;
+
+                let credits_behind =
+                    (top_staker_credits.floor() as u64).saturating_sub(staker_credits);
+
+                let current_epoch_estimates = match reward_estimate {
+                    Some(reward_estimate) => {
+                        let points = u128::from(activated_stake) * u128::from(staker_credits);
+
+                        let estimated_epoch_reward =
+                            (reward_estimate.estimated_total_epoch_reward as u128 * points
+                                / reward_estimate.total_points.max(1))
+                                as u64;
+
+                        let expected_epoch_reward =
+                            u128::from(reward_estimate.estimated_total_epoch_reward)
+                                * u128::from(activated_stake)
+                                / u128::from(reward_estimate.total_activated_stake.max(1));
+
+                        format!(
+                            "| estimated {} (expected: {})",
+                            number_format.format_sol(estimated_epoch_reward, 9),
+                            number_format.format_sol(expected_epoch_reward as u64, 9),
+                        )
+                    }
+                    None => "".into(),
+                };
+
+                let annotations = annotations
+                    .and_then(|annotations| annotations.get(vote_pubkey))
+                    .map(|annotations| {
+                        annotations
+                            .iter()
+                            .map(|annotation| format!(" [{}]", annotation))
+                            .collect::<String>()
+                    })
+                    .unwrap_or_default();
+
+                #[allow(clippy::to_string_in_format_args)]
+                let vote_pubkey_str = vote_pubkey.to_string();
+
+                Some(format!(
+                    "{:>4}. {:<44} ({:>6}%) ({:>3}th percentile){}{} {}",
+                    i + 1,
+                    vote_pubkey_str,
+                    number_format.format_decimal(percent_of_top_staker, 2),
+                    p,
+                    if credits_behind > 0 {
+                        format!(
+                            " [-{} credits]",
+                            number_format.format_integer(credits_behind)
+                        )
+                    } else {
+                        "".into()
+                    },
+                    annotations,
+                    current_epoch_estimates,
+                ))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A validator's projected delegator yield, derived from its staker credits
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatorYield {
+    pub vote_pubkey: Pubkey,
+    pub staker_credits: u64,
+    /// Yield relative to the highest yielding validator, from 0.0 to 1.0
+    pub relative_yield: f64,
+    /// Projected annual yield, in percent, without compounding
+    pub apy: f64,
+}
+
+/// Ranks the output of `get_validators_by_credit_score` by projected delegator yield.
+///
+/// The staker credits must have been computed with commission deducted. Each validator's share
+/// of `reward_estimate.estimated_total_epoch_reward` per lamport of stake is annualized with
+/// `epochs_per_year`
+pub fn validators_by_yield(
+    validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+    reward_estimate: &RewardEstimate,
+    epochs_per_year: f64,
+) -> Vec<ValidatorYield> {
+    let max_staker_credits = validators_by_staker_credits
+        .iter()
+        .map(|(staker_credits, ..)| *staker_credits)
+        .max()
+        .unwrap_or_default()
+        .max(1) as f64;
+    let reward_per_point = reward_estimate.estimated_total_epoch_reward as f64
+        / reward_estimate.total_points.max(1) as f64;
+
+    let mut yields = validators_by_staker_credits
+        .iter()
+        .map(|(staker_credits, vote_pubkey, _)| ValidatorYield {
+            vote_pubkey: *vote_pubkey,
+            staker_credits: *staker_credits,
+            relative_yield: *staker_credits as f64 / max_staker_credits,
+            apy: *staker_credits as f64 * reward_per_point * epochs_per_year * 100.,
+        })
+        .collect::<Vec<_>>();
+    yields.sort_by(|a, b| {
+        b.apy
+            .partial_cmp(&a.apy)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    yields
+}
+
+/// Renders the output of `validators_by_yield` as one line per validator, at most `num`
+pub fn format_validators_by_yield(validator_yields: &[ValidatorYield], num: usize) -> String {
+    validator_yields
+        .iter()
+        .take(num)
+        .enumerate()
+        .map(|(i, validator_yield)| {
+            #[allow(clippy::to_string_in_format_args)]
+            let vote_pubkey_str = validator_yield.vote_pubkey.to_string();
+
+            format!(
+                "{:>4}. {:<44} {:>6.3}% APY ({:>6.2}% of top)",
+                i + 1,
+                vote_pubkey_str,
+                validator_yield.apy,
+                validator_yield.relative_yield * 100.,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders the output of `score::score_validators_smoothed` as one line per validator, at most
+/// `num`, with the raw score of the scored epoch alongside the smoothed score
+pub fn format_validators_by_smoothed_score(
+    smoothed_scores: &[SmoothedScore],
+    num: usize,
+) -> String {
+    let top_smoothed_staker_credits = smoothed_scores
+        .first()
+        .map(|smoothed_score| smoothed_score.smoothed_staker_credits)
+        .unwrap_or_default()
+        .max(1.);
+
+    smoothed_scores
+        .iter()
+        .take(num)
+        .enumerate()
+        .map(|(i, smoothed_score)| {
+            #[allow(clippy::to_string_in_format_args)]
+            let vote_pubkey_str = smoothed_score.vote_pubkey.to_string();
+
+            format!(
+                "{:>4}. {:<44} ({:>6.2}%) smoothed {:>9.0}, epoch {:>7}",
+                i + 1,
+                vote_pubkey_str,
+                smoothed_score.smoothed_staker_credits * 100. / top_smoothed_staker_credits,
+                smoothed_score.smoothed_staker_credits,
+                smoothed_score.staker_credits,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Validators with activated stake in `min_stake..max_stake`, and the distribution of their
+/// staker credits
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakeCohort {
+    /// Lower bound, in lamports, inclusive
+    pub min_stake: u64,
+    /// Upper bound, in lamports, exclusive. `None` for the largest cohort
+    pub max_stake: Option<u64>,
+    pub validators: usize,
+    pub p25_staker_credits: f64,
+    pub median_staker_credits: f64,
+    pub p75_staker_credits: f64,
+    pub top_staker_credits: f64,
+}
+
+/// Stake bucket boundaries, in lamports: 10k, 100k and 1M SOL
+pub fn default_stake_buckets() -> Vec<u64> {
+    [10_000, 100_000, 1_000_000]
+        .iter()
+        .map(|sol| sol * LAMPORTS_PER_SOL)
+        .collect()
+}
+
+/// Groups the output of `get_validators_by_credit_score` into cohorts separated by the
+/// ascending `bucket_bounds`, in lamports, so validators can be compared with peers of similar
+/// stake. Validators without activated stake are left out
+pub fn stake_cohorts(
+    validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+    bucket_bounds: &[u64],
+) -> Vec<StakeCohort> {
+    let mut bounds = vec![0];
+    bounds.extend(bucket_bounds.iter().copied().filter(|bound| *bound > 0));
+
+    bounds
+        .iter()
+        .enumerate()
+        .map(|(i, min_stake)| {
+            let max_stake = bounds.get(i + 1).copied();
+            let staker_credits = validators_by_staker_credits
+                .iter()
+                .filter(|(_, _, activated_stake)| {
+                    let activated_stake = activated_stake.lamports();
+                    activated_stake > 0
+                        && activated_stake >= *min_stake
+                        && max_stake.is_none_or(|max_stake| activated_stake < max_stake)
+                })
+                .map(|(staker_credits, ..)| *staker_credits as f64)
+                .collect::<Vec<_>>();
+            let percentiles = Percentiles::new(&staker_credits);
+
+            StakeCohort {
+                min_stake: *min_stake,
+                max_stake,
+                validators: staker_credits.len(),
+                p25_staker_credits: percentiles.at(25.),
+                median_staker_credits: percentiles.at(50.),
+                p75_staker_credits: percentiles.at(75.),
+                top_staker_credits: percentiles.at(100.),
+            }
+        })
+        .collect()
+}
+
+/// Renders the output of `stake_cohorts` as one line per cohort, with numbers written as
+/// `locale` says
+pub fn format_stake_cohorts(stake_cohorts: &[StakeCohort], locale: &Locale) -> String {
+    let number_format = &locale.number_format;
+    stake_cohorts
+        .iter()
+        .map(|cohort| {
+            let sol =
+                |lamports: u64| format!("◎{}", number_format.abbreviate(lamports_to_sol(lamports)));
+            let range = match cohort.max_stake {
+                Some(max_stake) => format!("{} - {}", sol(cohort.min_stake), sol(max_stake)),
+                None => format!(">= {}", sol(cohort.min_stake)),
+            };
+            format!(
+                "{:<18} {:>5} validators | p25 {:>7} | median {:>7} | p75 {:>7} | top {:>7}",
+                range,
+                cohort.validators,
+                number_format.format_decimal(cohort.p25_staker_credits, 0),
+                number_format.format_decimal(cohort.median_staker_credits, 0),
+                number_format.format_decimal(cohort.p75_staker_credits, 0),
+                number_format.format_decimal(cohort.top_staker_credits, 0),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let percentiles = Percentiles::new(&[30., 10., 20., 0., 40.]);
+        assert_eq!(percentiles.at(0.), 0.);
+        assert_eq!(percentiles.at(50.), 20.);
+        assert_eq!(percentiles.at(100.), 40.);
+        assert_eq!(percentiles.at(150.), 40.);
+        assert_eq!(percentiles.at(-10.), 0.);
+        // Interpolated halfway between 10 and 20
+        assert_eq!(percentiles.at(37.5), 15.);
+
+        assert_eq!(percentiles.rank(40.), 100);
+        assert_eq!(percentiles.rank(20.), 50);
+        assert_eq!(percentiles.rank(0.), 0);
+    }
+
+    #[test]
+    fn test_percentiles_empty() {
+        let percentiles = Percentiles::new(&[]);
+        assert_eq!(percentiles.at(50.), 0.);
+        assert_eq!(percentiles.rank(0.), 100);
+    }
+}
@@ -0,0 +1,426 @@
+//! Staking reward estimates and breakdowns
+use {
+    crate::{
+        epoch_info::EpochInfoHandle,
+        error::Error,
+        leader_schedule::get_leader_slots_in_epoch,
+        mev::{parse_tip_distribution_commission_bps, tip_distribution_address},
+        rpc::get_first_block_rewards,
+        schema::with_schema_version,
+        stake_pools::get_delegated_stake_accounts,
+        vote_accounts::VoteAccountsSnapshot,
+    },
+    serde_json::{json, Value},
+    solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcBlockConfig},
+    solana_sdk::{
+        clock::{Epoch, Slot},
+        inflation::Inflation,
+        native_token::{lamports_to_sol, Sol},
+        pubkey::Pubkey,
+        reward_type::RewardType,
+    },
+    solana_vote_program::vote_state::VoteState,
+    std::{
+        collections::{BTreeMap, HashSet},
+        fmt,
+        ops::Range,
+    },
+};
+
+/// Hard coded value from genesis.tar.gz
+pub const SLOTS_PER_YEAR: f64 = 78892314.984;
+/// Slot at which the `7XRJcS5Ud5vxGB54JbK9N2vBZVwnwdBNeJW1ibRgD9gx` feature was activated on
+/// mainnet
+const INFLATION_ACTIVATION_SLOT: u64 = 64800004;
+
+/// Number of completed epochs of credit history used to project future credits
+const CREDIT_HISTORY_EPOCHS: usize = 5;
+
+/// Returns the estimated rewards, in lamports, paid out to all stakers for the current epoch
+pub async fn get_estimated_total_epoch_reward(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let inflation = {
+        let rpc_inflation_governor = rpc_client.get_inflation_governor().await?;
+
+        let mut inflation = Inflation::default();
+        inflation.initial = rpc_inflation_governor.initial;
+        inflation.terminal = rpc_inflation_governor.terminal;
+        inflation.taper = rpc_inflation_governor.taper;
+        inflation.foundation = rpc_inflation_governor.foundation;
+        inflation.foundation_term = rpc_inflation_governor.foundation_term;
+        inflation
+    };
+
+    let estimated_total_supply = rpc_client.supply().await?.value.total;
+
+    let first_slot_in_epoch = epoch_info.absolute_slot - epoch_info.slot_index;
+
+    let inflation_num_slots =
+        first_slot_in_epoch - (INFLATION_ACTIVATION_SLOT - epoch_info.slots_in_epoch);
+
+    let inflation_rate = inflation.validator(inflation_num_slots as f64 / SLOTS_PER_YEAR);
+    let epoch_duration_in_years = epoch_info.slots_in_epoch as f64 / SLOTS_PER_YEAR;
+    Ok((inflation_rate * estimated_total_supply as f64 * epoch_duration_in_years) as u64)
+}
+
+/// Projected rewards for a hypothetical delegation
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelegatorRewardEstimate {
+    pub vote_pubkey: Pubkey,
+    /// Delegated stake, in lamports
+    pub stake: u64,
+    pub commission: u8,
+    /// Credits per epoch the validator is assumed to keep earning
+    pub credits_per_epoch: u64,
+    pub epochs: u64,
+    /// Estimated rewards, in lamports, after commission, per epoch
+    pub epoch_reward: u64,
+    pub epochs_per_year: f64,
+}
+
+impl DelegatorRewardEstimate {
+    /// Estimated rewards, in lamports, after commission, over all `epochs`
+    pub fn total_reward(&self) -> u64 {
+        self.epoch_reward.saturating_mul(self.epochs)
+    }
+
+    /// Estimated annual yield, in percent, without compounding
+    pub fn apy(&self) -> f64 {
+        self.epoch_reward as f64 * self.epochs_per_year * 100. / self.stake.max(1) as f64
+    }
+}
+
+/// Returns the rewards, in lamports, after commission, earned in one epoch by `stake` delegated
+/// to a validator earning `credits` with `commission`, given the cluster's `total_points` and
+/// `total_epoch_reward`
+pub fn project_epoch_reward(
+    stake: u64,
+    credits: u64,
+    commission: u8,
+    total_points: u128,
+    total_epoch_reward: u64,
+) -> u64 {
+    let points = u128::from(stake) * u128::from(credits);
+    let reward = u128::from(total_epoch_reward) * points / total_points.max(1);
+    (reward * u128::from(100 - commission.min(100)) / 100) as u64
+}
+
+/// Projects the rewards of delegating `stake_lamports` to `vote_pubkey` for `epochs` epochs,
+/// assuming the validator keeps earning its average credits over the recent completed epochs and
+/// keeps its current commission
+pub async fn estimate_delegator_rewards(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    vote_accounts: &VoteAccountsSnapshot,
+    stake_lamports: u64,
+    vote_pubkey: &Pubkey,
+    epochs: u64,
+) -> Result<DelegatorRewardEstimate, Box<dyn std::error::Error>> {
+    let total_epoch_reward = get_estimated_total_epoch_reward(rpc_client, epoch_info).await?;
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let last_completed_epoch = epoch_info
+        .epoch
+        .checked_sub(1)
+        .ok_or("No completed epoch yet")?;
+
+    let total_points = vote_accounts
+        .iter_with_epoch_credits(last_completed_epoch)
+        .map(|(vai, credits)| u128::from(vai.activated_stake) * u128::from(credits))
+        .sum::<u128>();
+
+    let vai = vote_accounts
+        .find_by_vote(vote_pubkey)
+        .ok_or_else(|| format!("Vote account {} not found", vote_pubkey))?;
+
+    let recent_credits = vai
+        .epoch_credits
+        .iter()
+        .rev()
+        .filter(|(epoch, ..)| *epoch <= last_completed_epoch)
+        .take(CREDIT_HISTORY_EPOCHS)
+        .map(|(_, credits, prev_credits)| credits.saturating_sub(*prev_credits))
+        .collect::<Vec<_>>();
+    let credits_per_epoch =
+        recent_credits.iter().sum::<u64>() / (recent_credits.len().max(1) as u64);
+
+    // The new delegation adds to the cluster's points
+    let total_points = total_points + u128::from(stake_lamports) * u128::from(credits_per_epoch);
+
+    Ok(DelegatorRewardEstimate {
+        vote_pubkey: *vote_pubkey,
+        stake: stake_lamports,
+        commission: vai.commission,
+        credits_per_epoch,
+        epochs,
+        epoch_reward: project_epoch_reward(
+            stake_lamports,
+            credits_per_epoch,
+            vai.commission,
+            total_points,
+            total_epoch_reward,
+        ),
+        epochs_per_year: SLOTS_PER_YEAR / epoch_info.slots_in_epoch.max(1) as f64,
+    })
+}
+
+/// A reward paid to a stake account at the end of an epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakeAccountReward {
+    pub stake_pubkey: Pubkey,
+    pub epoch: Epoch,
+    pub effective_slot: Slot,
+    /// Reward, in lamports
+    pub amount: u64,
+    /// Balance, in lamports, of the stake account after the reward
+    pub post_balance: u64,
+    /// Commission of the validator the stake was delegated to when the reward was paid
+    pub commission: Option<u8>,
+}
+
+/// Returns the rewards paid to `stake_pubkeys` for each epoch in `epochs`, ordered by epoch.
+/// Epochs in which an account was not rewarded are omitted
+pub async fn get_stake_account_rewards(
+    rpc_client: &RpcClient,
+    stake_pubkeys: &[Pubkey],
+    epochs: Range<Epoch>,
+) -> Result<Vec<StakeAccountReward>, Box<dyn std::error::Error>> {
+    let mut rewards = vec![];
+    for epoch in epochs {
+        let inflation_rewards = rpc_client
+            .get_inflation_reward(stake_pubkeys, Some(epoch))
+            .await?;
+        for (stake_pubkey, inflation_reward) in stake_pubkeys.iter().zip(inflation_rewards) {
+            if let Some(inflation_reward) = inflation_reward {
+                rewards.push(StakeAccountReward {
+                    stake_pubkey: *stake_pubkey,
+                    epoch: inflation_reward.epoch,
+                    effective_slot: inflation_reward.effective_slot,
+                    amount: inflation_reward.amount,
+                    post_balance: inflation_reward.post_balance,
+                    commission: inflation_reward.commission,
+                });
+            }
+        }
+    }
+    Ok(rewards)
+}
+
+/// Returns the total rewards, in lamports, of all accounts in `rewards` per epoch
+pub fn total_rewards_by_epoch(rewards: &[StakeAccountReward]) -> BTreeMap<Epoch, u64> {
+    let mut totals = BTreeMap::new();
+    for reward in rewards {
+        *totals.entry(reward.epoch).or_default() += reward.amount;
+    }
+    totals
+}
+
+/// Renders `rewards` as CSV, one row per account and epoch
+pub fn stake_account_rewards_to_csv(rewards: &[StakeAccountReward]) -> String {
+    let mut csv = String::from(
+        "epoch,stake_account,effective_slot,amount_lamports,amount_sol,\
+         post_balance_lamports,commission\n",
+    );
+    for reward in rewards {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            reward.epoch,
+            reward.stake_pubkey,
+            reward.effective_slot,
+            reward.amount,
+            lamports_to_sol(reward.amount),
+            reward.post_balance,
+            reward
+                .commission
+                .map(|commission| commission.to_string())
+                .unwrap_or_default(),
+        ));
+    }
+    csv
+}
+
+/// Renders `rewards` as JSON, with the total rewards of all accounts per epoch
+pub fn stake_account_rewards_to_json(rewards: &[StakeAccountReward]) -> Value {
+    with_schema_version(json!({
+        "rewards": rewards.iter().map(|reward| json!({
+            "epoch": reward.epoch,
+            "stakeAccount": reward.stake_pubkey.to_string(),
+            "effectiveSlot": reward.effective_slot,
+            "amount": reward.amount,
+            "postBalance": reward.post_balance,
+            "commission": reward.commission,
+        })).collect::<Vec<_>>(),
+        "totalsByEpoch": total_rewards_by_epoch(rewards)
+            .into_iter()
+            .map(|(epoch, amount)| json!({ "epoch": epoch, "amount": amount }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// Where a validator's income for one epoch came from, in lamports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochRewardsBreakdown {
+    pub vote_pubkey: Pubkey,
+    pub identity: Pubkey,
+    pub epoch: Epoch,
+    /// Inflation rewards paid to the stake accounts currently delegated to the validator
+    pub staker_rewards: u64,
+    /// Inflation rewards paid to the vote account, i.e. the validator's commission
+    pub commission_income: u64,
+    /// Transaction fees paid to the identity for the blocks it produced
+    pub block_rewards: u64,
+    pub blocks_produced: usize,
+    /// Tips collected in the validator's Jito tip distribution account, `None` if the validator
+    /// didn't run Jito or the account has been closed
+    pub mev_tips: Option<u64>,
+    /// The validator's share of `mev_tips`
+    pub mev_commission_income: Option<u64>,
+}
+
+impl EpochRewardsBreakdown {
+    /// Income kept by the validator: commission, block rewards and its share of MEV tips
+    pub fn validator_income(&self) -> u64 {
+        self.commission_income + self.block_rewards + self.mev_commission_income.unwrap_or(0)
+    }
+}
+
+impl fmt::Display for EpochRewardsBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Rewards of {} in epoch {}:",
+            self.vote_pubkey, self.epoch
+        )?;
+        writeln!(f, "  Staker rewards:    {}", Sol(self.staker_rewards))?;
+        writeln!(f, "  Commission income: {}", Sol(self.commission_income))?;
+        writeln!(
+            f,
+            "  Block rewards:     {} ({} blocks)",
+            Sol(self.block_rewards),
+            self.blocks_produced
+        )?;
+        match (self.mev_tips, self.mev_commission_income) {
+            (Some(mev_tips), Some(mev_commission_income)) => writeln!(
+                f,
+                "  MEV tips:          {} (validator share {})",
+                Sol(mev_tips),
+                Sol(mev_commission_income)
+            )?,
+            _ => writeln!(f, "  MEV tips:          -")?,
+        }
+        write!(f, "  Validator income:  {}", Sol(self.validator_income()))
+    }
+}
+
+/// Breaks down the rewards of `vote_pubkey` for the completed `epoch`.
+///
+/// Staking and voting rewards come from the first block of the following epoch. Staker rewards
+/// only cover the stake accounts delegated to the validator now, and block rewards need the
+/// leader schedule of `epoch`, which RPC nodes only keep for recent epochs
+pub async fn get_epoch_rewards_breakdown(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    epoch: Epoch,
+) -> Result<EpochRewardsBreakdown, Box<dyn std::error::Error>> {
+    let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+    let current_epoch = rpc_client.get_epoch_info().await?.epoch;
+    if epoch >= current_epoch {
+        return Err(Error::Config(format!("Epoch {} has not completed yet", epoch)).into());
+    }
+
+    let vote_account = rpc_client.get_account(vote_pubkey).await?;
+    let identity = VoteState::deserialize(&vote_account.data)
+        .map_err(|err| format!("Invalid vote account {}: {}", vote_pubkey, err))?
+        .node_pubkey;
+
+    let stake_pubkeys = get_delegated_stake_accounts(rpc_client, vote_pubkey)
+        .await?
+        .into_iter()
+        .map(|(stake_pubkey, _)| stake_pubkey.to_string())
+        .collect::<HashSet<_>>();
+    let vote_pubkey_str = vote_pubkey.to_string();
+    let (mut staker_rewards, mut commission_income) = (0, 0);
+    for reward in get_first_block_rewards(
+        rpc_client,
+        epoch_schedule.get_first_slot_in_epoch(epoch + 1),
+    )
+    .await?
+    {
+        match reward.reward_type {
+            Some(RewardType::Staking) if stake_pubkeys.contains(&reward.pubkey) => {
+                staker_rewards += reward.lamports.max(0) as u64
+            }
+            Some(RewardType::Voting) if reward.pubkey == vote_pubkey_str => {
+                commission_income += reward.lamports.max(0) as u64
+            }
+            _ => {}
+        }
+    }
+
+    let first_slot_in_epoch = epoch_schedule.get_first_slot_in_epoch(epoch);
+    let leader_slots =
+        get_leader_slots_in_epoch(rpc_client, first_slot_in_epoch, &identity).await?;
+    let confirmed_blocks = rpc_client
+        .get_blocks(
+            first_slot_in_epoch,
+            Some(epoch_schedule.get_last_slot_in_epoch(epoch)),
+        )
+        .await?;
+    let identity_str = identity.to_string();
+    let (mut block_rewards, mut blocks_produced) = (0, 0);
+    for slot in leader_slots {
+        if confirmed_blocks.binary_search(&slot).is_err() {
+            continue;
+        }
+        blocks_produced += 1;
+        let block = rpc_client
+            .get_block_with_config(slot, RpcBlockConfig::rewards_only())
+            .await?;
+        block_rewards += block
+            .rewards
+            .unwrap_or_default()
+            .iter()
+            .filter(|reward| {
+                reward.reward_type == Some(RewardType::Fee) && reward.pubkey == identity_str
+            })
+            .map(|reward| reward.lamports.max(0) as u64)
+            .sum::<u64>();
+    }
+
+    let tip_distribution_account = rpc_client
+        .get_account_with_commitment(
+            &tip_distribution_address(vote_pubkey, epoch),
+            rpc_client.commitment(),
+        )
+        .await?
+        .value;
+    let (mev_tips, mev_commission_income) = match tip_distribution_account {
+        Some(account) => {
+            let rent_exempt_minimum = rpc_client
+                .get_minimum_balance_for_rent_exemption(account.data.len())
+                .await?;
+            let mev_tips = account.lamports.saturating_sub(rent_exempt_minimum);
+            let commission_bps = parse_tip_distribution_commission_bps(&account.data)
+                .ok_or_else(|| format!("Invalid tip distribution account for {}", vote_pubkey))?;
+            (
+                Some(mev_tips),
+                Some((u128::from(mev_tips) * u128::from(commission_bps) / 10_000) as u64),
+            )
+        }
+        None => (None, None),
+    };
+
+    Ok(EpochRewardsBreakdown {
+        vote_pubkey: *vote_pubkey,
+        identity,
+        epoch,
+        staker_rewards,
+        commission_income,
+        block_rewards,
+        blocks_produced,
+        mev_tips,
+        mev_commission_income,
+    })
+}
@@ -0,0 +1,562 @@
+use {
+    crate::{
+        amount::Lamports,
+        epoch_info::EpochInfoHandle,
+        error::Error,
+        mev::{parse_tip_distribution_commission_bps, tip_distribution_address, MevCommission},
+        score::{
+            self, EpochCredits, MissingCommission, ScoreCompleteness, ScoringConfig, SmoothedScore,
+            VoteAccountCredits,
+        },
+        vote_accounts::VoteAccountsSnapshot,
+        vote_progress::{VoteAccountPresence, VoteProgress},
+    },
+    futures::{stream, Stream, StreamExt},
+    log::*,
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcBlockConfig, RpcGetVoteAccountsConfig},
+        rpc_custom_error,
+    },
+    solana_sdk::{
+        clock::{Epoch, Slot},
+        commitment_config::CommitmentConfig,
+        epoch_info::EpochInfo,
+        pubkey::Pubkey,
+        reward_type::RewardType,
+    },
+    solana_transaction_status::{Reward, UiConfirmedBlock},
+    solana_vote_program::vote_state::VoteState,
+    std::{collections::BTreeMap, fmt, ops::Range},
+};
+
+/// A voting reward that could not be used as a commission entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpochCommissionWarning {
+    /// The reward's pubkey is not a valid address
+    InvalidPubkey { pubkey: String },
+    /// The vote account was rewarded more than once with different commissions. The first
+    /// commission is kept
+    ConflictingCommissions {
+        vote_pubkey: Pubkey,
+        commissions: Vec<u8>,
+    },
+}
+
+impl fmt::Display for EpochCommissionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpochCommissionWarning::InvalidPubkey { pubkey } => {
+                write!(f, "Voting reward for invalid address {:?} ignored", pubkey)
+            }
+            EpochCommissionWarning::ConflictingCommissions {
+                vote_pubkey,
+                commissions,
+            } => write!(
+                f,
+                "{} has voting rewards with conflicting commissions {:?}, using {}%",
+                vote_pubkey, commissions, commissions[0]
+            ),
+        }
+    }
+}
+
+/// The commission of each validator at the start of an epoch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpochCommissions {
+    pub commissions: BTreeMap<Pubkey, u8>,
+    /// Rewards that were left out of `commissions`
+    pub warnings: Vec<EpochCommissionWarning>,
+}
+
+/// Returns the commission of each validator at the start of `epoch`, from the voting rewards
+/// paid in the epoch's first block
+pub async fn get_epoch_commissions(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfo,
+    epoch: Epoch,
+) -> Result<EpochCommissions, Box<dyn std::error::Error>> {
+    if epoch > epoch_info.epoch {
+        return Err(Error::Config(format!("Future epoch, {}, requested", epoch)).into());
+    }
+
+    let first_slot_in_epoch = epoch_info
+        .absolute_slot
+        .saturating_sub(epoch_info.slot_index)
+        - (epoch_info.epoch - epoch) * epoch_info.slots_in_epoch;
+
+    Ok(voting_commissions(
+        get_first_block_rewards(rpc_client, first_slot_in_epoch).await?,
+    ))
+}
+
+/// Returns the commission of each validator paid a voting reward in `rewards`
+pub(crate) fn voting_commissions(rewards: Vec<Reward>) -> EpochCommissions {
+    let mut all_commissions = BTreeMap::<Pubkey, Vec<u8>>::new();
+    let mut warnings = vec![];
+    for reward in rewards {
+        if let Reward {
+            reward_type: Some(RewardType::Voting),
+            commission: Some(commission),
+            pubkey,
+            ..
+        } = reward
+        {
+            match pubkey.parse::<Pubkey>() {
+                Ok(vote_pubkey) => all_commissions
+                    .entry(vote_pubkey)
+                    .or_default()
+                    .push(commission),
+                Err(_) => warnings.push(EpochCommissionWarning::InvalidPubkey { pubkey }),
+            }
+        }
+    }
+
+    let mut commissions = BTreeMap::new();
+    for (vote_pubkey, mut vote_commissions) in all_commissions {
+        let commission = vote_commissions[0];
+        vote_commissions.sort_unstable();
+        vote_commissions.dedup();
+        if vote_commissions.len() > 1 {
+            warnings.push(EpochCommissionWarning::ConflictingCommissions {
+                vote_pubkey,
+                commissions: vote_commissions,
+            });
+        }
+        commissions.insert(vote_pubkey, commission);
+    }
+
+    EpochCommissions {
+        commissions,
+        warnings,
+    }
+}
+
+/// Returns the rewards of the first block at or after `first_slot_in_epoch`. The first block of
+/// an epoch pays out the staking and voting rewards of the previous epoch
+pub(crate) async fn get_first_block_rewards(
+    rpc_client: &RpcClient,
+    first_slot_in_epoch: Slot,
+) -> Result<Vec<Reward>, Box<dyn std::error::Error>> {
+    Ok(get_first_block(rpc_client, first_slot_in_epoch)
+        .await?
+        .rewards
+        .unwrap_or_default())
+}
+
+/// Returns the first block at or after `first_slot_in_epoch`, with rewards but no transactions
+pub(crate) async fn get_first_block(
+    rpc_client: &RpcClient,
+    first_slot_in_epoch: Slot,
+) -> Result<UiConfirmedBlock, Box<dyn std::error::Error>> {
+    let mut first_block_in_epoch = first_slot_in_epoch;
+    loop {
+        info!("fetching block in slot {}", first_block_in_epoch);
+        match rpc_client
+            .get_block_with_config(
+                first_block_in_epoch,
+                RpcBlockConfig {
+                    // The first block of a past epoch is always finalized
+                    commitment: Some(CommitmentConfig::finalized()),
+                    ..RpcBlockConfig::rewards_only()
+                },
+            )
+            .await
+        {
+            Ok(block) => return Ok(block),
+            Err(err) => {
+                if matches!(
+                        err.kind(),
+                        solana_client::client_error::ClientErrorKind::RpcError(solana_client::rpc_request::RpcError::RpcResponseError {
+                            code: rpc_custom_error::JSON_RPC_SERVER_ERROR_SLOT_SKIPPED |
+                            rpc_custom_error::JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED,
+                            ..
+                        })
+                    ) {
+                        info!("slot {} skipped",first_block_in_epoch);
+                        first_block_in_epoch += 1;
+                        continue;
+                    }
+                return Err(format!(
+                    "Failed to fetch the block for slot {}: {:?}",
+                    first_block_in_epoch, err
+                )
+                .into());
+            }
+        }
+    }
+}
+
+/// Returns the credit history of every vote account, including unstaked delinquent ones
+pub async fn get_vote_account_credits(
+    rpc_client: &RpcClient,
+) -> Result<Vec<VoteAccountCredits>, Box<dyn std::error::Error>> {
+    Ok(VoteAccountsSnapshot::fetch(rpc_client)
+        .await?
+        .vote_account_credits())
+}
+
+/// Returns the full credit history of every vote account, including unstaked delinquent ones
+pub async fn get_epoch_credits_history(
+    rpc_client: &RpcClient,
+) -> Result<BTreeMap<Pubkey, Vec<EpochCredits>>, Box<dyn std::error::Error>> {
+    Ok(get_vote_account_credits(rpc_client)
+        .await?
+        .into_iter()
+        .map(|vote_account| {
+            (
+                vote_account.vote_pubkey,
+                vote_account.epoch_credits_history(),
+            )
+        })
+        .collect())
+}
+
+/// Returns a `Vec` of ("epoch staker credits earned", "validator vote account address"), ordered
+/// by epoch staker credits earned.
+///
+/// If the epoch rolls over while the inputs are fetched they are fetched again once, after which
+/// `Error::EpochRolledOver` is returned
+pub async fn get_validators_by_credit_score(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    epoch: Epoch,
+    ignore_commission: bool,
+    missing_commission: MissingCommission,
+) -> Result<
+    Vec<(
+        /* credits: */ u64,
+        /* vote_pubkey: */ Pubkey,
+        /* activated_stake_for_current_epoch: */ Lamports,
+    )>,
+    Box<dyn std::error::Error>,
+> {
+    let mut retried = false;
+    loop {
+        let vote_accounts = VoteAccountsSnapshot::fetch(rpc_client).await?;
+        match get_validators_by_credit_score_with_vote_accounts(
+            rpc_client,
+            epoch_info,
+            &vote_accounts,
+            epoch,
+            ignore_commission,
+            missing_commission,
+        )
+        .await
+        {
+            Err(err)
+                if !retried
+                    && matches!(
+                        err.downcast_ref::<Error>(),
+                        Some(Error::EpochRolledOver { .. })
+                    ) =>
+            {
+                warn!("{}, retrying", err);
+                epoch_info.invalidate();
+                retried = true;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Like `get_validators_by_credit_score`, scoring the vote accounts of an existing snapshot.
+///
+/// Fails with `Error::EpochRolledOver`, without retrying, if `vote_accounts` is from a different
+/// epoch than `epoch_info`
+pub async fn get_validators_by_credit_score_with_vote_accounts(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    vote_accounts: &VoteAccountsSnapshot,
+    epoch: Epoch,
+    ignore_commission: bool,
+    missing_commission: MissingCommission,
+) -> Result<Vec<(u64, Pubkey, Lamports)>, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    if let Some(vote_accounts_epoch) = vote_accounts.latest_epoch() {
+        if vote_accounts_epoch != epoch_info.epoch {
+            return Err(Error::EpochRolledOver {
+                epoch_info_epoch: epoch_info.epoch,
+                vote_accounts_epoch,
+            }
+            .into());
+        }
+    }
+    let epoch_commissions = if epoch == epoch_info.epoch {
+        None
+    } else {
+        let epoch_commissions = get_epoch_commissions(rpc_client, &epoch_info, epoch).await?;
+        for warning in &epoch_commissions.warnings {
+            warn!("Epoch {}: {}", epoch, warning);
+        }
+        Some(epoch_commissions.commissions)
+    };
+
+    Ok(score::score_validators(
+        vote_accounts.vote_account_credits(),
+        epoch,
+        epoch_commissions.as_ref(),
+        ignore_commission,
+        missing_commission,
+    )?)
+}
+
+/// The scores of one epoch, as returned by `get_validators_by_credit_score`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochScores {
+    pub epoch: Epoch,
+    pub validators_by_staker_credits: Vec<(u64, Pubkey, Lamports)>,
+}
+
+/// Progress of `score_epochs`, reported each time an epoch is scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoringProgress {
+    /// The epoch just scored
+    pub epoch: Epoch,
+    /// Number of epochs scored so far, including `epoch`
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl ScoringProgress {
+    pub fn percent_done(&self) -> f64 {
+        self.completed as f64 * 100. / self.total.max(1) as f64
+    }
+}
+
+impl fmt::Display for ScoringProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Scored epoch {} ({} of {}, {:.0}%)",
+            self.epoch,
+            self.completed,
+            self.total,
+            self.percent_done()
+        )
+    }
+}
+
+/// Scores the vote accounts of `vote_accounts` in each of `epochs`, with up to `concurrency`
+/// epochs fetched at once. Epochs are yielded as soon as they are scored, so not necessarily in
+/// order, and `progress` is called after each one.
+///
+/// Each past epoch costs a fetch of its commissions. `getVoteAccounts` only reports the credits
+/// of the last few epochs, so older epochs score no validators
+#[allow(clippy::too_many_arguments)]
+pub fn score_epochs<'a>(
+    rpc_client: &'a RpcClient,
+    epoch_info: &'a EpochInfoHandle,
+    vote_accounts: &'a VoteAccountsSnapshot,
+    epochs: Range<Epoch>,
+    ignore_commission: bool,
+    missing_commission: MissingCommission,
+    concurrency: usize,
+    mut progress: impl FnMut(ScoringProgress) + 'a,
+) -> impl Stream<Item = Result<EpochScores, Box<dyn std::error::Error>>> + 'a {
+    let total = epochs.clone().count();
+    let mut completed = 0;
+    stream::iter(epochs)
+        .map(move |epoch| async move {
+            get_validators_by_credit_score_with_vote_accounts(
+                rpc_client,
+                epoch_info,
+                vote_accounts,
+                epoch,
+                ignore_commission,
+                missing_commission,
+            )
+            .await
+            .map(|validators_by_staker_credits| EpochScores {
+                epoch,
+                validators_by_staker_credits,
+            })
+        })
+        .buffer_unordered(concurrency.max(1))
+        .inspect(move |result| {
+            if let Ok(epoch_scores) = result {
+                completed += 1;
+                progress(ScoringProgress {
+                    epoch: epoch_scores.epoch,
+                    completed,
+                    total,
+                });
+            }
+        })
+}
+
+/// Returns the completeness of the score of each validator of `vote_accounts` in `epoch`, as
+/// computed by `get_validators_by_credit_score_with_vote_accounts`
+pub async fn get_score_completeness(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    vote_accounts: &VoteAccountsSnapshot,
+    epoch: Epoch,
+    ignore_commission: bool,
+) -> Result<BTreeMap<Pubkey, ScoreCompleteness>, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let epoch_commissions = if epoch == epoch_info.epoch || ignore_commission {
+        None
+    } else {
+        Some(
+            get_epoch_commissions(rpc_client, &epoch_info, epoch)
+                .await?
+                .commissions,
+        )
+    };
+    Ok(score::score_completeness(
+        vote_accounts.vote_account_credits(),
+        epoch,
+        epoch_commissions.as_ref(),
+        ignore_commission,
+    ))
+}
+
+/// Maximum number of accounts per `getMultipleAccounts` request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Returns the MEV commission of each of `vote_pubkeys` in `epoch`, from their Jito tip
+/// distribution accounts. Tip distribution accounts are closed a few epochs after they expire
+pub async fn get_mev_commissions(
+    rpc_client: &RpcClient,
+    vote_pubkeys: &[Pubkey],
+    epoch: Epoch,
+) -> Result<BTreeMap<Pubkey, MevCommission>, Box<dyn std::error::Error>> {
+    let mut mev_commissions = BTreeMap::new();
+    for vote_pubkeys in vote_pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let addresses = vote_pubkeys
+            .iter()
+            .map(|vote_pubkey| tip_distribution_address(vote_pubkey, epoch))
+            .collect::<Vec<_>>();
+        let accounts = rpc_client.get_multiple_accounts(&addresses).await?;
+
+        for (vote_pubkey, account) in vote_pubkeys.iter().zip(accounts) {
+            let mev_commission = match account {
+                Some(account) => MevCommission::Bps(
+                    parse_tip_distribution_commission_bps(&account.data).ok_or_else(|| {
+                        format!("Invalid tip distribution account for {}", vote_pubkey)
+                    })?,
+                ),
+                None => MevCommission::NotRunningJito,
+            };
+            mev_commissions.insert(*vote_pubkey, mev_commission);
+        }
+    }
+    Ok(mev_commissions)
+}
+
+/// Returns the total credits earned by `vote_pubkey`, and the slot at which they were observed
+pub async fn get_vote_credits(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+) -> Result<(Slot, u64), Box<dyn std::error::Error>> {
+    get_vote_credits_with_commitment(rpc_client, vote_pubkey, rpc_client.commitment()).await
+}
+
+/// Like `get_vote_credits`, observing the credits at `commitment` rather than the client's
+/// default. Live monitoring may prefer `confirmed` to see credits sooner
+pub async fn get_vote_credits_with_commitment(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    commitment: CommitmentConfig,
+) -> Result<(Slot, u64), Box<dyn std::error::Error>> {
+    let vote_progress =
+        get_vote_progress_with_commitment(rpc_client, vote_pubkey, commitment).await?;
+    Ok((vote_progress.slot, vote_progress.credits))
+}
+
+/// Returns the credits, last vote and root of `vote_pubkey`, observed at `commitment`
+pub async fn get_vote_progress_with_commitment(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    commitment: CommitmentConfig,
+) -> Result<VoteProgress, Box<dyn std::error::Error>> {
+    let response = rpc_client
+        .get_account_with_commitment(vote_pubkey, commitment)
+        .await?;
+    let vote_account = response
+        .value
+        .ok_or_else(|| format!("Vote account {} not found", vote_pubkey))?;
+    let vote_state = VoteState::deserialize(&vote_account.data)
+        .map_err(|err| format!("Invalid vote account {}: {}", vote_pubkey, err))?;
+    Ok(VoteProgress {
+        slot: response.context.slot,
+        credits: vote_state.credits(),
+        last_vote: vote_state.last_voted_slot(),
+        root_slot: vote_state.root_slot,
+    })
+}
+
+/// Returns whether `vote_pubkey` is among the vote accounts, observed at `commitment`
+pub async fn get_vote_account_presence(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    commitment: CommitmentConfig,
+) -> Result<VoteAccountPresence, Box<dyn std::error::Error>> {
+    let vote_accounts = rpc_client
+        .get_vote_accounts_with_config(RpcGetVoteAccountsConfig {
+            vote_pubkey: Some(vote_pubkey.to_string()),
+            commitment: Some(commitment),
+            ..RpcGetVoteAccountsConfig::default()
+        })
+        .await?;
+    if !vote_accounts.current.is_empty() || !vote_accounts.delinquent.is_empty() {
+        return Ok(VoteAccountPresence::Listed);
+    }
+    let account = rpc_client
+        .get_account_with_commitment(vote_pubkey, commitment)
+        .await?
+        .value;
+    Ok(match account {
+        Some(_) => VoteAccountPresence::Unlisted,
+        None => VoteAccountPresence::NotFound,
+    })
+}
+
+/// Returns validators ordered by their staker credits smoothed over the epochs ending with
+/// `epoch`, see `score::score_validators_smoothed`
+pub fn get_validators_by_smoothed_score(
+    vote_accounts: &VoteAccountsSnapshot,
+    epoch: Epoch,
+    config: &ScoringConfig,
+) -> Vec<SmoothedScore> {
+    score::score_validators_smoothed(vote_accounts.vote_account_credits(), epoch, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::reward_type::RewardType};
+
+    fn voting_reward(vote_pubkey: &Pubkey, commission: u8) -> Reward {
+        Reward {
+            pubkey: vote_pubkey.to_string(),
+            lamports: 1,
+            post_balance: 1,
+            reward_type: Some(RewardType::Voting),
+            commission: Some(commission),
+        }
+    }
+
+    #[test]
+    fn test_voting_commissions() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let epoch_commissions = voting_commissions(vec![
+            voting_reward(&a, 5),
+            voting_reward(&b, 10),
+            voting_reward(&b, 100),
+            voting_reward(&a, 5),
+            voting_reward(&b, 10),
+        ]);
+        assert_eq!(
+            epoch_commissions.commissions,
+            BTreeMap::from([(a, 5), (b, 10)])
+        );
+        assert_eq!(
+            epoch_commissions.warnings,
+            vec![EpochCommissionWarning::ConflictingCommissions {
+                vote_pubkey: b,
+                commissions: vec![10, 100],
+            }]
+        );
+    }
+}
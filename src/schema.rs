@@ -0,0 +1,38 @@
+//! Versioning of the JSON payloads this crate emits: score feeds, published score summaries and
+//! reports.
+//!
+//! Every payload carries a `schemaVersion`. Within a schema version fields are only ever added,
+//! never removed, renamed or changed in meaning, so consumers must ignore fields they don't know.
+//! Any other change increments `SCHEMA_VERSION`. Payloads without a `schemaVersion` predate
+//! versioning and are schema version 1
+use serde_json::Value;
+
+/// Schema version of the payloads emitted by this build
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Adds `schemaVersion` to the JSON object `payload`
+pub fn with_schema_version(mut payload: Value) -> Value {
+    if let Some(object) = payload.as_object_mut() {
+        object.insert("schemaVersion".to_string(), SCHEMA_VERSION.into());
+    }
+    payload
+}
+
+/// Returns the schema version of `payload`
+pub fn schema_version(payload: &Value) -> u64 {
+    payload
+        .get("schemaVersion")
+        .and_then(|schema_version| schema_version.as_u64())
+        .unwrap_or(1)
+}
+
+/// Fails if `payload` uses a schema version this build can't parse
+pub fn check_schema_version(payload: &Value) -> Result<(), String> {
+    match schema_version(payload) {
+        schema_version if schema_version <= SCHEMA_VERSION => Ok(()),
+        schema_version => Err(format!(
+            "Unsupported schema version {}, this build supports up to {}",
+            schema_version, SCHEMA_VERSION
+        )),
+    }
+}
@@ -0,0 +1,650 @@
+//! Pure credit scoring, independent of any RPC transport.
+//!
+//! Everything in this module operates on already fetched data so it can be used from environments
+//! without network access, such as a browser dashboard running the `wasm` build.
+use {
+    crate::{amount::Lamports, error::Error},
+    log::*,
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    std::{cmp::Reverse, collections::BTreeMap, fmt},
+};
+
+/// The subset of a vote account's state that is required to compute its credit score
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VoteAccountCredits {
+    pub vote_pubkey: Pubkey,
+    pub commission: u8,
+    pub activated_stake: u64,
+    /// (epoch, credits, prev_credits), as reported by `getVoteAccounts`
+    pub epoch_credits: Vec<(Epoch, u64, u64)>,
+}
+
+/// One entry of a vote account's `epoch_credits`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EpochCredits {
+    pub epoch: Epoch,
+    /// Total credits at the end of `epoch`
+    pub credits: u64,
+    /// Total credits at the start of `epoch`
+    pub prev_credits: u64,
+}
+
+impl EpochCredits {
+    /// Credits earned in `epoch`
+    pub fn earned(&self) -> u64 {
+        self.credits.saturating_sub(self.prev_credits)
+    }
+}
+
+impl From<&(Epoch, u64, u64)> for EpochCredits {
+    fn from((epoch, credits, prev_credits): &(Epoch, u64, u64)) -> Self {
+        Self {
+            epoch: *epoch,
+            credits: *credits,
+            prev_credits: *prev_credits,
+        }
+    }
+}
+
+impl VoteAccountCredits {
+    /// Returns every epoch the vote account keeps credits for, oldest first. Vote accounts keep
+    /// the most recent 64 epochs
+    pub fn epoch_credits_history(&self) -> Vec<EpochCredits> {
+        self.epoch_credits.iter().map(EpochCredits::from).collect()
+    }
+
+    /// Oldest epoch the vote account keeps credits for, `None` if it never earned any. With a
+    /// full 64 epoch history the vote account may have earned credits before
+    pub fn first_epoch_with_credits(&self) -> Option<Epoch> {
+        self.epoch_credits.first().map(|(epoch, ..)| *epoch)
+    }
+
+    /// Number of epochs from `first_epoch_with_credits` to `epoch`, both included, 0 if the vote
+    /// account has no credits up to `epoch`
+    pub fn voting_epochs(&self, epoch: Epoch) -> u64 {
+        self.first_epoch_with_credits()
+            .filter(|first_epoch| *first_epoch <= epoch)
+            .map(|first_epoch| epoch - first_epoch + 1)
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the credits earned in `epoch` from an `epoch_credits` vector, if the epoch is present
+pub fn epoch_credits_earned(epoch_credits: &[(Epoch, u64, u64)], epoch: Epoch) -> Option<u64> {
+    epoch_credits
+        .iter()
+        .find(|ec| ec.0 == epoch)
+        .map(|(_, credits, prev_credits)| credits.saturating_sub(*prev_credits))
+}
+
+/// Returns the portion of `credits` that remain for stakers after `commission` is deducted
+pub fn staker_credits(credits: u64, commission: u8) -> u64 {
+    (u128::from(credits) * u128::from(100 - commission.min(100)) / 100) as u64
+}
+
+/// What `score_validators` does with a validator that earned credits but has no entry in
+/// `epoch_commissions`, as happens for vote accounts created during the epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingCommission {
+    /// Leave the validator out of the scores
+    Skip,
+    /// Use the commission currently configured in the vote account, logging a warning
+    #[default]
+    UseCurrent,
+    /// Fail with `Error::MissingCommission`
+    Fail,
+}
+
+/// Returns a `Vec` of ("epoch staker credits earned", "validator vote account address",
+/// "activated stake"), ordered by epoch staker credits earned.
+///
+/// `epoch_commissions` holds the commission of each validator at the start of `epoch`. When it's
+/// `None` the commission currently configured in the vote account is used instead. Validators
+/// missing from `epoch_commissions` are handled as `missing_commission` says.
+pub fn score_validators<I>(
+    vote_accounts: I,
+    epoch: Epoch,
+    epoch_commissions: Option<&BTreeMap<Pubkey, u8>>,
+    ignore_commission: bool,
+    missing_commission: MissingCommission,
+) -> Result<
+    Vec<(
+        /* credits: */ u64,
+        /* vote_pubkey: */ Pubkey,
+        /* activated_stake_for_current_epoch: */ Lamports,
+    )>,
+    Error,
+>
+where
+    I: IntoIterator<Item = VoteAccountCredits>,
+{
+    let mut list = vec![];
+    for vac in vote_accounts {
+        let staker_credits = match epoch_credits_earned(&vac.epoch_credits, epoch) {
+            Some(epoch_credits) => {
+                let epoch_commission = match epoch_commissions {
+                    _ if ignore_commission => 0,
+                    None => vac.commission,
+                    Some(epoch_commissions) => {
+                        match (epoch_commissions.get(&vac.vote_pubkey), missing_commission) {
+                            (Some(epoch_commission), _) => *epoch_commission,
+                            (None, MissingCommission::Skip) => continue,
+                            (None, MissingCommission::UseCurrent) => {
+                                warn!(
+                                    "{}: no commission for epoch {}, using the current commission",
+                                    vac.vote_pubkey, epoch
+                                );
+                                vac.commission
+                            }
+                            (None, MissingCommission::Fail) => {
+                                return Err(Error::MissingCommission {
+                                    vote_pubkey: vac.vote_pubkey,
+                                    epoch,
+                                })
+                            }
+                        }
+                    }
+                };
+
+                let staker_credits = staker_credits(epoch_credits, epoch_commission);
+                debug!(
+                    "{}: total credits {}, staker credits {} in epoch {}",
+                    vac.vote_pubkey, epoch_credits, staker_credits, epoch,
+                );
+                staker_credits
+            }
+            None => 0,
+        };
+
+        list.push((
+            staker_credits,
+            vac.vote_pubkey,
+            Lamports(vac.activated_stake),
+        ));
+    }
+
+    list.sort_by_key(|(staker_credits, _, _)| Reverse(*staker_credits));
+    Ok(list)
+}
+
+/// Which inputs of a validator's score were available. A score with a missing input treats it
+/// as zero or as a fallback value, so it's less trustworthy than a complete one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreCompleteness {
+    /// The commission at the start of the epoch was known, rather than the current one used
+    pub commission_snapshot: bool,
+    /// The vote account has an `epoch_credits` entry for the epoch
+    pub epoch_credits: bool,
+    /// The block production data covers the full slot range, `None` if the score doesn't use
+    /// block production
+    pub block_production: Option<bool>,
+}
+
+impl ScoreCompleteness {
+    pub fn is_complete(&self) -> bool {
+        self.commission_snapshot && self.epoch_credits && self.block_production != Some(false)
+    }
+}
+
+impl fmt::Display for ScoreCompleteness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut missing = vec![];
+        if !self.commission_snapshot {
+            missing.push("commission snapshot");
+        }
+        if !self.epoch_credits {
+            missing.push("epoch credits");
+        }
+        if self.block_production == Some(false) {
+            missing.push("full block production");
+        }
+        if missing.is_empty() {
+            write!(f, "complete")
+        } else {
+            write!(f, "incomplete, no {}", missing.join(" or "))
+        }
+    }
+}
+
+/// Returns the completeness of the score of each of `vote_accounts` in `epoch`, for the same
+/// `epoch_commissions` as passed to `score_validators`
+pub fn score_completeness<I>(
+    vote_accounts: I,
+    epoch: Epoch,
+    epoch_commissions: Option<&BTreeMap<Pubkey, u8>>,
+    ignore_commission: bool,
+) -> BTreeMap<Pubkey, ScoreCompleteness>
+where
+    I: IntoIterator<Item = VoteAccountCredits>,
+{
+    vote_accounts
+        .into_iter()
+        .map(|vac| {
+            let commission_snapshot = ignore_commission
+                || epoch_commissions.is_none_or(|epoch_commissions| {
+                    epoch_commissions.contains_key(&vac.vote_pubkey)
+                });
+            (
+                vac.vote_pubkey,
+                ScoreCompleteness {
+                    commission_snapshot,
+                    epoch_credits: epoch_credits_earned(&vac.epoch_credits, epoch).is_some(),
+                    block_production: None,
+                },
+            )
+        })
+        .collect()
+}
+
+/// A page of the output of `score_validators`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScorePage<'a> {
+    /// Rank, from 0, of the first entry
+    pub offset: usize,
+    /// Number of validators in the full list
+    pub total: usize,
+    pub entries: &'a [(u64, Pubkey, Lamports)],
+}
+
+impl ScorePage<'_> {
+    /// Offset of the following page, `None` on the last page
+    pub fn next_offset(&self) -> Option<usize> {
+        let next_offset = self.offset + self.entries.len();
+        (!self.entries.is_empty() && next_offset < self.total).then_some(next_offset)
+    }
+}
+
+/// Returns at most `limit` entries of `validators_by_staker_credits` starting at `offset`, so a
+/// web service can serve a large list one page per request
+pub fn score_page(
+    validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+    offset: usize,
+    limit: usize,
+) -> ScorePage<'_> {
+    let total = validators_by_staker_credits.len();
+    let start = offset.min(total);
+    let end = start.saturating_add(limit).min(total);
+    ScorePage {
+        offset,
+        total,
+        entries: &validators_by_staker_credits[start..end],
+    }
+}
+
+/// Iterates over `validators_by_staker_credits` in pages of `limit` entries, for streaming
+/// responses
+pub fn score_pages(
+    validators_by_staker_credits: &[(u64, Pubkey, Lamports)],
+    limit: usize,
+) -> impl Iterator<Item = ScorePage<'_>> {
+    let total = validators_by_staker_credits.len();
+    validators_by_staker_credits
+        .chunks(limit.max(1))
+        .enumerate()
+        .map(move |(i, entries)| ScorePage {
+            offset: i * limit.max(1),
+            total,
+            entries,
+        })
+}
+
+/// Returns the staker credits earned in the `num_epochs` epochs ending with `epoch`, newest first.
+/// Epochs in which no credits were earned are included as zero. The commission currently
+/// configured in the vote account is used for every epoch
+pub fn staker_credits_history(
+    vote_account: &VoteAccountCredits,
+    epoch: Epoch,
+    num_epochs: u64,
+    ignore_commission: bool,
+) -> Vec<u64> {
+    let commission = if ignore_commission {
+        0
+    } else {
+        vote_account.commission
+    };
+    (0..num_epochs.min(epoch + 1))
+        .map(|age| {
+            staker_credits(
+                epoch_credits_earned(&vote_account.epoch_credits, epoch - age).unwrap_or_default(),
+                commission,
+            )
+        })
+        .collect()
+}
+
+/// Most credits a vote can earn before Timely Vote Credits
+pub const MAX_CREDITS_PER_VOTE_BEFORE_TVC: u64 = 1;
+/// Most credits a vote can earn with Timely Vote Credits, for landing within two slots
+pub const MAX_CREDITS_PER_VOTE_TVC: u64 = 16;
+
+/// Scales `credits` earned in `epoch` to the Timely Vote Credits scale. Credits earned before
+/// `tvc_activation_epoch`, the first epoch with Timely Vote Credits, are multiplied by the
+/// change of the most credits per vote. Without an activation epoch `credits` are returned as is
+pub fn normalize_credits(credits: u64, epoch: Epoch, tvc_activation_epoch: Option<Epoch>) -> u64 {
+    match tvc_activation_epoch {
+        Some(tvc_activation_epoch) if epoch < tvc_activation_epoch => {
+            credits.saturating_mul(MAX_CREDITS_PER_VOTE_TVC / MAX_CREDITS_PER_VOTE_BEFORE_TVC)
+        }
+        _ => credits,
+    }
+}
+
+/// Normalizes a history of credits ending with `epoch`, newest first, with `normalize_credits`,
+/// so epochs on both sides of the Timely Vote Credits activation can be averaged
+pub fn normalize_credits_history(
+    history: &[u64],
+    epoch: Epoch,
+    tvc_activation_epoch: Option<Epoch>,
+) -> Vec<u64> {
+    history
+        .iter()
+        .enumerate()
+        .map(|(age, credits)| normalize_credits(*credits, epoch - age as u64, tvc_activation_epoch))
+        .collect()
+}
+
+/// How the epochs of a multi-epoch score are weighted relative to each other
+#[derive(Debug, Clone, PartialEq)]
+pub enum EpochWeights {
+    /// The weight of an epoch halves every `half_life_epochs` epochs
+    Exponential { half_life_epochs: f64 },
+    /// The weight decreases linearly from `n` for the newest epoch to 1 for the oldest of `n`
+    Linear,
+    /// Explicit weights, newest epoch first. The number of weights is the number of epochs used
+    Explicit(Vec<f64>),
+}
+
+impl EpochWeights {
+    /// Returns the weights of `num_epochs` epochs, newest first
+    pub fn weights(&self, num_epochs: usize) -> Vec<f64> {
+        match self {
+            EpochWeights::Exponential { half_life_epochs } => {
+                let decay = 0.5f64.powf(1. / half_life_epochs.max(f64::EPSILON));
+                (0..num_epochs).map(|age| decay.powi(age as i32)).collect()
+            }
+            EpochWeights::Linear => (0..num_epochs)
+                .map(|age| (num_epochs - age) as f64)
+                .collect(),
+            EpochWeights::Explicit(weights) => (0..num_epochs)
+                .map(|age| weights.get(age).copied().unwrap_or_default())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for EpochWeights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpochWeights::Exponential { half_life_epochs } => {
+                write!(f, "with a {} epoch half-life", half_life_epochs)
+            }
+            EpochWeights::Linear => write!(f, "linearly"),
+            EpochWeights::Explicit(weights) => write!(
+                f,
+                "by {}",
+                weights
+                    .iter()
+                    .map(|weight| weight.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Parameters of a multi-epoch score
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringConfig {
+    /// Number of epochs, ending with the scored epoch, included in the score. Ignored for
+    /// `EpochWeights::Explicit`, which uses one epoch per weight
+    pub num_epochs: u64,
+    pub epoch_weights: EpochWeights,
+    pub ignore_commission: bool,
+    /// First epoch with Timely Vote Credits. When the scored epochs span it, the credits of the
+    /// earlier epochs are normalized before averaging
+    pub tvc_activation_epoch: Option<Epoch>,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            num_epochs: 10,
+            epoch_weights: EpochWeights::Exponential {
+                half_life_epochs: 3.,
+            },
+            ignore_commission: false,
+            tvc_activation_epoch: None,
+        }
+    }
+}
+
+impl ScoringConfig {
+    pub fn num_epochs(&self) -> u64 {
+        match &self.epoch_weights {
+            EpochWeights::Explicit(weights) => weights.len() as u64,
+            _ => self.num_epochs,
+        }
+    }
+}
+
+/// Returns the average of `history` weighted by `weights`, both newest first
+pub fn weighted_average(history: &[u64], weights: &[f64]) -> f64 {
+    let (weighted_sum, total_weight) = history.iter().zip(weights).fold(
+        (0., 0.),
+        |(weighted_sum, total_weight), (credits, weight)| {
+            (
+                weighted_sum + weight * *credits as f64,
+                total_weight + weight,
+            )
+        },
+    );
+    if total_weight > 0. {
+        weighted_sum / total_weight
+    } else {
+        0.
+    }
+}
+
+/// Returns the exponentially weighted average of `history`, newest first, where the weight of
+/// an epoch halves every `half_life_epochs` epochs
+pub fn ewma(history: &[u64], half_life_epochs: f64) -> f64 {
+    weighted_average(
+        history,
+        &EpochWeights::Exponential { half_life_epochs }.weights(history.len()),
+    )
+}
+
+/// A validator's score in a single epoch together with its score smoothed over several epochs
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmoothedScore {
+    pub vote_pubkey: Pubkey,
+    /// Staker credits earned in the scored epoch
+    pub staker_credits: u64,
+    /// Weighted average of the normalized staker credits over the scored and prior epochs
+    pub smoothed_staker_credits: f64,
+    /// Staker credits of the scored and prior epochs, newest first
+    pub staker_credits_history: Vec<u64>,
+    /// `staker_credits_history` normalized with `normalize_credits`
+    pub normalized_staker_credits_history: Vec<u64>,
+    pub activated_stake: u64,
+}
+
+/// Scores validators by the weighted average of their staker credits over the epochs ending with
+/// `epoch`, as configured by `config`, ordered by smoothed score.
+///
+/// A single poor epoch, such as one with a planned migration, moves the smoothed score much less
+/// than the raw score. See `staker_credits_history` for how commission is applied, and
+/// `normalize_credits` for how epochs before Timely Vote Credits are made comparable
+pub fn score_validators_smoothed<I>(
+    vote_accounts: I,
+    epoch: Epoch,
+    config: &ScoringConfig,
+) -> Vec<SmoothedScore>
+where
+    I: IntoIterator<Item = VoteAccountCredits>,
+{
+    let num_epochs = config.num_epochs();
+    let weights = config
+        .epoch_weights
+        .weights(num_epochs.min(epoch + 1) as usize);
+
+    let mut list = vote_accounts
+        .into_iter()
+        .map(|vac| {
+            let history = staker_credits_history(&vac, epoch, num_epochs, config.ignore_commission);
+            let normalized_history =
+                normalize_credits_history(&history, epoch, config.tvc_activation_epoch);
+            SmoothedScore {
+                vote_pubkey: vac.vote_pubkey,
+                staker_credits: history.first().copied().unwrap_or_default(),
+                smoothed_staker_credits: weighted_average(&normalized_history, &weights),
+                activated_stake: vac.activated_stake,
+                staker_credits_history: history,
+                normalized_staker_credits_history: normalized_history,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    list.sort_by(|a, b| {
+        b.smoothed_staker_credits
+            .partial_cmp(&a.smoothed_staker_credits)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote_account(commission: u8, epoch_credits: Vec<(Epoch, u64, u64)>) -> VoteAccountCredits {
+        VoteAccountCredits {
+            vote_pubkey: Pubkey::new_unique(),
+            commission,
+            activated_stake: 42,
+            epoch_credits,
+        }
+    }
+
+    #[test]
+    fn test_score_validators() {
+        let high_commission = vote_account(50, vec![(9, 1_000, 0), (10, 2_000, 1_000)]);
+        let low_commission = vote_account(10, vec![(10, 1_900, 1_000)]);
+        let not_voting = vote_account(0, vec![(9, 1_000, 0)]);
+
+        let scores = score_validators(
+            [
+                high_commission.clone(),
+                low_commission.clone(),
+                not_voting.clone(),
+            ],
+            10,
+            None,
+            false,
+            MissingCommission::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            scores,
+            vec![
+                (810, low_commission.vote_pubkey, Lamports(42)),
+                (500, high_commission.vote_pubkey, Lamports(42)),
+                (0, not_voting.vote_pubkey, Lamports(42)),
+            ]
+        );
+
+        let scores = score_validators(
+            [high_commission.clone(), low_commission.clone()],
+            10,
+            None,
+            true,
+            MissingCommission::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            scores,
+            vec![
+                (1_000, high_commission.vote_pubkey, Lamports(42)),
+                (900, low_commission.vote_pubkey, Lamports(42)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_score_validators_epoch_commissions() {
+        let known = vote_account(100, vec![(10, 1_000, 0)]);
+        let missing = vote_account(0, vec![(10, 1_000, 0)]);
+        let epoch_commissions = BTreeMap::from([(known.vote_pubkey, 20)]);
+        let score = |missing_commission| {
+            score_validators(
+                [known.clone(), missing.clone()],
+                10,
+                Some(&epoch_commissions),
+                false,
+                missing_commission,
+            )
+        };
+
+        assert_eq!(
+            score(MissingCommission::Skip).unwrap(),
+            vec![(800, known.vote_pubkey, Lamports(42))]
+        );
+        assert_eq!(
+            score(MissingCommission::UseCurrent).unwrap(),
+            vec![
+                (1_000, missing.vote_pubkey, Lamports(42)),
+                (800, known.vote_pubkey, Lamports(42)),
+            ]
+        );
+        assert!(matches!(
+            score(MissingCommission::Fail),
+            Err(Error::MissingCommission { vote_pubkey, epoch: 10 })
+                if vote_pubkey == missing.vote_pubkey
+        ));
+    }
+
+    #[test]
+    fn test_normalize_credits() {
+        assert_eq!(normalize_credits(100, 9, None), 100);
+        assert_eq!(normalize_credits(100, 9, Some(10)), 1_600);
+        assert_eq!(normalize_credits(100, 10, Some(10)), 100);
+        assert_eq!(normalize_credits(u64::MAX, 9, Some(10)), u64::MAX);
+        assert_eq!(
+            normalize_credits_history(&[1_600, 100, 100], 11, Some(11)),
+            vec![1_600, 1_600, 1_600]
+        );
+    }
+
+    #[test]
+    fn test_epoch_weights() {
+        assert_eq!(
+            EpochWeights::Exponential {
+                half_life_epochs: 1.
+            }
+            .weights(3),
+            vec![1., 0.5, 0.25]
+        );
+        assert_eq!(EpochWeights::Linear.weights(3), vec![3., 2., 1.]);
+        assert_eq!(
+            EpochWeights::Explicit(vec![2., 1.]).weights(3),
+            vec![2., 1., 0.]
+        );
+        assert_eq!(
+            ScoringConfig {
+                epoch_weights: EpochWeights::Explicit(vec![2., 1.]),
+                ..ScoringConfig::default()
+            }
+            .num_epochs(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_ewma() {
+        assert_eq!(ewma(&[], 3.), 0.);
+        assert_eq!(ewma(&[500, 500, 500], 3.), 500.);
+        // Weights 1, 0.5
+        assert_eq!(ewma(&[300, 0], 1.), 200.);
+        assert_eq!(weighted_average(&[100, 400], &[3., 1.]), 175.);
+    }
+}
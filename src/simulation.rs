@@ -0,0 +1,105 @@
+//! Synthetic clusters for exercising reports and alerting without a live RPC node
+use {
+    crate::score::VoteAccountCredits,
+    solana_sdk::{clock::Epoch, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationConfig {
+    pub validators: usize,
+    /// Epoch the synthetic cluster is in. Credit history is generated up to and including it
+    pub epoch: Epoch,
+    /// Number of epochs of credit history per vote account
+    pub history_epochs: u64,
+    /// Credits a validator that votes on every slot earns in an epoch
+    pub max_credits_per_epoch: u64,
+    /// Probability, from 0.0 to 1.0, that a validator is delinquent for a whole epoch
+    pub delinquency_rate: f64,
+    /// Seed of the generator. The same seed always produces the same cluster
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            validators: 1_500,
+            epoch: 500,
+            history_epochs: 10,
+            max_credits_per_epoch: 432_000,
+            delinquency_rate: 0.02,
+            seed: 0,
+        }
+    }
+}
+
+/// SplitMix64, which is plenty for synthetic data and keeps the output reproducible everywhere
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Generates a cluster of vote accounts with a realistic spread of stake, commission and voting
+/// performance, in the same shape as `get_vote_account_credits` returns
+pub fn synthetic_vote_accounts(config: &SimulationConfig) -> Vec<VoteAccountCredits> {
+    const COMMISSIONS: [u8; 7] = [0, 0, 5, 7, 8, 10, 100];
+
+    let mut rng = SplitMix64(config.seed);
+    let first_epoch = config
+        .epoch
+        .saturating_sub(config.history_epochs.saturating_sub(1));
+
+    (0..config.validators)
+        .map(|_| {
+            let mut vote_pubkey = [0u8; 32];
+            for chunk in vote_pubkey.chunks_mut(8) {
+                chunk.copy_from_slice(&rng.next_u64().to_le_bytes());
+            }
+
+            // Stake is roughly log-uniform between 1k and 10M SOL
+            let activated_stake =
+                (10f64.powf(3. + 4. * rng.next_f64()) * LAMPORTS_PER_SOL as f64) as u64;
+            let commission = COMMISSIONS[(rng.next_u64() % COMMISSIONS.len() as u64) as usize];
+            // Most validators vote on 90-99% of slots, one in ten is struggling
+            let performance = if rng.next_f64() < 0.1 {
+                0.5 + 0.4 * rng.next_f64()
+            } else {
+                0.9 + 0.09 * rng.next_f64()
+            };
+
+            let mut total_credits = 0;
+            // Like real vote accounts, epochs without credits have no entry
+            let epoch_credits = (first_epoch..=config.epoch)
+                .filter_map(|epoch| {
+                    if rng.next_f64() < config.delinquency_rate {
+                        return None;
+                    }
+                    let prev_credits = total_credits;
+                    let jitter = 0.98 + 0.02 * rng.next_f64();
+                    total_credits += (config.max_credits_per_epoch as f64
+                        * (performance * jitter).min(1.))
+                        as u64;
+                    Some((epoch, total_credits, prev_credits))
+                })
+                .collect();
+
+            VoteAccountCredits {
+                vote_pubkey: Pubkey::new_from_array(vote_pubkey),
+                commission,
+                activated_stake,
+                epoch_credits,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,344 @@
+//! Correlation of a validator's skipped leader slots with skips by neighboring leaders
+use {
+    crate::{
+        block_production_cache::{format_skip_rate, skip_rate, BlockProductionCache},
+        epoch_info::EpochInfoHandle,
+        leader_schedule::get_leader_slots,
+    },
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        clock::{Epoch, Slot, NUM_CONSECUTIVE_LEADER_SLOTS},
+        pubkey::Pubkey,
+    },
+    std::fmt,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipCause {
+    /// Neighboring leaders produced their blocks, the problem is likely local to the validator
+    Local,
+    /// Neighboring leaders skipped too, the cluster was turbulent
+    ClusterWide,
+}
+
+/// A skipped leader slot, annotated with how the neighboring leaders fared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkipEvent {
+    pub slot: Slot,
+    /// Slots of other leaders within the correlation window
+    pub neighbor_slots: usize,
+    /// `neighbor_slots` that were skipped
+    pub neighbor_skips: usize,
+    pub cause: SkipCause,
+}
+
+impl fmt::Display for SkipEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Skipped slot {}: {} of {} neighboring slots also skipped, {}",
+            self.slot,
+            self.neighbor_skips,
+            self.neighbor_slots,
+            match self.cause {
+                SkipCause::Local => "likely a local problem",
+                SkipCause::ClusterWide => "cluster-wide turbulence",
+            }
+        )
+    }
+}
+
+/// Returns a `SkipEvent` for every slot of the sorted `leader_slots` missing from the sorted
+/// `confirmed_blocks`.
+///
+/// Slots of other leaders within `window` slots either side are the neighbors. A skip is
+/// `SkipCause::ClusterWide` when at least `cluster_wide_percent` of its neighbors also skipped.
+/// Only leader slots whose window ends at or before `last_confirmed_slot` are considered
+pub fn correlate_skips(
+    leader_slots: &[Slot],
+    confirmed_blocks: &[Slot],
+    last_confirmed_slot: Slot,
+    window: u64,
+    cluster_wide_percent: f64,
+) -> Vec<SkipEvent> {
+    let is_confirmed = |slot: &Slot| confirmed_blocks.binary_search(slot).is_ok();
+
+    leader_slots
+        .iter()
+        .filter(|slot| **slot + window <= last_confirmed_slot && !is_confirmed(slot))
+        .map(|slot| {
+            let neighbors = (slot.saturating_sub(window)..=slot + window)
+                .filter(|neighbor| leader_slots.binary_search(neighbor).is_err())
+                .collect::<Vec<_>>();
+            let neighbor_skips = neighbors
+                .iter()
+                .filter(|neighbor| !is_confirmed(neighbor))
+                .count();
+            let cause = if !neighbors.is_empty()
+                && neighbor_skips as f64 * 100. / neighbors.len() as f64 >= cluster_wide_percent
+            {
+                SkipCause::ClusterWide
+            } else {
+                SkipCause::Local
+            };
+            SkipEvent {
+                slot: *slot,
+                neighbor_slots: neighbors.len(),
+                neighbor_skips,
+                cause,
+            }
+        })
+        .collect()
+}
+
+/// Returns the skipped leader slots of `identity` so far in the current epoch, see
+/// `correlate_skips`
+pub async fn get_skip_events(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    identity: &Pubkey,
+    window: u64,
+    cluster_wide_percent: f64,
+) -> Result<Vec<SkipEvent>, Box<dyn std::error::Error>> {
+    let leader_slots = get_leader_slots(rpc_client, epoch_info, identity).await?;
+    let last_confirmed_slot = rpc_client.get_slot().await?;
+
+    let (first_slot, last_slot) = match (leader_slots.first(), leader_slots.last()) {
+        (Some(first_slot), Some(last_slot)) if *first_slot <= last_confirmed_slot => (
+            first_slot.saturating_sub(window),
+            (last_slot + window).min(last_confirmed_slot),
+        ),
+        _ => return Ok(vec![]),
+    };
+    let confirmed_blocks = rpc_client.get_blocks(first_slot, Some(last_slot)).await?;
+
+    Ok(correlate_skips(
+        &leader_slots,
+        &confirmed_blocks,
+        last_confirmed_slot,
+        window,
+        cluster_wide_percent,
+    ))
+}
+
+/// A validator's block production in one epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochSkipRate {
+    pub epoch: Epoch,
+    pub leader_slots: usize,
+    pub blocks_produced: usize,
+    /// False for the current epoch, which is only covered up to the current slot
+    pub complete: bool,
+}
+
+impl EpochSkipRate {
+    /// Percentage of leader slots skipped, `None` without leader slots
+    pub fn skip_rate(&self) -> Option<f64> {
+        skip_rate(self.leader_slots, self.blocks_produced)
+    }
+}
+
+impl fmt::Display for EpochSkipRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Epoch {}{}: {} of {} leader slots produced, skip rate {}",
+            self.epoch,
+            if self.complete { "" } else { " (so far)" },
+            self.blocks_produced,
+            self.leader_slots,
+            format_skip_rate(self.skip_rate())
+        )
+    }
+}
+
+/// Returns the block production of `identity` in the `last_n_epochs` epochs ending with the
+/// current epoch, oldest first.
+///
+/// Results are fetched through `block_production_cache`, so completed epochs are only fetched
+/// once when the cache is persisted. RPC nodes only serve block production for the slots they
+/// still have in their ledger, older epochs fail
+pub async fn skip_rate_history(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    block_production_cache: &mut BlockProductionCache,
+    identity: &Pubkey,
+    last_n_epochs: u64,
+) -> Result<Vec<EpochSkipRate>, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+
+    let first_epoch = epoch_info
+        .epoch
+        .saturating_sub(last_n_epochs.saturating_sub(1));
+    let mut history = vec![];
+    for epoch in first_epoch..=epoch_info.epoch {
+        let complete = epoch < epoch_info.epoch;
+        let last_slot = if complete {
+            epoch_schedule.get_last_slot_in_epoch(epoch)
+        } else {
+            epoch_info.absolute_slot
+        };
+        let block_production = block_production_cache
+            .get_block_production(
+                rpc_client,
+                identity,
+                epoch_schedule.get_first_slot_in_epoch(epoch),
+                last_slot,
+            )
+            .await?;
+        history.push(EpochSkipRate {
+            epoch,
+            leader_slots: block_production.leader_slots,
+            blocks_produced: block_production.blocks_produced,
+            complete,
+        });
+    }
+    Ok(history)
+}
+
+/// Leader slots and the blocks produced in them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlotProduction {
+    pub leader_slots: usize,
+    pub blocks_produced: usize,
+}
+
+impl SlotProduction {
+    /// Percentage of leader slots skipped, `None` without leader slots
+    pub fn skip_rate(&self) -> Option<f64> {
+        skip_rate(self.leader_slots, self.blocks_produced)
+    }
+}
+
+impl fmt::Display for SlotProduction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} produced, skip rate {}",
+            self.blocks_produced,
+            self.leader_slots,
+            format_skip_rate(self.skip_rate())
+        )
+    }
+}
+
+/// How a validator's leader slots are laid out in an epoch, and how production varies with it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderSlotDistribution {
+    /// Groups of `NUM_CONSECUTIVE_LEADER_SLOTS` leader slots
+    pub groups: usize,
+    /// Groups immediately following another group of the same validator
+    pub back_to_back_groups: usize,
+    /// Production by position of the slot within its group. Skips concentrated in the first
+    /// position point at a slow handover from the previous leader
+    pub by_position: [SlotProduction; NUM_CONSECUTIVE_LEADER_SLOTS as usize],
+    /// Production in equal segments of the epoch, in order
+    pub by_segment: Vec<SlotProduction>,
+}
+
+impl fmt::Display for LeaderSlotDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} leader groups, {} back-to-back",
+            self.groups, self.back_to_back_groups
+        )?;
+        for (position, slot_production) in self.by_position.iter().enumerate() {
+            writeln!(f, "Slot {} of group: {}", position + 1, slot_production)?;
+        }
+        for (segment, slot_production) in self.by_segment.iter().enumerate() {
+            writeln!(
+                f,
+                "Epoch segment {}/{}: {}",
+                segment + 1,
+                self.by_segment.len(),
+                slot_production
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Analyzes the sorted `leader_slots` of a validator in the epoch starting at
+/// `first_slot_in_epoch`, against the sorted `confirmed_blocks`.
+///
+/// Only leader slots up to `last_confirmed_slot` count towards production, while `groups` and
+/// `back_to_back_groups` cover the whole schedule. The epoch is divided into `segments` segments
+pub fn leader_slot_distribution(
+    leader_slots: &[Slot],
+    confirmed_blocks: &[Slot],
+    first_slot_in_epoch: Slot,
+    slots_in_epoch: u64,
+    last_confirmed_slot: Slot,
+    segments: usize,
+) -> LeaderSlotDistribution {
+    let segments = segments.max(1);
+    let slots_per_segment = slots_in_epoch.div_ceil(segments as u64).max(1);
+
+    let mut distribution = LeaderSlotDistribution {
+        groups: 0,
+        back_to_back_groups: 0,
+        by_position: [SlotProduction::default(); NUM_CONSECUTIVE_LEADER_SLOTS as usize],
+        by_segment: vec![SlotProduction::default(); segments],
+    };
+
+    let mut last_group = None;
+    for slot in leader_slots {
+        let slot_index = slot.saturating_sub(first_slot_in_epoch);
+        let group = slot_index / NUM_CONSECUTIVE_LEADER_SLOTS;
+        if last_group != Some(group) {
+            distribution.groups += 1;
+            if last_group.is_some_and(|last_group| last_group + 1 == group) {
+                distribution.back_to_back_groups += 1;
+            }
+            last_group = Some(group);
+        }
+
+        if *slot > last_confirmed_slot {
+            continue;
+        }
+        let produced = usize::from(confirmed_blocks.binary_search(slot).is_ok());
+        let position = (slot_index % NUM_CONSECUTIVE_LEADER_SLOTS) as usize;
+        let segment = ((slot_index / slots_per_segment) as usize).min(segments - 1);
+        for slot_production in [
+            &mut distribution.by_position[position],
+            &mut distribution.by_segment[segment],
+        ] {
+            slot_production.leader_slots += 1;
+            slot_production.blocks_produced += produced;
+        }
+    }
+    distribution
+}
+
+/// Returns the `LeaderSlotDistribution` of `identity` in the current epoch, so far
+pub async fn get_leader_slot_distribution(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    identity: &Pubkey,
+    segments: usize,
+) -> Result<LeaderSlotDistribution, Box<dyn std::error::Error>> {
+    let leader_slots = get_leader_slots(rpc_client, epoch_info, identity).await?;
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let first_slot_in_epoch = epoch_info.absolute_slot - epoch_info.slot_index;
+    let last_confirmed_slot = rpc_client.get_slot().await?;
+
+    let confirmed_blocks = match leader_slots.first() {
+        Some(first_slot) if *first_slot <= last_confirmed_slot => {
+            rpc_client
+                .get_blocks(*first_slot, Some(last_confirmed_slot))
+                .await?
+        }
+        _ => vec![],
+    };
+
+    Ok(leader_slot_distribution(
+        &leader_slots,
+        &confirmed_blocks,
+        first_slot_in_epoch,
+        epoch_info.slots_in_epoch,
+        last_confirmed_slot,
+        segments,
+    ))
+}
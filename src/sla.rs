@@ -0,0 +1,252 @@
+//! Per-epoch compliance of a validator with an operator-defined service level agreement, as
+//! evidence for delegation agreements
+use {
+    crate::{
+        block_production_cache::{format_skip_rate, BlockProductionCache},
+        epoch_info::EpochInfoHandle,
+        report::Percentiles,
+        score::epoch_credits_earned,
+        vote_accounts::VoteAccountsSnapshot,
+        vote_latency::get_vote_latency_histograms,
+    },
+    serde_json::Value,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    std::{collections::BTreeMap, fmt},
+};
+
+/// The service level a validator commits to. Unset limits are not checked
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sla {
+    /// Maximum percentage of leader slots skipped
+    pub max_skip_rate: Option<f64>,
+    /// Minimum uptime, in percent, see `EpochSlaMetrics::uptime`
+    pub min_uptime: Option<f64>,
+    /// Maximum average vote latency, in slots
+    pub max_vote_latency: Option<f64>,
+    /// Minimum percentile of the credits earned among all validators
+    pub min_credits_percentile: Option<u8>,
+}
+
+impl Sla {
+    /// Parses an SLA of the form `{"maxSkipRate", "minUptime", "maxVoteLatency",
+    /// "minCreditsPercentile"}`, where every field is optional
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        if !value.is_object() {
+            return Err(format!("Invalid SLA: {}", value));
+        }
+        let f64_field = |name: &str| value.get(name).and_then(|field| field.as_f64());
+        Ok(Self {
+            max_skip_rate: f64_field("maxSkipRate"),
+            min_uptime: f64_field("minUptime"),
+            max_vote_latency: f64_field("maxVoteLatency"),
+            min_credits_percentile: value
+                .get("minCreditsPercentile")
+                .and_then(|field| field.as_u64())
+                .map(|percentile| percentile.min(100) as u8),
+        })
+    }
+
+    /// Returns the breaches of the SLA in `metrics`. A metric that could not be measured is not
+    /// a breach
+    pub fn breaches(&self, metrics: &EpochSlaMetrics) -> Vec<SlaBreach> {
+        let mut breaches = vec![];
+        if let (Some(max), Some(skip_rate)) = (self.max_skip_rate, metrics.skip_rate) {
+            if skip_rate > max {
+                breaches.push(SlaBreach::SkipRate { skip_rate, max });
+            }
+        }
+        if let (Some(min), Some(uptime)) = (self.min_uptime, metrics.uptime) {
+            if uptime < min {
+                breaches.push(SlaBreach::Uptime { uptime, min });
+            }
+        }
+        if let (Some(max), Some(vote_latency)) = (self.max_vote_latency, metrics.vote_latency) {
+            if vote_latency > max {
+                breaches.push(SlaBreach::VoteLatency { vote_latency, max });
+            }
+        }
+        if let (Some(min), Some(percentile)) =
+            (self.min_credits_percentile, metrics.credits_percentile)
+        {
+            if percentile < min {
+                breaches.push(SlaBreach::CreditsPercentile { percentile, min });
+            }
+        }
+        breaches
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlaBreach {
+    SkipRate { skip_rate: f64, max: f64 },
+    Uptime { uptime: f64, min: f64 },
+    VoteLatency { vote_latency: f64, max: f64 },
+    CreditsPercentile { percentile: u8, min: u8 },
+}
+
+impl fmt::Display for SlaBreach {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SlaBreach::SkipRate { skip_rate, max } => {
+                write!(f, "Skip rate {:.2}% above {:.2}%", skip_rate, max)
+            }
+            SlaBreach::Uptime { uptime, min } => {
+                write!(f, "Uptime {:.2}% below {:.2}%", uptime, min)
+            }
+            SlaBreach::VoteLatency { vote_latency, max } => write!(
+                f,
+                "Vote latency {:.2} slots above {:.2} slots",
+                vote_latency, max
+            ),
+            SlaBreach::CreditsPercentile { percentile, min } => write!(
+                f,
+                "Credits at the {}th percentile, below the {}th",
+                percentile, min
+            ),
+        }
+    }
+}
+
+/// The metrics of a validator an `Sla` is checked against, for one epoch. `None` when the metric
+/// could not be measured
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EpochSlaMetrics {
+    pub epoch: Epoch,
+    pub skip_rate: Option<f64>,
+    /// Credits earned as a percentage of the most credits earned by any validator in the epoch
+    pub uptime: Option<f64>,
+    /// Average vote latency, in slots, of a sample of the epoch's votes
+    pub vote_latency: Option<f64>,
+    pub credits_percentile: Option<u8>,
+}
+
+/// SLA compliance of a validator in one epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochSlaReport {
+    pub vote_pubkey: Pubkey,
+    pub metrics: EpochSlaMetrics,
+    pub breaches: Vec<SlaBreach>,
+}
+
+impl EpochSlaReport {
+    pub fn compliant(&self) -> bool {
+        self.breaches.is_empty()
+    }
+}
+
+impl fmt::Display for EpochSlaReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let metric = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+        let metrics = &self.metrics;
+        write!(
+            f,
+            "{} epoch {}: {}, skip rate {}, uptime {}, vote latency {}, credits percentile {}",
+            self.vote_pubkey,
+            metrics.epoch,
+            if self.compliant() {
+                "compliant"
+            } else {
+                "BREACHED"
+            },
+            format_skip_rate(metrics.skip_rate),
+            metric(metrics.uptime.map(|uptime| format!("{:.2}%", uptime))),
+            metric(
+                metrics
+                    .vote_latency
+                    .map(|vote_latency| format!("{:.2} slots", vote_latency))
+            ),
+            metric(
+                metrics
+                    .credits_percentile
+                    .map(|percentile| format!("{}th", percentile))
+            ),
+        )?;
+        for breach in &self.breaches {
+            write!(f, "\n  {}", breach)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks `vote_pubkey` against `sla` in each of the `epochs` most recent completed epochs,
+/// oldest first.
+///
+/// `getVoteAccounts` only reports the credits of the last few epochs, so uptime and percentile
+/// are missing for older epochs. Vote latency is measured on the last `vote_latency_samples`
+/// votes, and left out when `vote_latency_samples` is `None`
+pub async fn get_sla_reports(
+    rpc_client: &RpcClient,
+    epoch_info: &EpochInfoHandle,
+    block_production_cache: &mut BlockProductionCache,
+    vote_pubkey: &Pubkey,
+    sla: &Sla,
+    epochs: u64,
+    vote_latency_samples: Option<usize>,
+) -> Result<Vec<EpochSlaReport>, Box<dyn std::error::Error>> {
+    let epoch_info = epoch_info.get(rpc_client).await?;
+    let epoch_schedule = rpc_client.get_epoch_schedule().await?;
+    let vote_accounts = VoteAccountsSnapshot::fetch(rpc_client).await?;
+    let vai = vote_accounts
+        .find_by_vote(vote_pubkey)
+        .ok_or_else(|| format!("Vote account {} not found", vote_pubkey))?;
+    let identity = vote_accounts
+        .identity_of(vote_pubkey)
+        .ok_or_else(|| format!("Invalid identity {}", vai.node_pubkey))?;
+
+    let vote_latencies = match vote_latency_samples {
+        Some(samples) => {
+            get_vote_latency_histograms(rpc_client, &epoch_schedule, vote_pubkey, samples)
+                .await?
+                .into_iter()
+                .filter_map(|histogram| {
+                    histogram
+                        .average()
+                        .map(|average| (histogram.epoch, average))
+                })
+                .collect()
+        }
+        None => BTreeMap::new(),
+    };
+
+    let first_epoch = epoch_info.epoch.saturating_sub(epochs);
+    let mut reports = vec![];
+    for epoch in first_epoch..epoch_info.epoch {
+        let block_production = block_production_cache
+            .get_block_production(
+                rpc_client,
+                &identity,
+                epoch_schedule.get_first_slot_in_epoch(epoch),
+                epoch_schedule.get_last_slot_in_epoch(epoch),
+            )
+            .await?;
+        let skip_rate = block_production.skip_rate();
+
+        let all_credits = vote_accounts
+            .iter_with_epoch_credits(epoch)
+            .map(|(_, credits)| credits as f64)
+            .collect::<Vec<_>>();
+        let credits = epoch_credits_earned(&vai.epoch_credits, epoch);
+        let max_credits = all_credits.iter().copied().fold(0., f64::max);
+        let uptime = credits
+            .filter(|_| max_credits > 0.)
+            .map(|credits| credits as f64 * 100. / max_credits);
+        let credits_percentile = credits
+            .filter(|_| !all_credits.is_empty())
+            .map(|credits| Percentiles::new(&all_credits).rank(credits as f64));
+
+        let metrics = EpochSlaMetrics {
+            epoch,
+            skip_rate,
+            uptime,
+            vote_latency: vote_latencies.get(&epoch).copied(),
+            credits_percentile,
+        };
+        reports.push(EpochSlaReport {
+            vote_pubkey: *vote_pubkey,
+            breaches: sla.breaches(&metrics),
+            metrics,
+        });
+    }
+    Ok(reports)
+}
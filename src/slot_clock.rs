@@ -0,0 +1,94 @@
+//! Wall-clock estimates for slots
+use {
+    solana_sdk::clock::{Slot, UnixTimestamp, DEFAULT_MS_PER_SLOT},
+    std::fmt,
+};
+
+/// Maps slots to estimated wall-clock times by extrapolating from a reference slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotClock {
+    pub reference_slot: Slot,
+    /// Unix time, in milliseconds, of `reference_slot`
+    pub reference_time_ms: i64,
+    pub ms_per_slot: u64,
+}
+
+impl SlotClock {
+    pub fn new(reference_slot: Slot, reference_time_ms: i64, ms_per_slot: u64) -> Self {
+        Self {
+            reference_slot,
+            reference_time_ms,
+            ms_per_slot: ms_per_slot.max(1),
+        }
+    }
+
+    /// A clock assuming the cluster's target slot time of `DEFAULT_MS_PER_SLOT`
+    pub fn with_default_slot_time(reference_slot: Slot, reference_time_ms: i64) -> Self {
+        Self::new(reference_slot, reference_time_ms, DEFAULT_MS_PER_SLOT)
+    }
+
+    /// Estimated Unix time, in milliseconds, of `slot`
+    pub fn slot_time_ms(&self, slot: Slot) -> i64 {
+        let slots = slot as i64 - self.reference_slot as i64;
+        self.reference_time_ms + slots * self.ms_per_slot as i64
+    }
+
+    /// Estimated Unix time, in seconds, of `slot`
+    pub fn slot_time(&self, slot: Slot) -> UnixTimestamp {
+        self.slot_time_ms(slot).div_euclid(1000)
+    }
+
+    /// Estimated slot at the Unix time `time_ms`, in milliseconds
+    pub fn slot_at(&self, time_ms: i64) -> Slot {
+        let slots = (time_ms - self.reference_time_ms).div_euclid(self.ms_per_slot as i64);
+        (self.reference_slot as i64 + slots).max(0) as Slot
+    }
+}
+
+/// A UTC calendar date and time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UtcDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl UtcDateTime {
+    pub fn from_unix_timestamp(timestamp: UnixTimestamp) -> Self {
+        let days = timestamp.div_euclid(86_400);
+        let seconds_of_day = timestamp.rem_euclid(86_400) as u32;
+
+        // Civil-from-days conversion for the proleptic Gregorian calendar
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: seconds_of_day / 3600,
+            minute: seconds_of_day / 60 % 60,
+            second: seconds_of_day % 60,
+        }
+    }
+}
+
+impl fmt::Display for UtcDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
@@ -0,0 +1,139 @@
+//! Projection of when activating or deactivating stake becomes fully effective
+use {
+    crate::stake_pools::get_stake_history,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        clock::Epoch, native_token::Sol, pubkey::Pubkey, stake::state::StakeState,
+        stake_history::StakeHistoryEntry,
+    },
+    std::fmt,
+};
+
+/// Epochs a projection gives up after, when the cluster-wide rate limit is effectively zero
+const MAX_PROJECTED_EPOCHS: u64 = 1_000;
+
+/// Returns the number of epochs until `remaining` lamports of activating (or deactivating) stake
+/// are fully activated (or deactivated).
+///
+/// Each epoch the cluster lets at most `warmup_cooldown_rate` of `cluster_effective` stake
+/// change, shared proportionally among all `cluster_transitioning` stake. The projection assumes
+/// the cluster-wide effective and transitioning stake stay at their current level, `None` if
+/// the stake would never finish
+pub fn epochs_until_effective(
+    remaining: u64,
+    cluster_effective: u64,
+    cluster_transitioning: u64,
+    warmup_cooldown_rate: f64,
+) -> Option<u64> {
+    if remaining == 0 {
+        return Some(0);
+    }
+    if cluster_transitioning == 0 {
+        // The first epoch of the stake, not yet in the stake history
+        return Some(1);
+    }
+    let weight = remaining as f64 / cluster_transitioning as f64;
+    let per_epoch = ((weight * cluster_effective as f64 * warmup_cooldown_rate) as u64).max(1);
+    Some(remaining.div_ceil(per_epoch)).filter(|epochs| *epochs <= MAX_PROJECTED_EPOCHS)
+}
+
+/// The activation state of a stake account and when it is projected to settle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakeActivationCountdown {
+    pub stake_pubkey: Pubkey,
+    pub epoch: Epoch,
+    pub effective: u64,
+    pub activating: u64,
+    pub deactivating: u64,
+    /// Epochs until `activating` and `deactivating` are both zero, `None` if it can't be projected
+    pub epochs_remaining: Option<u64>,
+}
+
+impl StakeActivationCountdown {
+    /// Epoch from which the stake is fully effective (or fully deactivated)
+    pub fn settled_epoch(&self) -> Option<Epoch> {
+        self.epochs_remaining
+            .map(|epochs_remaining| self.epoch + epochs_remaining)
+    }
+}
+
+impl fmt::Display for StakeActivationCountdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} effective, {} activating, {} deactivating",
+            self.stake_pubkey,
+            Sol(self.effective),
+            Sol(self.activating),
+            Sol(self.deactivating)
+        )?;
+        match (self.activating + self.deactivating, self.epochs_remaining) {
+            (0, _) => Ok(()),
+            (_, Some(epochs_remaining)) => write!(
+                f,
+                ", settled in {} epoch(s), from epoch {}",
+                epochs_remaining,
+                self.epoch + epochs_remaining
+            ),
+            (_, None) => write!(f, ", settling can't be projected"),
+        }
+    }
+}
+
+fn latest_cluster_stake(stake_history: &[(Epoch, StakeHistoryEntry)]) -> StakeHistoryEntry {
+    // Stake history is ordered newest first
+    stake_history
+        .first()
+        .map(|(_, entry)| entry.clone())
+        .unwrap_or_default()
+}
+
+/// Returns the activation countdown of each of `stake_pubkeys`. Accounts that are not delegated
+/// are skipped
+pub async fn get_stake_activation_countdowns(
+    rpc_client: &RpcClient,
+    stake_pubkeys: &[Pubkey],
+) -> Result<Vec<StakeActivationCountdown>, Box<dyn std::error::Error>> {
+    let epoch = rpc_client.get_epoch_info().await?.epoch;
+    let stake_history = get_stake_history(rpc_client).await?;
+    let cluster_stake = latest_cluster_stake(&stake_history);
+
+    let mut countdowns = vec![];
+    for (stake_pubkey, account) in stake_pubkeys
+        .iter()
+        .zip(rpc_client.get_multiple_accounts(stake_pubkeys).await?)
+    {
+        let account = account.ok_or_else(|| format!("Stake account {} not found", stake_pubkey))?;
+        let stake = match account.deserialize_data::<StakeState>() {
+            Ok(StakeState::Stake(_, stake)) => stake,
+            _ => continue,
+        };
+        let status = stake
+            .delegation
+            .stake_activating_and_deactivating(epoch, Some(&stake_history));
+
+        let epochs_until_activated = epochs_until_effective(
+            status.activating,
+            cluster_stake.effective,
+            cluster_stake.activating,
+            stake.delegation.warmup_cooldown_rate,
+        );
+        let epochs_until_deactivated = epochs_until_effective(
+            status.deactivating,
+            cluster_stake.effective,
+            cluster_stake.deactivating,
+            stake.delegation.warmup_cooldown_rate,
+        );
+        countdowns.push(StakeActivationCountdown {
+            stake_pubkey: *stake_pubkey,
+            epoch,
+            effective: status.effective,
+            activating: status.activating,
+            deactivating: status.deactivating,
+            epochs_remaining: epochs_until_activated
+                .zip(epochs_until_deactivated)
+                .map(|(activated, deactivated)| activated.max(deactivated)),
+        });
+    }
+    Ok(countdowns)
+}
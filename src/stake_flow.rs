@@ -0,0 +1,244 @@
+//! Epoch-over-epoch stake flow into and out of a validator
+use {
+    crate::{
+        format::NumberFormat,
+        stake_pools::{
+            classify_stake_account, get_delegated_stake_accounts, get_stake_history, StakePool,
+            StakeSource,
+        },
+    },
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{clock::Epoch, pubkey::Pubkey, stake::state::StakeState},
+    std::{collections::BTreeMap, fmt},
+};
+
+/// Stake moving into and out of a validator across an epoch boundary
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StakeFlow {
+    pub vote_pubkey: Pubkey,
+    pub epoch: Epoch,
+    /// Effective stake, in lamports, in `epoch`
+    pub effective_stake: u64,
+    /// Stake, in lamports, activating in `epoch` and becoming effective in a following epoch
+    pub stake_inflow: u64,
+    /// Stake, in lamports, deactivating in `epoch` and leaving in a following epoch
+    pub stake_outflow: u64,
+    /// `stake_inflow` per source
+    pub inflow_by_source: BTreeMap<StakeSource, u64>,
+    /// `stake_outflow` per source
+    pub outflow_by_source: BTreeMap<StakeSource, u64>,
+}
+
+impl StakeFlow {
+    /// Expected change, in lamports, of the effective stake once the flows settle
+    pub fn net_change(&self) -> i64 {
+        self.stake_inflow as i64 - self.stake_outflow as i64
+    }
+
+    /// Builds a flow from the difference in activated stake between two epochs, as reported by
+    /// `getVoteAccounts`, when a stake account scan is not available
+    pub fn from_activated_stake(
+        vote_pubkey: Pubkey,
+        epoch: Epoch,
+        previous_activated_stake: u64,
+        activated_stake: u64,
+    ) -> Self {
+        Self {
+            vote_pubkey,
+            epoch,
+            effective_stake: activated_stake,
+            stake_inflow: activated_stake.saturating_sub(previous_activated_stake),
+            stake_outflow: previous_activated_stake.saturating_sub(activated_stake),
+            ..Self::default()
+        }
+    }
+}
+
+impl fmt::Display for StakeFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let number_format = NumberFormat::default();
+        let sol = |lamports: u64| number_format.format_sol(lamports, 0);
+        let sources = |by_source: &BTreeMap<StakeSource, u64>| {
+            by_source
+                .iter()
+                .map(|(source, stake)| format!("{} {}", source, sol(*stake)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "{} epoch {}: effective {}, inflow {} ({}), outflow {} ({}), net {}{}",
+            self.vote_pubkey,
+            self.epoch,
+            sol(self.effective_stake),
+            sol(self.stake_inflow),
+            sources(&self.inflow_by_source),
+            sol(self.stake_outflow),
+            sources(&self.outflow_by_source),
+            if self.net_change() < 0 { "-" } else { "+" },
+            sol(self.net_change().unsigned_abs())
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DelegationChangeKind {
+    /// A stake account delegated to the validator in this epoch
+    Delegated,
+    /// A stake account delegated to the validator started deactivating in this epoch
+    Deactivated,
+}
+
+/// A stake account that started moving into or out of a validator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelegationChange {
+    pub stake_pubkey: Pubkey,
+    pub vote_pubkey: Pubkey,
+    pub epoch: Epoch,
+    pub kind: DelegationChangeKind,
+    /// Delegated stake, in lamports
+    pub stake: u64,
+    pub source: StakeSource,
+}
+
+impl fmt::Display for DelegationChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} in epoch {} ({})",
+            self.stake_pubkey,
+            match self.kind {
+                DelegationChangeKind::Delegated => "delegated",
+                DelegationChangeKind::Deactivated => "is deactivating",
+            },
+            NumberFormat::default().format_sol(self.stake, 2),
+            match self.kind {
+                DelegationChangeKind::Delegated => "to",
+                DelegationChangeKind::Deactivated => "from",
+            },
+            self.vote_pubkey,
+            self.epoch,
+            self.source
+        )
+    }
+}
+
+/// Returns the stake accounts in `stake_accounts` that delegated to, or started deactivating
+/// from, `vote_pubkey` in `epoch` with at least `min_stake` lamports, largest first
+pub fn delegation_changes(
+    vote_pubkey: &Pubkey,
+    epoch: Epoch,
+    stake_accounts: &[(Pubkey, StakeState)],
+    stake_pools: &[StakePool],
+    min_stake: u64,
+) -> Vec<DelegationChange> {
+    let mut changes = stake_accounts
+        .iter()
+        .filter_map(|(stake_pubkey, stake_state)| {
+            let (meta, stake) = match stake_state {
+                StakeState::Stake(meta, stake) => (meta, stake),
+                _ => return None,
+            };
+            let delegation = &stake.delegation;
+            if delegation.voter_pubkey != *vote_pubkey || delegation.stake < min_stake {
+                return None;
+            }
+
+            let kind = if delegation.deactivation_epoch == epoch {
+                DelegationChangeKind::Deactivated
+            } else if delegation.activation_epoch == epoch
+                && delegation.deactivation_epoch == Epoch::MAX
+            {
+                DelegationChangeKind::Delegated
+            } else {
+                return None;
+            };
+
+            Some(DelegationChange {
+                stake_pubkey: *stake_pubkey,
+                vote_pubkey: *vote_pubkey,
+                epoch,
+                kind,
+                stake: delegation.stake,
+                source: classify_stake_account(
+                    stake_pools,
+                    &meta.authorized.staker,
+                    &meta.authorized.withdrawer,
+                ),
+            })
+        })
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| {
+        b.stake
+            .cmp(&a.stake)
+            .then(a.stake_pubkey.cmp(&b.stake_pubkey))
+    });
+    changes
+}
+
+/// Returns the delegations to, and deactivations from, `vote_pubkey` in the current epoch of at
+/// least `min_stake` lamports
+pub async fn get_delegation_changes(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    stake_pools: &[StakePool],
+    min_stake: u64,
+) -> Result<Vec<DelegationChange>, Box<dyn std::error::Error>> {
+    let epoch = rpc_client.get_epoch_info().await?.epoch;
+    let stake_accounts = get_delegated_stake_accounts(rpc_client, vote_pubkey).await?;
+    Ok(delegation_changes(
+        vote_pubkey,
+        epoch,
+        &stake_accounts,
+        stake_pools,
+        min_stake,
+    ))
+}
+
+/// Returns the stake activating and deactivating on `vote_pubkey` in the current epoch, from a
+/// scan of its stake accounts, attributed to the stake pools in `stake_pools`
+pub async fn get_stake_flow(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    stake_pools: &[StakePool],
+) -> Result<StakeFlow, Box<dyn std::error::Error>> {
+    let epoch = rpc_client.get_epoch_info().await?.epoch;
+    let stake_history = get_stake_history(rpc_client).await?;
+
+    let mut stake_flow = StakeFlow {
+        vote_pubkey: *vote_pubkey,
+        epoch,
+        ..StakeFlow::default()
+    };
+
+    for (_, stake_state) in get_delegated_stake_accounts(rpc_client, vote_pubkey).await? {
+        if let StakeState::Stake(meta, stake) = stake_state {
+            let status = stake
+                .delegation
+                .stake_activating_and_deactivating(epoch, Some(&stake_history));
+            stake_flow.effective_stake += status.effective;
+            if status.activating == 0 && status.deactivating == 0 {
+                continue;
+            }
+
+            let source = classify_stake_account(
+                stake_pools,
+                &meta.authorized.staker,
+                &meta.authorized.withdrawer,
+            );
+            if status.activating > 0 {
+                stake_flow.stake_inflow += status.activating;
+                *stake_flow
+                    .inflow_by_source
+                    .entry(source.clone())
+                    .or_default() += status.activating;
+            }
+            if status.deactivating > 0 {
+                stake_flow.stake_outflow += status.deactivating;
+                *stake_flow.outflow_by_source.entry(source).or_default() += status.deactivating;
+            }
+        }
+    }
+
+    Ok(stake_flow)
+}
@@ -0,0 +1,346 @@
+//! Attribution of a validator's delegated stake to stake pools and other large delegators
+use {
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
+    },
+    solana_sdk::{
+        account::from_account,
+        clock::Epoch,
+        pubkey,
+        pubkey::Pubkey,
+        stake::{self, state::StakeState},
+        sysvar::stake_history::{self, StakeHistory},
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        fmt,
+    },
+};
+
+/// Serialized discriminant of `StakeState::Stake`, at the start of a delegated stake account
+const STAKE_STATE_STAKE_TAG: [u8; 4] = 2u32.to_le_bytes();
+/// Offset of `Authorized::withdrawer` within a `StakeState::Initialized` or `StakeState::Stake`
+/// account
+pub(crate) const STAKE_WITHDRAWER_OFFSET: usize = 44;
+/// Offset of `Delegation::voter_pubkey` within a `StakeState::Stake` account
+const STAKE_VOTER_PUBKEY_OFFSET: usize = 124;
+
+/// SPL stake pool programs deployed by Sanctum, each hosting many LST pools
+const SANCTUM_STAKE_POOL_PROGRAM_IDS: [Pubkey; 2] = [
+    pubkey!("SP12tWFxD9oJsVWNavTTBZvMbA6gkAmxtVgxdqvyvhY"),
+    pubkey!("SPMBzsVUuoHA4Jm6KunbsotaahvVikZs1JyTW6iJvbn"),
+];
+/// `AccountType::StakePool`, the first byte of an SPL stake pool account
+const SPL_STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+
+/// A known delegator, recognized by the stake or withdraw authority of its stake accounts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakePool {
+    pub name: String,
+    pub authorities: Vec<Pubkey>,
+}
+
+impl StakePool {
+    pub fn new(name: &str, authorities: &[Pubkey]) -> Self {
+        Self {
+            name: name.to_string(),
+            authorities: authorities.to_vec(),
+        }
+    }
+}
+
+/// Stake pools and delegation programs with a fixed set of authorities.
+///
+/// The Sanctum LSTs use a distinct authority per pool, see `get_known_stake_pools`
+pub fn known_stake_pools() -> Vec<StakePool> {
+    vec![
+        StakePool::new(
+            "Marinade",
+            &[
+                pubkey!("9eG63CdHjsfhHmobHgLtESGC8GabbmRcaSpHAZrtmhco"),
+                pubkey!("stWirqFCf2Uts1JBL1Jsd3r6VBWhgnpdPxCTe1MFjrq"),
+            ],
+        ),
+        StakePool::new(
+            "Jito",
+            &[pubkey!("6iQKfEyhr3bZMotVkW6beNZz5CPAkiwvgV2CTje9pVSS")],
+        ),
+        StakePool::new(
+            "BlazeStake",
+            &[pubkey!("6WecYymEARvjG5ZyqkrVQ6YkhPfujNzWpSPwNKXHCbV2")],
+        ),
+        StakePool::new(
+            "SFDP",
+            &[pubkey!("mpa4abUkjQoAvPzREkh5Mo75hZhPFQ2FSH6w7dWKuQ5")],
+        ),
+    ]
+}
+
+/// Returns the Sanctum LSTs as a single stake pool, recognized by the withdraw authority of every
+/// pool currently hosted by the Sanctum stake pool programs
+pub async fn get_sanctum_stake_pool(
+    rpc_client: &RpcClient,
+) -> Result<StakePool, Box<dyn std::error::Error>> {
+    let mut authorities = vec![];
+    for program_id in &SANCTUM_STAKE_POOL_PROGRAM_IDS {
+        let pools = rpc_client
+            .get_program_accounts_with_config(
+                program_id,
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                        0,
+                        &[SPL_STAKE_POOL_ACCOUNT_TYPE],
+                    ))]),
+                    account_config: RpcAccountInfoConfig {
+                        commitment: Some(rpc_client.commitment()),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )
+            .await?;
+        authorities.extend(pools.into_iter().map(|(pool_pubkey, _)| {
+            Pubkey::find_program_address(&[pool_pubkey.as_ref(), b"withdraw"], program_id).0
+        }));
+    }
+    Ok(StakePool::new("Sanctum", &authorities))
+}
+
+/// Returns `known_stake_pools` followed by the Sanctum LSTs
+pub async fn get_known_stake_pools(
+    rpc_client: &RpcClient,
+) -> Result<Vec<StakePool>, Box<dyn std::error::Error>> {
+    let mut stake_pools = known_stake_pools();
+    stake_pools.push(get_sanctum_stake_pool(rpc_client).await?);
+    Ok(stake_pools)
+}
+
+/// Where a stake account's delegation originates from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StakeSource {
+    Pool(String),
+    Native,
+}
+
+impl fmt::Display for StakeSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StakeSource::Pool(name) => write!(f, "{}", name),
+            StakeSource::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// Classifies a stake account by its stake and withdraw authorities
+pub fn classify_stake_account(
+    stake_pools: &[StakePool],
+    staker: &Pubkey,
+    withdrawer: &Pubkey,
+) -> StakeSource {
+    stake_pools
+        .iter()
+        .find(|stake_pool| {
+            stake_pool
+                .authorities
+                .iter()
+                .any(|authority| authority == staker || authority == withdrawer)
+        })
+        .map(|stake_pool| StakeSource::Pool(stake_pool.name.clone()))
+        .unwrap_or(StakeSource::Native)
+}
+
+/// A validator's effective stake, broken down by `StakeSource`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StakeAttribution {
+    pub vote_pubkey: Pubkey,
+    pub epoch: Epoch,
+    /// Effective stake, in lamports, per source
+    pub by_source: BTreeMap<StakeSource, u64>,
+}
+
+impl StakeAttribution {
+    pub fn total_stake(&self) -> u64 {
+        self.by_source.values().sum()
+    }
+
+    pub fn pool_stake(&self) -> u64 {
+        self.by_source
+            .iter()
+            .filter(|(source, _)| **source != StakeSource::Native)
+            .map(|(_, stake)| stake)
+            .sum()
+    }
+
+    /// Fraction of the effective stake that is delegated by stake pools, from 0.0 to 1.0
+    pub fn pool_fraction(&self) -> f64 {
+        let total_stake = self.total_stake();
+        if total_stake == 0 {
+            0.
+        } else {
+            self.pool_stake() as f64 / total_stake as f64
+        }
+    }
+}
+
+impl fmt::Display for StakeAttribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total_stake = self.total_stake().max(1) as f64;
+        let sources = self
+            .by_source
+            .iter()
+            .map(|(source, stake)| format!("{} {:.2}%", source, *stake as f64 * 100. / total_stake))
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "{}: {:.2}% pool stake ({})",
+            self.vote_pubkey,
+            self.pool_fraction() * 100.,
+            sources.join(", ")
+        )
+    }
+}
+
+pub(crate) async fn get_stake_history(
+    rpc_client: &RpcClient,
+) -> Result<StakeHistory, Box<dyn std::error::Error>> {
+    let stake_history_account = rpc_client.get_account(&stake_history::id()).await?;
+    from_account::<StakeHistory, _>(&stake_history_account)
+        .ok_or_else(|| "Failed to deserialize stake history".into())
+}
+
+/// Returns all stake accounts currently delegated to `vote_pubkey`
+pub async fn get_delegated_stake_accounts(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+) -> Result<Vec<(Pubkey, StakeState)>, Box<dyn std::error::Error>> {
+    let accounts = rpc_client
+        .get_program_accounts_with_config(
+            &stake::program::id(),
+            RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    STAKE_VOTER_PUBKEY_OFFSET,
+                    vote_pubkey.as_ref(),
+                ))]),
+                account_config: RpcAccountInfoConfig {
+                    commitment: Some(rpc_client.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )
+        .await?;
+
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(stake_pubkey, account)| {
+            account
+                .deserialize_data::<StakeState>()
+                .ok()
+                .map(|stake_state| (stake_pubkey, stake_state))
+        })
+        .collect())
+}
+
+/// Returns every delegated stake account, in a single `getProgramAccounts` request
+async fn get_all_delegated_stake_accounts(
+    rpc_client: &RpcClient,
+) -> Result<Vec<(Pubkey, StakeState)>, Box<dyn std::error::Error>> {
+    let accounts = rpc_client
+        .get_program_accounts_with_config(
+            &stake::program::id(),
+            RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    0,
+                    &STAKE_STATE_STAKE_TAG,
+                ))]),
+                account_config: RpcAccountInfoConfig {
+                    commitment: Some(rpc_client.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )
+        .await?;
+
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(stake_pubkey, account)| {
+            account
+                .deserialize_data::<StakeState>()
+                .ok()
+                .map(|stake_state| (stake_pubkey, stake_state))
+        })
+        .collect())
+}
+
+/// Returns the effective stake, in lamports, that each of `stake_pools` delegates to every vote
+/// account in the current epoch.
+///
+/// All delegated stake accounts are fetched with a single `getProgramAccounts` request and
+/// classified locally, so the cost doesn't grow with the number of pool authorities or
+/// validators. A stake account matching several pools is attributed like
+/// `classify_stake_account` does
+pub async fn get_pool_stake_by_vote_account(
+    rpc_client: &RpcClient,
+    stake_pools: &[StakePool],
+) -> Result<HashMap<Pubkey, BTreeMap<String, u64>>, Box<dyn std::error::Error>> {
+    let epoch = rpc_client.get_epoch_info().await?.epoch;
+    let stake_history = get_stake_history(rpc_client).await?;
+
+    let mut pool_stake = HashMap::<_, BTreeMap<_, _>>::new();
+    for (_, stake_state) in get_all_delegated_stake_accounts(rpc_client).await? {
+        if let StakeState::Stake(meta, stake) = stake_state {
+            let effective_stake = stake.delegation.stake(epoch, Some(&stake_history));
+            if let (StakeSource::Pool(name), true) = (
+                classify_stake_account(
+                    stake_pools,
+                    &meta.authorized.staker,
+                    &meta.authorized.withdrawer,
+                ),
+                effective_stake > 0,
+            ) {
+                *pool_stake
+                    .entry(stake.delegation.voter_pubkey)
+                    .or_default()
+                    .entry(name)
+                    .or_default() += effective_stake;
+            }
+        }
+    }
+    Ok(pool_stake)
+}
+
+/// Attributes the effective stake of `vote_pubkey` in the current epoch to the stake pools in
+/// `stake_pools`, with all remaining stake reported as `StakeSource::Native`
+pub async fn get_stake_attribution(
+    rpc_client: &RpcClient,
+    vote_pubkey: &Pubkey,
+    stake_pools: &[StakePool],
+) -> Result<StakeAttribution, Box<dyn std::error::Error>> {
+    let epoch = rpc_client.get_epoch_info().await?.epoch;
+    let stake_history = get_stake_history(rpc_client).await?;
+
+    let mut attribution = StakeAttribution {
+        vote_pubkey: *vote_pubkey,
+        epoch,
+        ..StakeAttribution::default()
+    };
+
+    for (_, stake_state) in get_delegated_stake_accounts(rpc_client, vote_pubkey).await? {
+        if let StakeState::Stake(meta, stake) = stake_state {
+            let effective_stake = stake.delegation.stake(epoch, Some(&stake_history));
+            if effective_stake > 0 {
+                let source = classify_stake_account(
+                    stake_pools,
+                    &meta.authorized.staker,
+                    &meta.authorized.withdrawer,
+                );
+                *attribution.by_source.entry(source).or_default() += effective_stake;
+            }
+        }
+    }
+
+    Ok(attribution)
+}
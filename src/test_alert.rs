@@ -0,0 +1,87 @@
+//! Synthetic alerts, to check that alerts reach the on-call team without waiting for a real
+//! incident
+use {
+    crate::{
+        cluster_halt::RootStallEvent, credit_stall::CreditStallEvent,
+        vote_progress::RootAdvanceEvent,
+    },
+    solana_sdk::pubkey::Pubkey,
+    std::{fmt, str::FromStr},
+};
+
+/// Prefix of every test alert message, so it's not mistaken for a real incident
+pub const TEST_ALERT_PREFIX: &str = "[TEST ALERT]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestAlert {
+    Delinquent,
+    CreditStall,
+    VoteRootStall,
+    ClusterHalt,
+}
+
+impl TestAlert {
+    pub const ALL: [TestAlert; 4] = [
+        TestAlert::Delinquent,
+        TestAlert::CreditStall,
+        TestAlert::VoteRootStall,
+        TestAlert::ClusterHalt,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TestAlert::Delinquent => "delinquent",
+            TestAlert::CreditStall => "credit-stall",
+            TestAlert::VoteRootStall => "vote-root-stall",
+            TestAlert::ClusterHalt => "cluster-halt",
+        }
+    }
+
+    /// Returns the message of the alert for the validator voting with `vote_pubkey`, worded as
+    /// the real alert and prefixed with `TEST_ALERT_PREFIX`
+    pub fn message(&self, vote_pubkey: &Pubkey) -> String {
+        let msg = match self {
+            TestAlert::Delinquent => format!("{} is delinquent", vote_pubkey),
+            TestAlert::CreditStall => format!(
+                "{}: {}",
+                vote_pubkey,
+                CreditStallEvent::Stalled {
+                    credits: 0,
+                    samples: 0,
+                }
+            ),
+            TestAlert::VoteRootStall => format!(
+                "{}: {}",
+                vote_pubkey,
+                RootAdvanceEvent::Stalled {
+                    root_slot: None,
+                    last_vote: None,
+                    stalled_for_ms: 0,
+                }
+            ),
+            TestAlert::ClusterHalt => RootStallEvent::Stalled {
+                slot: 0,
+                stalled_for_ms: 0,
+            }
+            .to_string(),
+        };
+        format!("{} {}", TEST_ALERT_PREFIX, msg)
+    }
+}
+
+impl fmt::Display for TestAlert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for TestAlert {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TestAlert::ALL
+            .into_iter()
+            .find(|test_alert| test_alert.name() == s)
+            .ok_or_else(|| format!("Unknown test alert {}", s))
+    }
+}
@@ -0,0 +1,196 @@
+//! A single `getVoteAccounts` response shared between computations
+use {
+    crate::score::{epoch_credits_earned, VoteAccountCredits},
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::RpcGetVoteAccountsConfig,
+        rpc_response::{RpcVoteAccountInfo, RpcVoteAccountStatus},
+    },
+    solana_sdk::{clock::Epoch, commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::collections::{BTreeMap, HashMap},
+};
+
+/// Identity to vote account lookup, in both directions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdentityVoteMap {
+    identity_by_vote: HashMap<Pubkey, Pubkey>,
+    /// An identity can vote with several vote accounts, current ones come first
+    vote_pubkeys_by_identity: HashMap<Pubkey, Vec<Pubkey>>,
+}
+
+impl IdentityVoteMap {
+    /// Builds the lookup from `(vote pubkey, identity)` pairs, skipping unparsable addresses
+    pub fn new<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut map = Self::default();
+        for (vote_pubkey, identity) in pairs {
+            if let (Ok(vote_pubkey), Ok(identity)) =
+                (vote_pubkey.parse::<Pubkey>(), identity.parse::<Pubkey>())
+            {
+                map.identity_by_vote.insert(vote_pubkey, identity);
+                map.vote_pubkeys_by_identity
+                    .entry(identity)
+                    .or_default()
+                    .push(vote_pubkey);
+            }
+        }
+        map
+    }
+
+    pub fn identity(&self, vote_pubkey: &Pubkey) -> Option<Pubkey> {
+        self.identity_by_vote.get(vote_pubkey).copied()
+    }
+
+    /// Returns the vote account of `identity`, preferring a current one if the identity has
+    /// several
+    pub fn vote_pubkey(&self, identity: &Pubkey) -> Option<Pubkey> {
+        self.vote_pubkeys(identity).first().copied()
+    }
+
+    pub fn vote_pubkeys(&self, identity: &Pubkey) -> &[Pubkey] {
+        self.vote_pubkeys_by_identity
+            .get(identity)
+            .map(|vote_pubkeys| vote_pubkeys.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Every vote account, including unstaked delinquent ones, as of one `getVoteAccounts` request.
+///
+/// Fetching the vote accounts is the heaviest request most computations make, so fetch a snapshot
+/// once and pass it to each of them
+#[derive(Debug, Clone)]
+pub struct VoteAccountsSnapshot {
+    vote_accounts: RpcVoteAccountStatus,
+    identity_vote_map: IdentityVoteMap,
+}
+
+impl From<RpcVoteAccountStatus> for VoteAccountsSnapshot {
+    fn from(vote_accounts: RpcVoteAccountStatus) -> Self {
+        let identity_vote_map = IdentityVoteMap::new(
+            vote_accounts
+                .current
+                .iter()
+                .chain(&vote_accounts.delinquent)
+                .map(|vai| (vai.vote_pubkey.as_str(), vai.node_pubkey.as_str())),
+        );
+        Self {
+            vote_accounts,
+            identity_vote_map,
+        }
+    }
+}
+
+impl VoteAccountsSnapshot {
+    pub async fn fetch(rpc_client: &RpcClient) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::fetch_with_commitment(rpc_client, rpc_client.commitment()).await
+    }
+
+    /// Like `fetch`, at `commitment` rather than the client's default
+    pub async fn fetch_with_commitment(
+        rpc_client: &RpcClient,
+        commitment: CommitmentConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(rpc_client
+            .get_vote_accounts_with_config(RpcGetVoteAccountsConfig {
+                commitment: Some(commitment),
+                keep_unstaked_delinquents: Some(true),
+                ..RpcGetVoteAccountsConfig::default()
+            })
+            .await?
+            .into())
+    }
+
+    /// Current vote accounts followed by delinquent ones
+    pub fn iter(&self) -> impl Iterator<Item = &RpcVoteAccountInfo> {
+        self.vote_accounts
+            .current
+            .iter()
+            .chain(&self.vote_accounts.delinquent)
+    }
+
+    pub fn len(&self) -> usize {
+        self.vote_accounts.current.len() + self.vote_accounts.delinquent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The most recent epoch any vote account has credits in. Validators vote within the first
+    /// slots of an epoch, so this is the epoch the snapshot was taken in
+    pub fn latest_epoch(&self) -> Option<Epoch> {
+        self.iter()
+            .filter_map(|vai| vai.epoch_credits.last().map(|(epoch, ..)| *epoch))
+            .max()
+    }
+
+    pub fn find_by_vote(&self, vote_pubkey: &Pubkey) -> Option<&RpcVoteAccountInfo> {
+        let vote_pubkey = vote_pubkey.to_string();
+        self.iter().find(|vai| vai.vote_pubkey == vote_pubkey)
+    }
+
+    /// Returns the vote account of `identity`, preferring a current one if the identity has
+    /// several
+    pub fn find_by_identity(&self, identity: &Pubkey) -> Option<&RpcVoteAccountInfo> {
+        self.find_by_vote(&self.identity_vote_map.vote_pubkey(identity)?)
+    }
+
+    /// The identity to vote account lookup of this snapshot, for features that need one from
+    /// the other
+    pub fn identity_vote_map(&self) -> &IdentityVoteMap {
+        &self.identity_vote_map
+    }
+
+    /// Returns the identity that votes with `vote_pubkey`
+    pub fn identity_of(&self, vote_pubkey: &Pubkey) -> Option<Pubkey> {
+        self.identity_vote_map.identity(vote_pubkey)
+    }
+
+    pub fn is_delinquent(&self, vote_pubkey: &Pubkey) -> bool {
+        let vote_pubkey = vote_pubkey.to_string();
+        self.vote_accounts
+            .delinquent
+            .iter()
+            .any(|vai| vai.vote_pubkey == vote_pubkey)
+    }
+
+    /// Returns each vote account that has an `epoch_credits` entry for `epoch`, with the credits
+    /// earned in it
+    pub fn iter_with_epoch_credits(
+        &self,
+        epoch: Epoch,
+    ) -> impl Iterator<Item = (&RpcVoteAccountInfo, u64)> {
+        self.iter().filter_map(move |vai| {
+            epoch_credits_earned(&vai.epoch_credits, epoch).map(|credits| (vai, credits))
+        })
+    }
+
+    /// Returns the credit history of every vote account, skipping unparsable addresses
+    pub fn vote_account_credits(&self) -> Vec<VoteAccountCredits> {
+        self.iter()
+            .filter_map(|vai| {
+                vai.vote_pubkey
+                    .parse::<Pubkey>()
+                    .ok()
+                    .map(|vote_pubkey| VoteAccountCredits {
+                        vote_pubkey,
+                        commission: vai.commission,
+                        activated_stake: vai.activated_stake,
+                        epoch_credits: vai.epoch_credits.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the first epoch with credits of every vote account that earned any, see
+    /// `VoteAccountCredits::first_epoch_with_credits`
+    pub fn first_epochs_with_credits(&self) -> BTreeMap<Pubkey, Epoch> {
+        self.vote_account_credits()
+            .into_iter()
+            .filter_map(|vac| {
+                vac.first_epoch_with_credits()
+                    .map(|first_epoch| (vac.vote_pubkey, first_epoch))
+            })
+            .collect()
+    }
+}
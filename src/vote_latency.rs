@@ -0,0 +1,200 @@
+//! Histograms of how many slots a validator's votes take to land
+use {
+    serde_json::Value,
+    solana_client::{
+        nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+        rpc_config::RpcTransactionConfig,
+    },
+    solana_sdk::{
+        clock::{Epoch, Slot},
+        epoch_schedule::EpochSchedule,
+        pubkey::Pubkey,
+        signature::Signature,
+        vote,
+    },
+    solana_transaction_status::{
+        EncodedTransaction, UiInstruction, UiMessage, UiParsedInstruction, UiTransactionEncoding,
+    },
+    std::{collections::BTreeMap, fmt, str::FromStr},
+};
+
+/// Signatures requested per `getSignaturesForAddress` call, the RPC maximum
+const SIGNATURES_PAGE_SIZE: usize = 1_000;
+
+/// Landing latency of the votes of one epoch, in slots between the voted slot and the slot the
+/// vote landed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VoteLatencyHistogram {
+    pub epoch: Epoch,
+    pub one_slot: usize,
+    pub two_slots: usize,
+    pub three_or_more_slots: usize,
+    /// Sum of the latencies of all votes, for the average
+    pub total_latency: u64,
+}
+
+impl VoteLatencyHistogram {
+    pub fn new(epoch: Epoch) -> Self {
+        Self {
+            epoch,
+            ..Self::default()
+        }
+    }
+
+    pub fn record(&mut self, latency: u64) {
+        match latency {
+            0 | 1 => self.one_slot += 1,
+            2 => self.two_slots += 1,
+            _ => self.three_or_more_slots += 1,
+        }
+        self.total_latency += latency;
+    }
+
+    pub fn votes(&self) -> usize {
+        self.one_slot + self.two_slots + self.three_or_more_slots
+    }
+
+    /// Average latency in slots, `None` without votes
+    pub fn average(&self) -> Option<f64> {
+        (self.votes() > 0).then(|| self.total_latency as f64 / self.votes() as f64)
+    }
+
+    fn percent(&self, votes: usize) -> f64 {
+        votes as f64 * 100. / self.votes().max(1) as f64
+    }
+}
+
+impl fmt::Display for VoteLatencyHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Epoch {}: {} votes, 1 slot {:.1}%, 2 slots {:.1}%, 3+ slots {:.1}%, average {}",
+            self.epoch,
+            self.votes(),
+            self.percent(self.one_slot),
+            self.percent(self.two_slots),
+            self.percent(self.three_or_more_slots),
+            self.average()
+                .map(|average| format!("{:.2} slots", average))
+                .unwrap_or_else(|| "-".into())
+        )
+    }
+}
+
+/// Returns the newest slot voted on by a `jsonParsed` vote program instruction: the last slot of
+/// a `vote`, or the last lockout of a `voteStateUpdate` or `towerSync`
+pub fn parse_voted_slot(parsed_instruction: &Value) -> Option<Slot> {
+    let info = parsed_instruction.get("info")?;
+    if let Some(slots) = info
+        .get("vote")
+        .and_then(|vote| vote.get("slots"))
+        .and_then(|slots| slots.as_array())
+    {
+        return slots.last().and_then(|slot| slot.as_u64());
+    }
+    ["voteStateUpdate", "towerSync"]
+        .iter()
+        .find_map(|key| info.get(key))
+        .and_then(|update| update.get("lockouts"))
+        .and_then(|lockouts| lockouts.as_array())
+        .and_then(|lockouts| lockouts.last())
+        .and_then(|lockout| lockout.get("slot"))
+        .and_then(|slot| slot.as_u64())
+}
+
+/// Returns the voted slot of the vote transaction `signature`, `None` if it holds no vote
+async fn get_voted_slot(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+) -> Result<Option<Slot>, Box<dyn std::error::Error>> {
+    let transaction = rpc_client
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::JsonParsed),
+                commitment: Some(rpc_client.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await?;
+    let instructions = match transaction.transaction.transaction {
+        EncodedTransaction::Json(transaction) => match transaction.message {
+            UiMessage::Parsed(message) => message.instructions,
+            UiMessage::Raw(_) => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    let vote_program_id = vote::program::id().to_string();
+    Ok(instructions
+        .iter()
+        .find_map(|instruction| match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(instruction))
+                if instruction.program_id == vote_program_id =>
+            {
+                parse_voted_slot(&instruction.parsed)
+            }
+            _ => None,
+        }))
+}
+
+/// Returns the landing latency histogram of each epoch covered by the last `samples` successful
+/// vote transactions of `vote_pubkey`, oldest epoch first.
+///
+/// The latency of a vote is the slot it landed in minus the newest slot it voted on. Every
+/// sampled vote costs one `getTransaction` call, and RPC nodes only serve the transactions still
+/// in their ledger, so the oldest epoch is usually only partially covered
+pub async fn get_vote_latency_histograms(
+    rpc_client: &RpcClient,
+    epoch_schedule: &EpochSchedule,
+    vote_pubkey: &Pubkey,
+    samples: usize,
+) -> Result<Vec<VoteLatencyHistogram>, Box<dyn std::error::Error>> {
+    let mut histograms = BTreeMap::<Epoch, VoteLatencyHistogram>::new();
+    let mut sampled = 0;
+    let mut before = None;
+    while sampled < samples {
+        let signatures = rpc_client
+            .get_signatures_for_address_with_config(
+                vote_pubkey,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(SIGNATURES_PAGE_SIZE),
+                    commitment: Some(rpc_client.commitment()),
+                },
+            )
+            .await?;
+        let last_signature = match signatures.last() {
+            Some(last_signature) => Signature::from_str(&last_signature.signature)?,
+            None => break,
+        };
+
+        for signature in signatures
+            .iter()
+            .filter(|signature| signature.err.is_none())
+        {
+            if sampled >= samples {
+                break;
+            }
+            let voted_slot =
+                match get_voted_slot(rpc_client, &Signature::from_str(&signature.signature)?)
+                    .await?
+                {
+                    Some(voted_slot) => voted_slot,
+                    None => continue,
+                };
+            let epoch = epoch_schedule.get_epoch(signature.slot);
+            histograms
+                .entry(epoch)
+                .or_insert_with(|| VoteLatencyHistogram::new(epoch))
+                .record(signature.slot.saturating_sub(voted_slot));
+            sampled += 1;
+        }
+
+        if signatures.len() < SIGNATURES_PAGE_SIZE {
+            break;
+        }
+        before = Some(last_signature);
+    }
+    Ok(histograms.into_values().collect())
+}
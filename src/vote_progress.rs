@@ -0,0 +1,317 @@
+//! Tracking of a validator's votes and roots, to catch a validator whose votes land but whose
+//! root stops advancing, a consensus participation problem the credits alone don't show
+use {crate::check::CheckStatus, solana_sdk::clock::Slot, std::fmt};
+
+/// A sample of a vote account's state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VoteProgress {
+    /// Slot at which the vote account was observed
+    pub slot: Slot,
+    pub credits: u64,
+    /// Most recent slot voted on
+    pub last_vote: Option<Slot>,
+    pub root_slot: Option<Slot>,
+}
+
+impl VoteProgress {
+    /// Slots between the observation and the last vote
+    pub fn vote_distance(&self) -> Option<u64> {
+        self.last_vote
+            .map(|last_vote| self.slot.saturating_sub(last_vote))
+    }
+
+    /// Slots between the observation and the root
+    pub fn root_distance(&self) -> Option<u64> {
+        self.root_slot
+            .map(|root_slot| self.slot.saturating_sub(root_slot))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootAdvanceEvent {
+    /// The root has not advanced past `root_slot` for `stalled_for_ms` while votes kept landing
+    Stalled {
+        root_slot: Option<Slot>,
+        last_vote: Option<Slot>,
+        stalled_for_ms: i64,
+    },
+    /// The root advanced to `root_slot` after a stall of `stalled_for_ms`
+    Resumed {
+        root_slot: Option<Slot>,
+        stalled_for_ms: i64,
+    },
+}
+
+impl RootAdvanceEvent {
+    pub fn severity(&self) -> CheckStatus {
+        match self {
+            RootAdvanceEvent::Stalled { .. } => CheckStatus::Warning,
+            RootAdvanceEvent::Resumed { .. } => CheckStatus::Ok,
+        }
+    }
+}
+
+impl fmt::Display for RootAdvanceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let slot = |slot: &Option<Slot>| {
+            slot.map(|slot| slot.to_string())
+                .unwrap_or_else(|| "none".into())
+        };
+        match self {
+            RootAdvanceEvent::Stalled {
+                root_slot,
+                last_vote,
+                stalled_for_ms,
+            } => write!(
+                f,
+                "Root stuck at slot {} for {}s while votes keep landing (last vote {})",
+                slot(root_slot),
+                stalled_for_ms / 1000,
+                slot(last_vote)
+            ),
+            RootAdvanceEvent::Resumed {
+                root_slot,
+                stalled_for_ms,
+            } => write!(
+                f,
+                "Root advancing again at slot {} after a {}s stall",
+                slot(root_slot),
+                stalled_for_ms / 1000
+            ),
+        }
+    }
+}
+
+/// Reports when a validator's root doesn't advance for longer than a threshold although its last
+/// vote does, and when the root advances again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootAdvanceDetector {
+    stall_threshold_ms: i64,
+    last_root_slot: Option<Slot>,
+    /// Last vote when `last_root_slot` was first observed
+    last_vote_at_root_advance: Option<Slot>,
+    /// Unix time, in milliseconds, at which `last_root_slot` was first observed
+    last_advance_ms: Option<i64>,
+    stalled: bool,
+}
+
+impl RootAdvanceDetector {
+    pub fn new(stall_threshold_ms: i64) -> Self {
+        Self {
+            stall_threshold_ms,
+            last_root_slot: None,
+            last_vote_at_root_advance: None,
+            last_advance_ms: None,
+            stalled: false,
+        }
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Records `vote_progress` observed at Unix time `now_ms`, in milliseconds. An event is
+    /// returned only when the stall state changes
+    pub fn observe(
+        &mut self,
+        vote_progress: &VoteProgress,
+        now_ms: i64,
+    ) -> Option<RootAdvanceEvent> {
+        match self.last_advance_ms {
+            Some(last_advance_ms) if vote_progress.root_slot <= self.last_root_slot => {
+                let stalled_for_ms = now_ms - last_advance_ms;
+                if !self.stalled
+                    && stalled_for_ms >= self.stall_threshold_ms
+                    && vote_progress.last_vote > self.last_vote_at_root_advance
+                {
+                    self.stalled = true;
+                    return Some(RootAdvanceEvent::Stalled {
+                        root_slot: self.last_root_slot,
+                        last_vote: vote_progress.last_vote,
+                        stalled_for_ms,
+                    });
+                }
+                None
+            }
+            last_advance_ms => {
+                self.last_root_slot = vote_progress.root_slot;
+                self.last_vote_at_root_advance = vote_progress.last_vote;
+                self.last_advance_ms = Some(now_ms);
+                if self.stalled {
+                    self.stalled = false;
+                    return Some(RootAdvanceEvent::Resumed {
+                        root_slot: vote_progress.root_slot,
+                        stalled_for_ms: now_ms - last_advance_ms.unwrap_or(now_ms),
+                    });
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Whether a vote account is among the cluster's vote accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteAccountPresence {
+    Listed,
+    /// The account exists but is no longer among the vote accounts: it was fully unstaked and
+    /// stopped voting
+    Unlisted,
+    /// The account does not exist: it was closed, or the address is mistyped
+    NotFound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteAccountPresenceEvent {
+    Closed,
+    Unstaked,
+    /// The vote account is listed again after an earlier `Closed` or `Unstaked`
+    Reappeared,
+}
+
+impl VoteAccountPresenceEvent {
+    /// Critical unless the vote account is back, the validator can no longer earn credits
+    pub fn severity(&self) -> CheckStatus {
+        match self {
+            VoteAccountPresenceEvent::Closed | VoteAccountPresenceEvent::Unstaked => {
+                CheckStatus::Critical
+            }
+            VoteAccountPresenceEvent::Reappeared => CheckStatus::Ok,
+        }
+    }
+}
+
+impl fmt::Display for VoteAccountPresenceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VoteAccountPresenceEvent::Closed => write!(
+                f,
+                "Vote account not found, it was closed or the address is mistyped"
+            ),
+            VoteAccountPresenceEvent::Unstaked => write!(
+                f,
+                "No longer among the vote accounts, it was fully unstaked and stopped voting"
+            ),
+            VoteAccountPresenceEvent::Reappeared => write!(f, "Back among the vote accounts"),
+        }
+    }
+}
+
+/// Reports when a monitored vote account disappears from the vote accounts, and when it comes
+/// back. The vote account is assumed listed before the first observation, so a mistyped address
+/// is reported on the first one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoteAccountPresenceDetector {
+    presence: VoteAccountPresence,
+}
+
+impl Default for VoteAccountPresenceDetector {
+    fn default() -> Self {
+        Self {
+            presence: VoteAccountPresence::Listed,
+        }
+    }
+}
+
+impl VoteAccountPresenceDetector {
+    pub fn is_missing(&self) -> bool {
+        self.presence != VoteAccountPresence::Listed
+    }
+
+    /// Records `presence`. An event is returned only when the presence changes
+    pub fn observe(&mut self, presence: VoteAccountPresence) -> Option<VoteAccountPresenceEvent> {
+        if presence == self.presence {
+            return None;
+        }
+        self.presence = presence;
+        Some(match presence {
+            VoteAccountPresence::Listed => VoteAccountPresenceEvent::Reappeared,
+            VoteAccountPresence::Unlisted => VoteAccountPresenceEvent::Unstaked,
+            VoteAccountPresence::NotFound => VoteAccountPresenceEvent::Closed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote_progress(slot: Slot, last_vote: Slot, root_slot: Slot) -> VoteProgress {
+        VoteProgress {
+            slot,
+            credits: 0,
+            last_vote: Some(last_vote),
+            root_slot: Some(root_slot),
+        }
+    }
+
+    #[test]
+    fn test_vote_distance() {
+        let vote_progress = vote_progress(1_000, 998, 968);
+        assert_eq!(vote_progress.vote_distance(), Some(2));
+        assert_eq!(vote_progress.root_distance(), Some(32));
+        assert_eq!(VoteProgress::default().vote_distance(), None);
+    }
+
+    #[test]
+    fn test_root_advance() {
+        let mut detector = RootAdvanceDetector::new(30_000);
+        assert_eq!(detector.observe(&vote_progress(100, 99, 68), 0), None);
+        assert_eq!(detector.observe(&vote_progress(150, 149, 68), 29_999), None);
+        assert_eq!(
+            detector.observe(&vote_progress(151, 150, 68), 30_000),
+            Some(RootAdvanceEvent::Stalled {
+                root_slot: Some(68),
+                last_vote: Some(150),
+                stalled_for_ms: 30_000,
+            })
+        );
+        assert!(detector.is_stalled());
+        // Reported once per stall
+        assert_eq!(detector.observe(&vote_progress(200, 199, 68), 60_000), None);
+        assert_eq!(
+            detector.observe(&vote_progress(250, 249, 218), 90_000),
+            Some(RootAdvanceEvent::Resumed {
+                root_slot: Some(218),
+                stalled_for_ms: 90_000,
+            })
+        );
+        assert!(!detector.is_stalled());
+    }
+
+    #[test]
+    fn test_root_advance_without_votes() {
+        // A validator that stopped voting altogether is delinquent, not stuck on its root
+        let mut detector = RootAdvanceDetector::new(30_000);
+        assert_eq!(detector.observe(&vote_progress(100, 99, 68), 0), None);
+        assert_eq!(detector.observe(&vote_progress(200, 99, 68), 60_000), None);
+        assert!(!detector.is_stalled());
+    }
+
+    #[test]
+    fn test_vote_account_presence() {
+        let mut detector = VoteAccountPresenceDetector::default();
+        assert_eq!(detector.observe(VoteAccountPresence::Listed), None);
+        assert_eq!(
+            detector.observe(VoteAccountPresence::Unlisted),
+            Some(VoteAccountPresenceEvent::Unstaked)
+        );
+        assert!(detector.is_missing());
+        assert_eq!(detector.observe(VoteAccountPresence::Unlisted), None);
+        assert_eq!(
+            detector.observe(VoteAccountPresence::NotFound),
+            Some(VoteAccountPresenceEvent::Closed)
+        );
+        assert_eq!(
+            detector.observe(VoteAccountPresence::Listed),
+            Some(VoteAccountPresenceEvent::Reappeared)
+        );
+        assert!(!detector.is_missing());
+
+        // A mistyped address is reported on the first observation
+        assert_eq!(
+            VoteAccountPresenceDetector::default().observe(VoteAccountPresence::NotFound),
+            Some(VoteAccountPresenceEvent::Closed)
+        );
+    }
+}
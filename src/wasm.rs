@@ -0,0 +1,103 @@
+//! Browser entry points, available with the `wasm` feature.
+//!
+//! These operate on a pre-fetched `getVoteAccounts` JSON RPC response so no network access is
+//! required from within the wasm module.
+use {
+    crate::{
+        format::Locale,
+        report::format_validators_by_credit_score,
+        score::{score_validators, MissingCommission, VoteAccountCredits},
+    },
+    serde_json::Value,
+    solana_sdk::pubkey::Pubkey,
+    wasm_bindgen::prelude::*,
+};
+
+fn parse_commission(value: &Value) -> Result<Option<u8>, String> {
+    match value
+        .get("commission")
+        .and_then(|commission| commission.as_u64())
+    {
+        Some(commission) => u8::try_from(commission)
+            .ok()
+            .filter(|commission| *commission <= 100)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid commission: {}", commission)),
+        None => Ok(None),
+    }
+}
+
+fn parse_vote_account(value: &Value, commission: u8) -> Option<VoteAccountCredits> {
+    Some(VoteAccountCredits {
+        vote_pubkey: value.get("votePubkey")?.as_str()?.parse::<Pubkey>().ok()?,
+        commission,
+        activated_stake: value.get("activatedStake")?.as_u64()?,
+        epoch_credits: value
+            .get("epochCredits")?
+            .as_array()?
+            .iter()
+            .filter_map(|ec| {
+                let ec = ec.as_array()?;
+                Some((
+                    ec.first()?.as_u64()?,
+                    ec.get(1)?.as_u64()?,
+                    ec.get(2)?.as_u64()?,
+                ))
+            })
+            .collect(),
+    })
+}
+
+/// Parses the `result` of a `getVoteAccounts` JSON RPC response. The full response object,
+/// including the `jsonrpc` envelope, is also accepted
+pub fn parse_vote_accounts(vote_accounts_json: &str) -> Result<Vec<VoteAccountCredits>, String> {
+    let value = serde_json::from_str::<Value>(vote_accounts_json)
+        .map_err(|err| format!("Invalid JSON: {}", err))?;
+    let value = value.get("result").unwrap_or(&value);
+
+    let mut vote_accounts = vec![];
+    for key in ["current", "delinquent"] {
+        let list = value
+            .get(key)
+            .and_then(|list| list.as_array())
+            .ok_or_else(|| format!("Missing `{}` vote accounts", key))?;
+        for value in list {
+            if let Some(commission) = parse_commission(value)? {
+                vote_accounts.extend(parse_vote_account(value, commission));
+            }
+        }
+    }
+    Ok(vote_accounts)
+}
+
+/// Ranks the validators in a `getVoteAccounts` JSON RPC response by their credit score for
+/// `epoch`, returning the same report produced by the command-line tool.
+///
+/// The commission currently configured in each vote account is used.
+#[wasm_bindgen(js_name = rankValidators)]
+pub fn rank_validators(
+    vote_accounts_json: &str,
+    epoch: u64,
+    ignore_commission: bool,
+    num: Option<usize>,
+    max_percentile: u8,
+) -> Result<String, JsValue> {
+    let vote_accounts = parse_vote_accounts(vote_accounts_json).map_err(JsValue::from)?;
+    let validators_by_staker_credits = score_validators(
+        vote_accounts,
+        epoch,
+        None,
+        ignore_commission,
+        MissingCommission::default(),
+    )
+    .map_err(|err| JsValue::from(err.to_string()))?;
+
+    Ok(format_validators_by_credit_score(
+        &validators_by_staker_credits,
+        num.unwrap_or(usize::MAX),
+        max_percentile,
+        None,
+        None,
+        &Locale::default(),
+    ))
+}
@@ -0,0 +1,166 @@
+//! Pushing of metrics to a Zabbix server or proxy with the Zabbix sender protocol
+use {
+    crate::{error::with_timeout, openmetrics::MetricSet},
+    serde_json::{json, Value},
+    std::time::Duration,
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    },
+};
+
+/// Default port of Zabbix trappers
+pub const DEFAULT_ZABBIX_PORT: u16 = 10051;
+
+/// Longest a whole exchange with the Zabbix server may take, so an unresponsive server can't
+/// hold up the watch loop
+const ZABBIX_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest response accepted from a Zabbix server
+const ZABBIX_MAX_RESPONSE_LEN: u64 = 1 << 20;
+
+const ZABBIX_HEADER: &[u8; 5] = b"ZBXD\x01";
+const ZABBIX_HEADER_LEN: usize = ZABBIX_HEADER.len() + 8;
+
+/// Returns the Zabbix item key of a sample, with label values as key parameters:
+/// `solana_credit_score_vote_credits[<vote pubkey>]`
+pub fn zabbix_key(name: &str, labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    format!(
+        "{}[{}]",
+        name,
+        labels
+            .iter()
+            .map(|(_, value)| format!("\"{}\"", value.replace('"', "\\\"")))
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+/// Returns the `sender data` request for every sample of `metric_set`, reported for the host
+/// named `host` in Zabbix
+pub fn zabbix_sender_request(metric_set: &MetricSet, host: &str) -> Value {
+    json!({
+        "request": "sender data",
+        "data": metric_set.samples().map(|(name, labels, value)| {
+            json!({
+                "host": host,
+                "key": zabbix_key(name, labels),
+                "value": value.to_string(),
+            })
+        }).collect::<Vec<_>>(),
+    })
+}
+
+/// Frames `payload` as a Zabbix protocol message: the `ZBXD\x01` header, the payload length as a
+/// little-endian u64 and the payload
+pub fn zabbix_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(ZABBIX_HEADER_LEN + payload.len());
+    frame.extend_from_slice(ZABBIX_HEADER);
+    frame.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Returns the payload length declared by the header of a Zabbix protocol message
+pub fn zabbix_payload_len(header: &[u8; ZABBIX_HEADER_LEN]) -> Option<u64> {
+    header
+        .starts_with(ZABBIX_HEADER)
+        .then(|| u64::from_le_bytes(header[ZABBIX_HEADER.len()..].try_into().unwrap()))
+}
+
+async fn zabbix_exchange(
+    server: &str,
+    request: &[u8],
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut stream = TcpStream::connect(server).await?;
+    stream.write_all(request).await?;
+
+    let mut header = [0; ZABBIX_HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    let payload_len = zabbix_payload_len(&header)
+        .filter(|payload_len| *payload_len <= ZABBIX_MAX_RESPONSE_LEN)
+        .ok_or_else(|| format!("Invalid response from Zabbix server {}", server))?;
+    let mut payload = vec![0; payload_len as usize];
+    stream.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+/// Sends `metric_set` to the Zabbix trapper at `server` (HOST:PORT) and returns the `info`
+/// the server responds with, such as `processed: 5; failed: 0; total: 5`. The items must exist
+/// as trapper items on `host`, samples without an item are counted as failed by the server
+pub async fn zabbix_send(
+    server: &str,
+    host: &str,
+    metric_set: &MetricSet,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let request = zabbix_frame(
+        zabbix_sender_request(metric_set, host)
+            .to_string()
+            .as_bytes(),
+    );
+    let response = with_timeout(
+        &format!("Sending metrics to Zabbix server {}", server),
+        Some(ZABBIX_TIMEOUT),
+        zabbix_exchange(server, &request),
+    )
+    .await?;
+    let response: Value = serde_json::from_slice(&response)?;
+    if response.get("response").and_then(|r| r.as_str()) != Some("success") {
+        return Err(format!("Zabbix server {} rejected the data: {}", server, response).into());
+    }
+    Ok(response
+        .get("info")
+        .and_then(|info| info.as_str())
+        .unwrap_or_default()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zabbix_key() {
+        assert_eq!(zabbix_key("up", &[]), "up");
+        assert_eq!(
+            zabbix_key(
+                "credits",
+                &[
+                    ("vote_pubkey".to_string(), "Vote111".to_string()),
+                    ("label".to_string(), "a \"b\"".to_string()),
+                ]
+            ),
+            r#"credits["Vote111","a \"b\""]"#
+        );
+    }
+
+    #[test]
+    fn test_zabbix_sender_request() {
+        let mut metric_set = MetricSet::default();
+        metric_set.gauge("up", "Up", &[], 1.);
+        metric_set.gauge("credits", "Credits", &[("vote_pubkey", "Vote111")], 42.5);
+        assert_eq!(
+            zabbix_sender_request(&metric_set, "validator"),
+            json!({
+                "request": "sender data",
+                "data": [
+                    {"host": "validator", "key": "up", "value": "1"},
+                    {"host": "validator", "key": "credits[\"Vote111\"]", "value": "42.5"},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_zabbix_frame() {
+        let frame = zabbix_frame(b"{}");
+        assert_eq!(frame, b"ZBXD\x01\x02\x00\x00\x00\x00\x00\x00\x00{}");
+        assert_eq!(
+            zabbix_payload_len(&frame[..ZABBIX_HEADER_LEN].try_into().unwrap()),
+            Some(2)
+        );
+        assert_eq!(zabbix_payload_len(b"HTTP/1.1 400 "), None);
+    }
+}